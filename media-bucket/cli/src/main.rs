@@ -5,8 +5,10 @@ use std::{path::PathBuf, process::ExitCode};
 
 use clap::{Parser, Subcommand, ValueEnum};
 use libmb::{
-    data_source::DataSourceError, http_server::ConfigError, model::Post, Bucket, BucketError,
-    SyncMatchStategy,
+    data_source::MediaImportError,
+    http_server::ConfigError,
+    model::{chrono::Duration, BucketStats, ContentQuery, Media, Post, ReprocessProgress},
+    Bucket, BucketError, SyncMatchStategy,
 };
 
 use libmb::http_server::{start_server, ServerConfig};
@@ -19,7 +21,12 @@ enum CliError {
     CreateBucketError(BucketError),
 
     OpenError(BucketError),
-    GcError(DataSourceError),
+    GcError(BucketError),
+    StatsError(BucketError),
+    ReindexError(BucketError),
+    RenditionsError(BucketError),
+    ReprocessError(BucketError),
+    SettingsError(BucketError),
     OpenSourceError(BucketError),
     OpenDestError(BucketError),
     SyncBucketError(BucketError),
@@ -54,6 +61,13 @@ impl Debug for CliError {
             CliError::SyncBucketError(err) => write!(f, "Error while syncing: {err:?}"),
             CliError::OpenError(err) => write!(f, "Error while opening bucket: {err:?}"),
             CliError::GcError(err) => write!(f, "Error while running garbage collection: {err:?}"),
+            CliError::StatsError(err) => write!(f, "Error while collecting statistics: {err:?}"),
+            CliError::ReindexError(err) => write!(f, "Error while reindexing: {err:?}"),
+            CliError::RenditionsError(err) => {
+                write!(f, "Error while creating renditions: {err:?}")
+            }
+            CliError::ReprocessError(err) => write!(f, "Error while reprocessing: {err:?}"),
+            CliError::SettingsError(err) => write!(f, "Error while changing settings: {err:?}"),
         }
     }
 }
@@ -91,6 +105,85 @@ enum Commands {
         /// The bucket location.
         #[clap(value_parser, value_name = "LOCAITON")]
         location: String,
+
+        /// Purge posts that have been in the trash for longer than this many seconds.
+        #[clap(value_parser, long, value_name = "SECONDS", default_value = None)]
+        trash_retention: Option<u64>,
+    },
+
+    /// Show storage, post and tag statistics of a bucket.
+    Stats {
+        /// The bucket location.
+        #[clap(value_parser, value_name = "LOCATION")]
+        location: String,
+    },
+
+    /// Extract the searchable text of all documents in a bucket again.
+    Reindex {
+        /// The bucket location.
+        #[clap(value_parser, value_name = "LOCATION")]
+        location: String,
+
+        /// Also recognise the text of images and scanned pdfs with tesseract.
+        #[clap(value_parser, long, default_value_t = false)]
+        ocr: bool,
+    },
+
+    /// Convert all videos in a bucket that browsers cannot play to mp4 with ffmpeg.
+    Renditions {
+        /// The bucket location.
+        #[clap(value_parser, value_name = "LOCATION")]
+        location: String,
+    },
+
+    /// Read the metadata and render the thumbnails of content in a bucket again.
+    Reprocess {
+        /// The bucket location.
+        #[clap(value_parser, value_name = "LOCATION")]
+        location: String,
+
+        /// Only reprocess this content, can be given multiple times.
+        #[clap(value_parser, long = "content-id", value_name = "ID")]
+        content_ids: Vec<u64>,
+
+        /// Only reprocess content of this type, like `video` or `image/png`.
+        #[clap(value_parser, long, default_value = None)]
+        mime: Option<String>,
+
+        /// Resume an interrupted reprocess after the last content id it reported.
+        #[clap(value_parser, long, value_name = "ID", default_value_t = 0)]
+        after: u64,
+    },
+
+    /// Show or change the settings stored in a bucket.
+    Settings {
+        /// The bucket location.
+        #[clap(value_parser, value_name = "LOCATION")]
+        location: String,
+
+        /// Recognise the text of imported images and scanned pdfs with tesseract.
+        #[clap(value_parser, long, default_value = None)]
+        ocr: Option<bool>,
+
+        /// Use the time photos were taken as the creation date of posts imported without one.
+        #[clap(value_parser, long, default_value = None)]
+        use_taken_at: Option<bool>,
+
+        /// Remove the GPS location from imported photos and videos, it is not kept in the index either.
+        #[clap(value_parser, long, default_value = None)]
+        strip_gps: Option<bool>,
+
+        /// Convert uploaded videos that browsers cannot play to mp4 in the background.
+        #[clap(value_parser, long, default_value = None)]
+        renditions: Option<bool>,
+
+        /// Also segment videos at lower bitrates for HLS streaming, not only at the original quality.
+        #[clap(value_parser, long, default_value = None)]
+        hls_ladder: Option<bool>,
+
+        /// The maximum number of bytes of resized images that are kept, the least recently used ones above it are removed.
+        #[clap(value_parser, long, default_value = None)]
+        resize_cache_size: Option<u64>,
     },
 
     /// Move data from one bucket across another in bulk.
@@ -234,31 +327,247 @@ async fn run(cli: Cli) -> Result<(), CliError> {
                 total.fetch_add(1, Ordering::SeqCst);
             };
 
-            dest.sync_from(&src, strategy.into(), remove, &on_sync)
+            let batch_name = Some(format!("mb sync {source}"));
+
+            dest.sync_from(&src, strategy.into(), remove, batch_name, &on_sync)
                 .await
                 .map_err(CliError::SyncBucketError)?;
 
             println!("Synced a total of {} post(s)", total.load(Ordering::SeqCst));
         }
-        Commands::Gc { location } => {
+        Commands::Gc {
+            location,
+            trash_retention,
+        } => {
             let bucket = open_bucket(None, &location, None)
                 .await
                 .map_err(CliError::OpenError)?;
 
             let rows_affected = bucket
-                .data_source()
-                .cross()
-                .gc()
+                .gc(trash_retention.map(|seconds| Duration::seconds(seconds as i64)))
                 .await
                 .map_err(CliError::GcError)?;
 
             println!("{rows_affected} row(s) affected");
         }
+        Commands::Stats { location } => {
+            let bucket = open_bucket(None, &location, None)
+                .await
+                .map_err(CliError::OpenError)?;
+
+            let stats = bucket
+                .data_source()
+                .cross()
+                .get_bucket_stats()
+                .await
+                .map_err(|e| CliError::StatsError(e.into()))?;
+
+            print_stats(&stats);
+        }
+        Commands::Reindex { location, ocr } => {
+            let bucket = open_bucket(None, &location, None)
+                .await
+                .map_err(CliError::OpenError)?;
+
+            let on_error = |media: &Media, err: &MediaImportError| {
+                eprintln!("Cannot index media id {}: {err}", media.id);
+            };
+
+            let indexed = bucket
+                .reindex(ocr, &on_error)
+                .await
+                .map_err(CliError::ReindexError)?;
+
+            println!("Indexed {indexed} file(s)");
+        }
+        Commands::Renditions { location } => {
+            let bucket = open_bucket(None, &location, None)
+                .await
+                .map_err(CliError::OpenError)?;
+
+            let on_error = |media: &Media, err: &MediaImportError| {
+                eprintln!("Cannot convert media id {}: {err}", media.id);
+            };
+
+            let created = bucket
+                .create_renditions(&on_error)
+                .await
+                .map_err(CliError::RenditionsError)?;
+
+            println!("Created {created} rendition(s)");
+        }
+        Commands::Reprocess {
+            location,
+            content_ids,
+            mime,
+            after,
+        } => {
+            let bucket = open_bucket(None, &location, None)
+                .await
+                .map_err(CliError::OpenError)?;
+
+            let query = ContentQuery {
+                content_ids,
+                mime,
+                after,
+            };
+
+            let on_progress = |progress: &ReprocessProgress| {
+                if !progress.finished {
+                    println!(
+                        "{}/{} content id {}",
+                        progress.processed + progress.failed,
+                        progress.total,
+                        progress.last_content_id
+                    );
+                }
+            };
+
+            let on_error = |content_id: u64, err: &MediaImportError| {
+                eprintln!("Cannot reprocess content id {content_id}: {err}");
+            };
+
+            let progress = bucket
+                .reprocess(&query, &on_progress, &on_error)
+                .await
+                .map_err(CliError::ReprocessError)?;
+
+            println!(
+                "Reprocessed {} content, {} failed",
+                progress.processed, progress.failed
+            );
+        }
+        Commands::Settings {
+            location,
+            ocr,
+            use_taken_at,
+            strip_gps,
+            renditions,
+            hls_ladder,
+            resize_cache_size,
+        } => {
+            let bucket = open_bucket(None, &location, None)
+                .await
+                .map_err(CliError::OpenError)?;
+
+            let cross = bucket.data_source().cross();
+            let mut settings = cross
+                .get_settings()
+                .await
+                .map_err(|e| CliError::SettingsError(e.into()))?;
+
+            if ocr.is_some()
+                || use_taken_at.is_some()
+                || strip_gps.is_some()
+                || renditions.is_some()
+                || hls_ladder.is_some()
+                || resize_cache_size.is_some()
+            {
+                settings.ocr = ocr.unwrap_or(settings.ocr);
+                settings.use_taken_at = use_taken_at.unwrap_or(settings.use_taken_at);
+                settings.strip_gps = strip_gps.unwrap_or(settings.strip_gps);
+                settings.renditions = renditions.unwrap_or(settings.renditions);
+                settings.hls_ladder = hls_ladder.unwrap_or(settings.hls_ladder);
+                settings.resize_cache_size =
+                    resize_cache_size.unwrap_or(settings.resize_cache_size);
+
+                cross
+                    .update_settings(&settings)
+                    .await
+                    .map_err(|e| CliError::SettingsError(e.into()))?;
+            }
+
+            println!("ocr: {}", settings.ocr);
+            println!("use_taken_at: {}", settings.use_taken_at);
+            println!("strip_gps: {}", settings.strip_gps);
+            println!("renditions: {}", settings.renditions);
+            println!("hls_ladder: {}", settings.hls_ladder);
+            println!("resize_cache_size: {}", settings.resize_cache_size);
+        }
     }
 
     Ok(())
 }
 
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+fn print_stats(stats: &BucketStats) {
+    println!("Storage");
+    for mime in stats.storage.iter() {
+        println!(
+            "  {}/{}: {} file(s), {}",
+            mime.mime_type,
+            mime.mime_sub_type,
+            mime.file_count,
+            format_size(mime.total_file_size)
+        );
+    }
+    println!(
+        "  Thumbnails: {} file(s), {}",
+        stats.thumbnail_count,
+        format_size(stats.thumbnail_size)
+    );
+    println!(
+        "  Deduplication savings: {}",
+        format_size(stats.deduplication_savings)
+    );
+
+    println!("Posts");
+    println!("  Total: {}", stats.post_count);
+    println!("  In trash: {}", stats.trashed_post_count);
+    println!("  Untagged: {}", stats.untagged_post_count);
+    println!("  Without source: {}", stats.posts_without_source);
+
+    println!("Largest posts");
+    for post in stats.largest_posts.iter() {
+        println!(
+            "  #{} {}: {} item(s), {}",
+            post.post.id,
+            post.post.title.as_deref().unwrap_or("(untitled)"),
+            post.item_count,
+            format_size(post.total_file_size)
+        );
+    }
+
+    println!("Tags");
+    println!("  Total: {}", stats.tag_count);
+    for group in stats.tag_groups.iter() {
+        println!(
+            "  {}: {} tag(s) on {} post(s)",
+            group
+                .group
+                .as_ref()
+                .map(|g| g.name.as_str())
+                .unwrap_or("(no group)"),
+            group.tag_count,
+            group.post_count
+        );
+    }
+
+    println!("Growth");
+    for month in stats.growth.iter() {
+        println!(
+            "  {}: {} post(s), {} item(s), {}",
+            month.month,
+            month.post_count,
+            month.item_count,
+            format_size(month.total_file_size)
+        );
+    }
+}
+
 #[tokio::main]
 async fn main() -> ExitCode {
     let cli: Cli = Cli::parse();
@@ -0,0 +1,33 @@
+CREATE TABLE change_log
+(
+    change_id   INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    entity_type TEXT     NOT NULL,
+    entity_id   INTEGER  NOT NULL,
+    action      TEXT     NOT NULL,
+    previous    TEXT     NULL,
+    current     TEXT     NULL,
+    ip          TEXT     NULL,
+    session     TEXT     NULL,
+    created_at  DATETIME NOT NULL,
+
+    CONSTRAINT allowed_entity_types CHECK (entity_type IN ('post', 'tag')),
+    CONSTRAINT allowed_actions CHECK (action IN ('update', 'delete', 'revert'))
+);
+
+CREATE INDEX change_log_entity_index ON change_log (entity_type, entity_id);
+
+-- the change log is append-only
+
+CREATE TRIGGER change_log_prevent_update
+    BEFORE UPDATE
+    ON change_log
+BEGIN
+    SELECT RAISE(ABORT, 'change_log is append-only');
+END;
+
+CREATE TRIGGER change_log_prevent_delete
+    BEFORE DELETE
+    ON change_log
+BEGIN
+    SELECT RAISE(ABORT, 'change_log is append-only');
+END;
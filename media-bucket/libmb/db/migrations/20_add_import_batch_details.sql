@@ -0,0 +1,19 @@
+ALTER TABLE import_batches
+    ADD COLUMN name TEXT NULL;
+
+ALTER TABLE import_batches
+    ADD COLUMN origin TEXT NOT NULL DEFAULT 'unknown';
+
+ALTER TABLE import_batches
+    ADD COLUMN started_at DATETIME NULL;
+
+ALTER TABLE import_batches
+    ADD COLUMN finished_at DATETIME NULL;
+
+-- best guess for existing batches
+
+UPDATE import_batches
+SET started_at  = (SELECT MIN(p.created_at) FROM posts p WHERE p.import_batch_id = import_batches.import_batch_id),
+    finished_at = (SELECT MAX(p.created_at) FROM posts p WHERE p.import_batch_id = import_batches.import_batch_id);
+
+CREATE INDEX posts_import_batch_id_index ON posts (import_batch_id);
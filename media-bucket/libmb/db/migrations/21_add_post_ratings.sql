@@ -0,0 +1,8 @@
+ALTER TABLE posts
+    ADD COLUMN rating INTEGER NULL CHECK (rating BETWEEN 0 AND 5);
+
+ALTER TABLE posts
+    ADD COLUMN favourite BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX posts_rating_index ON posts (rating);
+CREATE INDEX posts_favourite_index ON posts (favourite);
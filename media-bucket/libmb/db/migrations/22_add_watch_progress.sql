@@ -0,0 +1,8 @@
+-- the playback position of an item in seconds
+ALTER TABLE post_items
+    ADD COLUMN watch_position REAL NULL;
+
+ALTER TABLE post_items
+    ADD COLUMN watch_updated_at DATETIME NULL;
+
+CREATE INDEX post_items_watch_updated_at_index ON post_items (watch_updated_at);
@@ -0,0 +1,2 @@
+-- lets co-occurrence queries go from a tag to its posts without touching the table
+CREATE INDEX tags_posts_tag_id_post_id_index ON tags_posts (tag_id, post_id);
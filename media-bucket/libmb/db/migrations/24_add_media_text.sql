@@ -0,0 +1,297 @@
+CREATE TABLE media_text
+(
+    media_id INTEGER NOT NULL REFERENCES media (media_id) ON DELETE CASCADE,
+    kind     TEXT    NOT NULL,
+    text     TEXT    NOT NULL,
+
+    PRIMARY KEY (media_id, kind)
+);
+
+DROP TRIGGER IF EXISTS posts_vtab_insert;
+DROP TRIGGER IF EXISTS posts_vtab_delete;
+DROP TRIGGER IF EXISTS posts_vtab_update;
+DROP TRIGGER IF EXISTS posts_vtab_tags_update;
+DROP TRIGGER IF EXISTS posts_vtab_tags_posts_update;
+DROP TRIGGER IF EXISTS posts_vtab_tags_posts_insert;
+DROP TRIGGER IF EXISTS posts_vtab_tags_posts_delete;
+DROP TRIGGER IF EXISTS posts_vtab_media_update;
+DROP TRIGGER IF EXISTS posts_vtab_items_update;
+DROP TRIGGER IF EXISTS posts_vtab_items_insert;
+DROP TRIGGER IF EXISTS posts_vtab_items_delete;
+
+DROP TABLE posts_vtab;
+
+CREATE VIRTUAL TABLE posts_vtab USING fts5
+(
+    post_id,
+    source,
+    title,
+    description,
+    import_batch_id,
+    created_at,
+
+    tags,
+
+    original_name,
+    original_directory,
+
+    document_title,
+    document_author,
+
+    content_text,
+
+    tokenize="trigram"
+);
+
+INSERT INTO posts_vtab
+SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+FROM posts p;
+
+-- POST TRIGGERS --
+
+CREATE TRIGGER posts_vtab_insert
+    AFTER INSERT
+    ON posts
+BEGIN
+    INSERT INTO posts_vtab
+    SELECT new.post_id, new.source, new.title, new.description, new.import_batch_id, new.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = new.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = new.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = new.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = new.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = new.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = new.post_id);
+END;
+
+CREATE TRIGGER posts_vtab_delete
+    AFTER DELETE
+    ON posts
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id = old.post_id;
+END;
+
+CREATE TRIGGER posts_vtab_update
+    AFTER UPDATE
+    ON posts
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (old.post_id, new.post_id);
+
+    INSERT INTO posts_vtab
+    SELECT new.post_id, new.source, new.title, new.description, new.import_batch_id, new.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = new.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = new.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = new.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = new.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = new.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = new.post_id);
+END;
+
+-- TAGS TRIGGERS --
+
+CREATE TRIGGER posts_vtab_tags_update
+    AFTER UPDATE
+    ON tags
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (SELECT tp.post_id FROM tags_posts tp WHERE tp.tag_id = old.tag_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (SELECT tp.post_id FROM tags_posts tp WHERE tp.tag_id = old.tag_id);
+END;
+
+CREATE TRIGGER posts_vtab_tags_posts_update
+    AFTER UPDATE
+    ON tags_posts
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (old.post_id, new.post_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (old.post_id, new.post_id);
+END;
+
+CREATE TRIGGER posts_vtab_tags_posts_insert
+    AFTER INSERT
+    ON tags_posts
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (new.post_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (new.post_id);
+END;
+
+CREATE TRIGGER posts_vtab_tags_posts_delete
+    AFTER DELETE
+    ON tags_posts
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (old.post_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (old.post_id);
+END;
+
+-- ITEMS TRIGGERS --
+
+CREATE TRIGGER posts_vtab_media_update
+    AFTER UPDATE
+    ON media
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (SELECT i.post_id FROM post_items i WHERE i.content_id IN (old.media_id, new.media_id));
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (SELECT i.post_id FROM post_items i WHERE i.content_id IN (old.media_id, new.media_id));
+END;
+
+CREATE TRIGGER posts_vtab_items_update
+    AFTER UPDATE
+    ON post_items
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (old.post_id, new.post_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (old.post_id, new.post_id);
+END;
+
+CREATE TRIGGER posts_vtab_items_insert
+    AFTER INSERT
+    ON post_items
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (new.post_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (new.post_id);
+END;
+
+CREATE TRIGGER posts_vtab_items_delete
+    AFTER DELETE
+    ON post_items
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (old.post_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (old.post_id);
+END;
+
+-- MEDIA TEXT TRIGGERS --
+
+CREATE TRIGGER posts_vtab_media_text_insert
+    AFTER INSERT
+    ON media_text
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (SELECT i.post_id FROM post_items i WHERE i.content_id = new.media_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (SELECT i.post_id FROM post_items i WHERE i.content_id = new.media_id);
+END;
+
+CREATE TRIGGER posts_vtab_media_text_update
+    AFTER UPDATE
+    ON media_text
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (SELECT i.post_id FROM post_items i WHERE i.content_id IN (old.media_id, new.media_id));
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (SELECT i.post_id FROM post_items i WHERE i.content_id IN (old.media_id, new.media_id));
+END;
+
+CREATE TRIGGER posts_vtab_media_text_delete
+    AFTER DELETE
+    ON media_text
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (SELECT i.post_id FROM post_items i WHERE i.content_id = old.media_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id)
+    FROM posts p
+    WHERE p.post_id IN (SELECT i.post_id FROM post_items i WHERE i.content_id = old.media_id);
+END;
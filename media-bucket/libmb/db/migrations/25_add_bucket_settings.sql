@@ -0,0 +1,8 @@
+CREATE TABLE bucket_settings
+(
+    settings_id INTEGER PRIMARY KEY CHECK (settings_id = 0),
+    ocr         BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+INSERT INTO bucket_settings(settings_id)
+VALUES (0);
@@ -0,0 +1,20 @@
+CREATE TABLE custom_fields
+(
+    field_id   INTEGER                            NOT NULL PRIMARY KEY AUTOINCREMENT,
+    name       TEXT                               NOT NULL UNIQUE,
+    field_type TEXT                               NOT NULL CHECK (field_type IN ('string', 'number', 'date', 'url')),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+-- the value has no type affinity, numbers are stored as REAL and everything else as TEXT
+-- so that comparisons use the order of the field's type
+CREATE TABLE posts_custom_fields
+(
+    post_id  INTEGER NOT NULL REFERENCES posts (post_id) ON DELETE CASCADE,
+    field_id INTEGER NOT NULL REFERENCES custom_fields (field_id) ON DELETE CASCADE,
+    value             NOT NULL,
+
+    PRIMARY KEY (post_id, field_id)
+);
+
+CREATE INDEX posts_custom_fields_field_value_index ON posts_custom_fields (field_id, value);
@@ -0,0 +1,13 @@
+ALTER TABLE media ADD COLUMN camera_make TEXT;
+ALTER TABLE media ADD COLUMN camera_model TEXT;
+ALTER TABLE media ADD COLUMN lens TEXT;
+ALTER TABLE media ADD COLUMN exposure_time REAL;
+ALTER TABLE media ADD COLUMN f_number REAL;
+ALTER TABLE media ADD COLUMN iso INTEGER;
+ALTER TABLE media ADD COLUMN focal_length REAL;
+ALTER TABLE media ADD COLUMN orientation INTEGER;
+ALTER TABLE media ADD COLUMN taken_at DATETIME;
+
+CREATE INDEX media_taken_at_index ON media (taken_at);
+
+ALTER TABLE bucket_settings ADD COLUMN use_taken_at BOOLEAN NOT NULL DEFAULT FALSE;
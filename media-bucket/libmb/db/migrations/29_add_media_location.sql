@@ -0,0 +1,6 @@
+ALTER TABLE media ADD COLUMN latitude REAL;
+ALTER TABLE media ADD COLUMN longitude REAL;
+
+CREATE INDEX media_location_index ON media (latitude, longitude);
+
+ALTER TABLE bucket_settings ADD COLUMN strip_gps BOOLEAN NOT NULL DEFAULT FALSE;
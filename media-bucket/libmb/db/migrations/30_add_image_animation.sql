@@ -0,0 +1,2 @@
+ALTER TABLE media ADD COLUMN frame_count INTEGER;
+ALTER TABLE media ADD COLUMN loop_duration_ms INTEGER;
@@ -0,0 +1 @@
+ALTER TABLE bucket_settings ADD COLUMN renditions BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,27 @@
+-- videos cut into segments for HLS streaming, the segments themselves are stored as blobs
+CREATE TABLE hls_variants
+(
+    media_id          INTEGER NOT NULL,
+    variant           INTEGER NOT NULL,
+    width             INTEGER NOT NULL,
+    height            INTEGER NOT NULL,
+    bandwidth         INTEGER NOT NULL,
+    average_bandwidth INTEGER NOT NULL,
+
+    PRIMARY KEY (media_id, variant)
+);
+
+-- without a foreign key, so the segments of removed media are kept until their blobs are deleted
+CREATE TABLE hls_segments
+(
+    media_id  INTEGER NOT NULL,
+    variant   INTEGER NOT NULL,
+    position  INTEGER NOT NULL,
+    duration  REAL    NOT NULL,
+    file_id   BLOB    NOT NULL UNIQUE,
+    file_size INTEGER NOT NULL,
+
+    PRIMARY KEY (media_id, variant, position)
+);
+
+ALTER TABLE bucket_settings ADD COLUMN hls_ladder BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,11 @@
+-- sprite sheets of video frames that are shown as previews while scrubbing
+CREATE TABLE storyboards
+(
+    content_id  INTEGER NOT NULL PRIMARY KEY REFERENCES content (content_id),
+    media_id    INTEGER NOT NULL REFERENCES media (media_id),
+    interval    INTEGER NOT NULL,
+    tile_width  INTEGER NOT NULL,
+    tile_height INTEGER NOT NULL,
+    columns     INTEGER NOT NULL,
+    frame_count INTEGER NOT NULL
+);
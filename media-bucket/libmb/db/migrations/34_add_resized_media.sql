@@ -0,0 +1,19 @@
+-- images resized on demand, the files are stored as blobs until gc prunes them
+CREATE TABLE resized_media
+(
+    media_id     INTEGER  NOT NULL,
+    width        INTEGER  NOT NULL,
+    height       INTEGER  NOT NULL,
+    fit          TEXT     NOT NULL,
+    format       TEXT     NOT NULL,
+    file_id      BLOB     NOT NULL UNIQUE,
+    file_size    INTEGER  NOT NULL,
+    last_used_at DATETIME NOT NULL,
+
+    PRIMARY KEY (media_id, width, height, fit, format)
+);
+
+CREATE INDEX resized_media_last_used_at_index ON resized_media (last_used_at);
+
+-- 512 MiB
+ALTER TABLE bucket_settings ADD COLUMN resize_cache_size INTEGER NOT NULL DEFAULT 536870912;
@@ -0,0 +1,25 @@
+-- only rebuild the search row of a post when an item changes a searched column,
+-- saving the watch progress or moving items within a post leaves it untouched
+
+DROP TRIGGER IF EXISTS posts_vtab_items_update;
+
+CREATE TRIGGER posts_vtab_items_update
+    AFTER UPDATE OF post_id, content_id, original_name, original_directory
+    ON post_items
+BEGIN
+    DELETE FROM posts_vtab WHERE post_id IN (old.post_id, new.post_id);
+
+    INSERT INTO posts_vtab
+    SELECT p.post_id, p.source, p.title, p.description, p.import_batch_id, p.created_at,
+           (SELECT GROUP_CONCAT(t.name, ' ') FROM tags_posts tp INNER JOIN tags t ON t.tag_id = tp.tag_id WHERE tp.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(i.original_name, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_name IS NOT NULL),
+           (SELECT GROUP_CONCAT(i.original_directory, ' ') FROM post_items i WHERE i.post_id = p.post_id AND i.original_directory IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_title IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.document_author, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.document_author IS NOT NULL),
+           (SELECT GROUP_CONCAT(mt.text, ' ') FROM post_items i INNER JOIN media_text mt ON mt.media_id = i.content_id WHERE i.post_id = p.post_id),
+           (SELECT GROUP_CONCAT(m.audio_artist, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.audio_artist IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.audio_album, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.audio_album IS NOT NULL),
+           (SELECT GROUP_CONCAT(m.audio_title, ' ') FROM post_items i INNER JOIN media m ON m.media_id = i.content_id WHERE i.post_id = p.post_id AND m.audio_title IS NOT NULL)
+    FROM posts p
+    WHERE p.post_id IN (old.post_id, new.post_id);
+END;
@@ -0,0 +1,5 @@
+-- dates were stored with the offset they were entered with, which does not order them as text
+UPDATE posts_custom_fields
+SET value = strftime('%Y-%m-%dT%H:%M:%SZ', value)
+WHERE field_id IN (SELECT field_id FROM custom_fields WHERE field_type = 'date')
+  AND strftime('%Y-%m-%dT%H:%M:%SZ', value) IS NOT NULL;
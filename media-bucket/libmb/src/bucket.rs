@@ -1,6 +1,7 @@
 #[cfg(feature = "local")]
 use std::path::Path;
 
+use chrono::{Duration, Utc};
 use futures::future::join_all;
 use thiserror::Error;
 use tokio::join;
@@ -9,11 +10,12 @@ use url::Url;
 use crate::{
     data_source::{DataSource, DataSourceError, ImportSource, MediaImportError, PageParams},
     model::{
-        CreateFullPost, CreateFullPostItem, ManyToOne, Post, PostItem, PostSearchQuery, Tag,
-        TagGroup,
+        ChangeOrigin, ContentQuery, CreateFullPost, CreateFullPostItem, CustomField, ManyToOne,
+        Media, MediaTextKind, Post, PostCustomField, PostItem, PostSearchQuery, ReprocessProgress,
+        Tag, TagGroup,
     },
 };
-use crate::model::ImportBatch;
+use crate::model::{ImportBatch, ImportBatchOrigin};
 
 #[derive(Clone, Copy, Debug)]
 pub enum SyncMatchStategy {
@@ -149,6 +151,24 @@ impl Bucket {
         })
     }
 
+    /// Opens an encrypted bucket that imports media with `importers` instead of the built-in importers.
+    #[cfg(feature = "encryption")]
+    pub async fn open_encrypted_with_importers(
+        path: &Path,
+        password: &str,
+        importers: crate::media_import::MediaImporterRegistry,
+    ) -> Result<Self, BucketError> {
+        use crate::local::LocalDataSource;
+
+        let mut data_source = LocalDataSource::open_encrypted(path, password).await?;
+        data_source.set_importers(importers);
+
+        Ok(Self {
+            is_encrypted: true,
+            data_source: Box::new(data_source),
+        })
+    }
+
     #[cfg(feature = "encryption")]
     pub async fn create_encrypted(path: &Path, password: &str) -> Result<Self, BucketError> {
         use crate::local::LocalDataSource;
@@ -168,15 +188,235 @@ impl Bucket {
         self.is_encrypted
     }
 
+    /// Permanently delete a post from the trash, including the blobs that no other post uses.
+    pub async fn purge_post(&self, id: u64, origin: &ChangeOrigin) -> Result<(), BucketError> {
+        let removed = self.data_source.cross().purge_post(id, origin).await?;
+
+        self.delete_blobs(&removed).await?;
+        self.delete_unused_hls_segments().await?;
+        self.prune_resized_media().await?;
+
+        Ok(())
+    }
+
+    /// Run the garbage collection of the data source.
+    /// If `trash_retention` is set, posts that have been in the trash for longer are purged first.
+    ///
+    /// Returns the number of affected rows and deleted blobs.
+    pub async fn gc(&self, trash_retention: Option<Duration>) -> Result<u64, BucketError> {
+        let mut rows_affected = 0;
+
+        if let Some(retention) = trash_retention {
+            let removed = self
+                .data_source
+                .cross()
+                .purge_trash(Utc::now() - retention)
+                .await?;
+
+            self.delete_blobs(&removed).await?;
+
+            rows_affected += removed.len() as u64;
+        }
+
+        rows_affected += self.delete_unused_hls_segments().await?;
+        rows_affected += self.prune_resized_media().await?;
+        rows_affected += self.data_source.cross().gc().await?;
+
+        Ok(rows_affected)
+    }
+
+    /// Extract the searchable text of all media files again, for example to index documents that
+    /// were imported before text extraction was available.
+    /// If `ocr` is set, the text of images and scanned pdfs is recognised as well.
+    /// A file that fails is passed to `on_error` and does not stop the reindex.
+    ///
+    /// Returns the number of indexed files.
+    pub async fn reindex(
+        &self,
+        ocr: bool,
+        on_error: &impl Fn(&Media, &MediaImportError),
+    ) -> Result<u64, BucketError> {
+        let kinds: &[MediaTextKind] = if ocr {
+            &[MediaTextKind::Document, MediaTextKind::Ocr]
+        } else {
+            &[MediaTextKind::Document]
+        };
+
+        let mut indexed = 0;
+        let mut page = PageParams::new(50, 0);
+
+        loop {
+            let results = self.data_source.media().get_page(&page).await?;
+
+            if results.data.is_empty() {
+                break;
+            }
+
+            for media in results.data.iter() {
+                let mut is_indexed = false;
+
+                // the document text is extracted first, so pdfs with a text layer skip ocr
+                for kind in kinds {
+                    match self
+                        .data_source
+                        .media_import()
+                        .extract_text(media, *kind)
+                        .await
+                    {
+                        Ok(extracted) => is_indexed |= extracted,
+                        Err(e) => on_error(media, &e),
+                    }
+                }
+
+                if is_indexed {
+                    indexed += 1;
+                }
+            }
+
+            page = page.next();
+        }
+
+        Ok(indexed)
+    }
+
+    /// Create renditions for all videos that browsers cannot play and do not have one yet.
+    /// A video that fails is passed to `on_error` and does not stop the others.
+    ///
+    /// Returns the number of created renditions.
+    pub async fn create_renditions(
+        &self,
+        on_error: &impl Fn(&Media, &MediaImportError),
+    ) -> Result<u64, BucketError> {
+        let mut created = 0;
+        let mut page = PageParams::new(50, 0);
+
+        loop {
+            let results = self.data_source.media().get_page(&page).await?;
+
+            if results.data.is_empty() {
+                break;
+            }
+
+            for media in results.data.iter() {
+                match self.data_source.media_import().create_rendition(media).await {
+                    Ok(Some(_)) => created += 1,
+                    Ok(None) => {}
+                    Err(e) => on_error(media, &e),
+                }
+            }
+
+            page = page.next();
+        }
+
+        Ok(created)
+    }
+
+    /// Read the metadata and render the thumbnails of the content selected by `query` again.
+    /// Content is processed in the order of its id, so an interrupted reprocess is resumed by
+    /// passing the `last_content_id` of its progress as `after`.
+    /// `on_progress` is called after every content, content that fails is passed to `on_error`
+    /// and does not stop the others.
+    ///
+    /// Returns the progress after all content has been processed.
+    pub async fn reprocess(
+        &self,
+        query: &ContentQuery,
+        on_progress: &impl Fn(&ReprocessProgress),
+        on_error: &impl Fn(u64, &MediaImportError),
+    ) -> Result<ReprocessProgress, BucketError> {
+        let mut query = query.clone();
+        let mut progress = ReprocessProgress {
+            total: self.data_source.content().count(&query).await?,
+            last_content_id: query.after,
+            ..Default::default()
+        };
+
+        loop {
+            let content_ids = self.data_source.content().get_ids(&query, 50).await?;
+
+            let Some(last_content_id) = content_ids.last() else {
+                break;
+            };
+
+            for content_id in content_ids.iter() {
+                match self.data_source.media_import().reprocess(*content_id).await {
+                    Ok(_) => progress.processed += 1,
+                    Err(e) => {
+                        progress.failed += 1;
+                        on_error(*content_id, &e);
+                    }
+                }
+
+                progress.last_content_id = *content_id;
+                on_progress(&progress);
+            }
+
+            query.after = *last_content_id;
+        }
+
+        progress.finished = true;
+        on_progress(&progress);
+
+        Ok(progress)
+    }
+
+    /// Delete the HLS segments of media that no longer exist.
+    ///
+    /// Returns the number of deleted segments.
+    async fn delete_unused_hls_segments(&self) -> Result<u64, BucketError> {
+        let file_ids = self.data_source.media().remove_unused_hls_segments().await?;
+
+        for file_id in file_ids.iter() {
+            match self.data_source.blobs().delete(file_id).await {
+                Ok(()) | Err(DataSourceError::NotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(file_ids.len() as u64)
+    }
+
+    /// Delete the resized images of media that no longer exist, and the least recently used ones
+    /// above the resize cache size of the bucket.
+    ///
+    /// Returns the number of deleted images.
+    async fn prune_resized_media(&self) -> Result<u64, BucketError> {
+        let settings = self.data_source.cross().get_settings().await?;
+        let file_ids = self.data_source.media().remove_resized(settings.resize_cache_size).await?;
+
+        for file_id in file_ids.iter() {
+            match self.data_source.blobs().delete(file_id).await {
+                Ok(()) | Err(DataSourceError::NotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(file_ids.len() as u64)
+    }
+
+    async fn delete_blobs(&self, media: &[Media]) -> Result<(), BucketError> {
+        for media in media {
+            match self.data_source.blobs().delete(&media.file_id).await {
+                Ok(()) | Err(DataSourceError::NotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn sync_from(
         &self,
         source: &Self,
         strat: SyncMatchStategy,
         delete_synced: bool,
+        batch_name: Option<String>,
         on_sync: &impl Fn(&Post),
     ) -> Result<(), BucketError> {
         let mut batch = ImportBatch {
-            id: 0
+            name: batch_name,
+            origin: ImportBatchOrigin::Sync,
+            ..Default::default()
         };
 
         self.data_source.import_batches().add(&mut batch).await?;
@@ -218,7 +458,7 @@ impl Bucket {
             source
                 .data_source()
                 .cross()
-                .cascade_delete_post(post_id)
+                .cascade_delete_post(post_id, &ChangeOrigin::default())
                 .await?;
         }
 
@@ -280,17 +520,77 @@ impl Bucket {
                 items,
                 tag_ids,
                 flatten: false,
-                batch_id: Some(batch.id)
+                batch_id: Some(batch.id),
+                batch_name: None,
+                batch_origin: ImportBatchOrigin::Sync,
+                rating: post.rating,
+                favourite: post.favourite,
             })
             .await?;
 
         if let Some(new_post) = new_post.first() {
+            self.sync_post_custom_fields(source, post.id, new_post.id).await?;
+
             on_sync(new_post)
         }
 
         Ok(())
     }
 
+    async fn sync_post_custom_fields(
+        &self,
+        source: &Self,
+        post_id: u64,
+        new_post_id: u64,
+    ) -> Result<(), BucketError> {
+        let values = source
+            .data_source()
+            .custom_fields()
+            .get_from_post(post_id)
+            .await?;
+
+        for PostCustomField { field, value } in values {
+            let synced_field = self.sync_custom_field(field).await?;
+
+            // a field with the same name but another type cannot hold the value
+            if synced_field.field_type != value.field_type() {
+                continue;
+            }
+
+            self.data_source()
+                .custom_fields()
+                .set_value(
+                    new_post_id,
+                    synced_field.id,
+                    Some(&value),
+                    &ChangeOrigin::default(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn sync_custom_field(&self, field: CustomField) -> Result<CustomField, BucketError> {
+        let fields = self.data_source().custom_fields();
+
+        if let Some(existing_field) = fields.get_by_name(&field.name).await? {
+            return Ok(existing_field);
+        }
+
+        let mut new_field = CustomField { id: 0, ..field };
+
+        match fields.add(&mut new_field).await {
+            Ok(()) => Ok(new_field),
+            // another post that is synced at the same time created it first
+            Err(DataSourceError::Duplicate) => fields
+                .get_by_name(&new_field.name)
+                .await?
+                .ok_or(BucketError::DataSourceError(DataSourceError::NotFound)),
+            Err(e) => Err(e.into()),
+        }
+    }
+
     async fn sync_post_tags(&self, source: &Self, post: &Post) -> Result<Vec<u64>, BucketError> {
         let tags = source
             .data_source()
@@ -366,7 +666,10 @@ impl Bucket {
 
         if synced_tag.group.is_none() && synced_group.is_some() {
             synced_tag.group = synced_group.as_ref().map(|g| ManyToOne::Id(g.id));
-            self.data_source().tags().update(&synced_tag).await?;
+            self.data_source()
+                .tags()
+                .update(&synced_tag, &ChangeOrigin::default())
+                .await?;
         }
 
         Ok(synced_tag.id)
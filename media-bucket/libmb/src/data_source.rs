@@ -1,6 +1,7 @@
 use std::path::Path;
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite};
 use uuid::Uuid;
@@ -20,6 +21,8 @@ pub trait DataSource: Send + Sync {
     fn import_batches(&self) -> &dyn ImportBatchDataSource;
     fn tags(&self) -> &dyn TagDataSource;
     fn tag_groups(&self) -> &dyn TagGroupDataSource;
+    fn changes(&self) -> &dyn ChangeDataSource;
+    fn custom_fields(&self) -> &dyn CustomFieldDataSource;
 
     fn passwords(&self) -> &dyn PasswordDataSource;
     fn media_import(&self) -> &dyn MediaImportDataSource;
@@ -37,6 +40,21 @@ pub enum DataSourceError {
     #[error("model not found")]
     NotFound,
 
+    #[error("A post must contain at least one item")]
+    EmptyPost,
+
+    #[error("A rating must be between 0 and 5")]
+    InvalidRating,
+
+    #[error("A watch position must be a positive number of seconds")]
+    InvalidWatchPosition,
+
+    #[error("A field name may only contain letters, digits, '_' and '-'")]
+    InvalidFieldName,
+
+    #[error("The value does not match the type of the field")]
+    FieldTypeMismatch,
+
     #[error("IO error: {0}")]
     IOError(#[from] std::io::Error),
 
@@ -154,7 +172,16 @@ pub trait BlobDataSource: Send + Sync {
 
 #[async_trait]
 pub trait ImportBatchDataSource: Sync + Send {
+    /// Create a new batch. `value.started_at` defaults to now.
     async fn add(&self, value: &mut ImportBatch) -> Result<(), DataSourceError>;
+
+    /// Rename a batch, all other fields are maintained by the data source.
+    async fn update(&self, value: &ImportBatch) -> Result<(), DataSourceError>;
+    async fn get_by_id(&self, id: u64) -> Result<Option<ImportBatch>, DataSourceError>;
+
+    /// Get the batches with the number of posts they still contain, newest first.
+    async fn get_page(&self, page: &PageParams)
+        -> Result<Page<SearchImportBatch>, DataSourceError>;
 }
 
 #[async_trait]
@@ -166,22 +193,179 @@ pub trait MediaDataSource: Sync + Send {
 
     async fn get_total_size(&self) -> Result<u64, DataSourceError>;
     async fn get_count(&self) -> Result<u64, DataSourceError>;
+
+    /// Get a page of all media ordered by id.
+    async fn get_page(&self, page: &PageParams) -> Result<Page<Media>, DataSourceError>;
+
+    async fn get_text(
+        &self,
+        media_id: u64,
+        kind: MediaTextKind,
+    ) -> Result<Option<String>, DataSourceError>;
+
+    /// Store the searchable text of a media file, replacing the previous text of the same kind.
+    /// Passing `None` removes the text.
+    async fn set_text(
+        &self,
+        media_id: u64,
+        kind: MediaTextKind,
+        text: Option<&str>,
+    ) -> Result<(), DataSourceError>;
+
+    /// Get the variants a video has been segmented into for HLS, the highest quality first.
+    async fn get_hls_variants(&self, media_id: u64) -> Result<Vec<HlsVariant>, DataSourceError>;
+
+    /// Get the segments of a HLS variant in playback order.
+    async fn get_hls_segments(
+        &self,
+        media_id: u64,
+        variant: i32,
+    ) -> Result<Vec<HlsSegment>, DataSourceError>;
+
+    async fn get_hls_segment(
+        &self,
+        media_id: u64,
+        variant: i32,
+        position: i32,
+    ) -> Result<Option<HlsSegment>, DataSourceError>;
+
+    /// Store a HLS variant of a video whose segments have already been written as blobs.
+    async fn add_hls_variant(
+        &self,
+        variant: &HlsVariant,
+        segments: &[HlsSegment],
+    ) -> Result<(), DataSourceError>;
+
+    /// Replace the metadata of a media file, for example after it has been read again.
+    async fn update_metadata(&self, value: &Media) -> Result<(), DataSourceError>;
+
+    /// Remove a media file if no content or storyboard uses it anymore.
+    /// Returns the removed media so that its blob can be deleted.
+    async fn remove_if_unused(&self, id: u64) -> Result<Option<Media>, DataSourceError>;
+
+    /// Remove the HLS segments of media that no longer exist.
+    /// Returns the ids of their blobs so that they can be deleted.
+    async fn remove_unused_hls_segments(&self) -> Result<Vec<Uuid>, DataSourceError>;
+
+    /// Get an image that has been resized like this before and mark it as used,
+    /// the time it was last used is only updated every few minutes.
+    async fn get_resized(
+        &self,
+        media_id: u64,
+        params: &ResizeParams,
+    ) -> Result<Option<ResizedMedia>, DataSourceError>;
+
+    /// Add a resized image whose file has already been written as a blob, and remove the least recently
+    /// used resized images until all of them take up at most the resize cache size of the bucket.
+    /// The added image is kept even if it is larger than the cache.
+    ///
+    /// Returns `None` if the same resized image has been added in the meantime,
+    /// and otherwise the ids of the blobs of the removed images so that they can be deleted.
+    async fn add_resized(
+        &self,
+        resized: &ResizedMedia,
+    ) -> Result<Option<Vec<Uuid>>, DataSourceError>;
+
+    /// Remove the resized images of media that no longer exist, and the least recently used ones
+    /// until the rest takes up at most `max_size` bytes.
+    /// Returns the ids of their blobs so that they can be deleted.
+    async fn remove_resized(&self, max_size: u64) -> Result<Vec<Uuid>, DataSourceError>;
 }
 
 #[async_trait]
 pub trait ContentDataSource: Sync + Send {
     async fn add(&self, value: &mut Content) -> Result<(), DataSourceError>;
     async fn get_by_content_id(&self, id: u64) -> Result<Option<Content>, DataSourceError>;
+
+    /// Count the content selected by the query.
+    async fn count(&self, query: &ContentQuery) -> Result<u64, DataSourceError>;
+
+    /// Get the ids of the first `limit` content selected by the query, in ascending order.
+    async fn get_ids(
+        &self,
+        query: &ContentQuery,
+        limit: usize,
+    ) -> Result<Vec<u64>, DataSourceError>;
     async fn update_thumbnail_id(
         &self,
         new_id: u64,
         content: &mut Content,
     ) -> Result<(), DataSourceError>;
+    /// Link a copy of the content that browsers can play, `None` removes the link.
+    async fn update_rendition_id(
+        &self,
+        new_id: Option<u64>,
+        content: &mut Content,
+    ) -> Result<(), DataSourceError>;
+
+    async fn get_storyboard(&self, content_id: u64) -> Result<Option<Storyboard>, DataSourceError>;
+
+    /// Link a storyboard to the content, replacing the previous one.
+    async fn set_storyboard(
+        &self,
+        content_id: u64,
+        storyboard: &Storyboard,
+    ) -> Result<(), DataSourceError>;
 }
 
 #[async_trait]
 pub trait PostItemDataSource: Sync + Send {
     async fn add(&self, value: &mut PostItem) -> Result<(), DataSourceError>;
+
+    /// Insert an item at `value.position`, shifting all following items one position back.
+    /// A position past the end of the post appends the item and updates `value.position` accordingly.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the post or content cannot be found.
+    async fn insert(
+        &self,
+        value: &mut PostItem,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError>;
+
+    /// Replace the content and upload metadata of the item at `value.position`.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the item or content cannot be found.
+    async fn replace(&self, value: &PostItem, origin: &ChangeOrigin)
+        -> Result<(), DataSourceError>;
+
+    /// Remove the item at `position`, shifting all following items one position forward.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the item cannot be found.
+    /// - `DataSourceError::EmptyPost` => If the item is the last item of the post.
+    async fn remove(
+        &self,
+        post_id: u64,
+        position: i32,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError>;
+
+    /// Move the item at position `from` to position `to`, keeping the order of all other items.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If either position is out of range.
+    async fn reorder(
+        &self,
+        post_id: u64,
+        from: i32,
+        to: i32,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError>;
+
+    /// Remember the playback position in seconds of the item at `position`, `None` forgets it.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the item cannot be found.
+    /// - `DataSourceError::InvalidWatchPosition` => If `watch_position` is negative or not finite.
+    async fn update_progress(
+        &self,
+        post_id: u64,
+        position: i32,
+        watch_position: Option<f64>,
+    ) -> Result<(), DataSourceError>;
+
     async fn get_by_id(
         &self,
         post_item: u64,
@@ -205,8 +389,10 @@ pub trait PostDataSource: Sync + Send {
 #[async_trait]
 pub trait TagDataSource: Sync + Send {
     async fn add(&self, value: &mut Tag) -> Result<(), DataSourceError>;
-    async fn update(&self, value: &Tag) -> Result<(), DataSourceError>;
-    async fn delete(&self, tag_id: u64) -> Result<(), DataSourceError>;
+    /// Update a tag and record the change in the change log.
+    async fn update(&self, value: &Tag, origin: &ChangeOrigin) -> Result<(), DataSourceError>;
+    /// Delete a tag, remove it from all posts and record the deletion in the change log.
+    async fn delete(&self, tag_id: u64, origin: &ChangeOrigin) -> Result<(), DataSourceError>;
     async fn get_by_id(&self, id: u64) -> Result<Option<Tag>, DataSourceError>;
     async fn add_tag_to_post(&self, tag_id: u64, post_id: u64) -> Result<(), DataSourceError>;
     async fn remove_tag_to_post(&self, tag_id: u64, post_id: u64) -> Result<(), DataSourceError>;
@@ -224,6 +410,56 @@ pub trait TagGroupDataSource: Sync + Send {
     ) -> Result<Page<TagGroup>, DataSourceError>;
 }
 
+#[async_trait]
+pub trait CustomFieldDataSource: Sync + Send {
+    /// Declare a new field.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::Duplicate` => If a field with the same name already exists.
+    /// - `DataSourceError::InvalidFieldName` => If the name is not a valid field name.
+    async fn add(&self, value: &mut CustomField) -> Result<(), DataSourceError>;
+    /// Delete a field together with all of its values.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the field cannot be found.
+    async fn delete(&self, field_id: u64) -> Result<(), DataSourceError>;
+    async fn get_by_id(&self, field_id: u64) -> Result<Option<CustomField>, DataSourceError>;
+    async fn get_by_name(&self, name: &str) -> Result<Option<CustomField>, DataSourceError>;
+    async fn get_all(&self) -> Result<Vec<CustomField>, DataSourceError>;
+    async fn get_from_post(&self, post_id: u64) -> Result<Vec<PostCustomField>, DataSourceError>;
+    /// Set the value of a field on a post and record the change in the change log, `None` removes the value.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the post or field cannot be found.
+    /// - `DataSourceError::FieldTypeMismatch` => If the value does not match the type of the field.
+    async fn set_value(
+        &self,
+        post_id: u64,
+        field_id: u64,
+        value: Option<&CustomFieldValue>,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError>;
+}
+
+/// A read-only view of the change log.
+/// Entries are written by the operations that change posts and tags.
+#[async_trait]
+pub trait ChangeDataSource: Sync + Send {
+    /// Get the changes of a post, newest first.
+    async fn get_page_from_post(
+        &self,
+        post_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<Change>, DataSourceError>;
+
+    /// Get the changes of a tag, newest first.
+    async fn get_page_from_tag(
+        &self,
+        tag_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<Change>, DataSourceError>;
+}
+
 #[async_trait]
 pub trait PasswordDataSource: Sync + Send {
     async fn validate_password(
@@ -242,7 +478,7 @@ pub trait FileDataSource: Sync + Send {
 
 #[derive(Error, Debug)]
 pub enum MediaImportError {
-    #[error("missing program")]
+    #[error("missing program \"{name}\"")]
     MissingProgram { name: &'static str },
 
     #[error("unsupported mimetype")]
@@ -270,6 +506,50 @@ pub trait MediaImportDataSource: Sync + Send {
         mime: mediatype::MediaTypeBuf,
         source: ImportSource<'_>,
     ) -> Result<Content, MediaImportError>;
+
+    /// Extract the searchable text of an already imported media file and store it.
+    /// Returns `false` if text of this kind cannot be extracted from the file.
+    async fn extract_text(
+        &self,
+        media: &Media,
+        kind: MediaTextKind,
+    ) -> Result<bool, MediaImportError>;
+
+    /// Convert an already imported video that browsers cannot play, and link the copy to its content.
+    /// Returns `None` if the video can already be played or already has a rendition.
+    /// Only a limited number of videos are converted at once, further calls wait for their turn.
+    async fn create_rendition(&self, media: &Media) -> Result<Option<Media>, MediaImportError>;
+
+    /// Read the metadata and render the thumbnail of already imported content again, for example
+    /// after it was imported without the programs it needs.
+    /// The replaced thumbnail is removed if nothing else uses it.
+    async fn reprocess(&self, content_id: u64) -> Result<Content, MediaImportError>;
+
+    /// Resize an already imported image, or get the cached copy if it has been resized like this
+    /// before.
+    async fn resize_image(
+        &self,
+        media: &Media,
+        params: &ResizeParams,
+    ) -> Result<ResizedMedia, MediaImportError>;
+
+    /// Render the storyboard of an already imported video and link it to its content.
+    /// Returns the existing storyboard if there is one, and `None` if the media is not a video
+    /// or its storyboard is being rendered right now. Rendering waits for the running video conversions.
+    async fn create_storyboard(
+        &self,
+        media: &Media,
+    ) -> Result<Option<Storyboard>, MediaImportError>;
+
+    /// Cut an already imported video into segments for HLS streaming, also at lower bitrates if
+    /// `ladder` is set. Returns the existing variants if the video has been segmented before,
+    /// and no variants if it is not a video or is being segmented right now.
+    /// Segmenting waits for the running video conversions like `create_rendition`.
+    async fn create_hls_stream(
+        &self,
+        media: &Media,
+        ladder: bool,
+    ) -> Result<Vec<HlsVariant>, MediaImportError>;
 }
 
 #[async_trait]
@@ -280,6 +560,12 @@ pub trait CrossDataSource: Sync + Send {
         query: &PostSearchQuery,
         page: &PageParams,
     ) -> Result<Page<SearchPost>, DataSourceError>;
+    /// Cluster the located posts that match the query for a map at this zoom level.
+    async fn get_geo_clusters(
+        &self,
+        query: &PostSearchQuery,
+        zoom: u8,
+    ) -> Result<Vec<GeoCluster>, DataSourceError>;
     async fn search_items(
         &self,
         post_id: u64,
@@ -303,9 +589,99 @@ pub trait CrossDataSource: Sync + Send {
         exact: bool,
     ) -> Result<Page<SearchTag>, DataSourceError>;
 
-    async fn update_full_post(&self, value: &Post, tags: &[u64]) -> Result<(), DataSourceError>;
+    /// Update a post and its tags and record the change in the change log.
+    async fn update_full_post(
+        &self,
+        value: &Post,
+        tags: &[u64],
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError>;
+
+    /// Delete a post with all its items and record the deletion in the change log.
+    async fn cascade_delete_post(
+        &self,
+        id: u64,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError>;
 
-    async fn cascade_delete_post(&self, id: u64) -> Result<(), DataSourceError>;
+    /// Move a post to the trash.
+    /// Trashed posts are hidden from searches and playlists until they are restored or purged.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the post cannot be found or is already in the trash.
+    async fn trash_post(&self, id: u64, origin: &ChangeOrigin) -> Result<(), DataSourceError>;
+
+    /// Move a post out of the trash.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the post cannot be found in the trash.
+    async fn restore_post(&self, id: u64, origin: &ChangeOrigin) -> Result<(), DataSourceError>;
+
+    /// Move every post of an import batch to the trash and return the number of trashed posts.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the batch cannot be found.
+    async fn undo_import_batch(
+        &self,
+        batch_id: u64,
+        origin: &ChangeOrigin,
+    ) -> Result<u64, DataSourceError>;
+
+    /// Permanently delete a post from the trash, including the content no other post references.
+    /// Returns the removed media, whose blobs are left for the caller to delete.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the post cannot be found in the trash.
+    async fn purge_post(
+        &self,
+        id: u64,
+        origin: &ChangeOrigin,
+    ) -> Result<Vec<Media>, DataSourceError>;
+
+    /// Permanently delete all posts that were moved to the trash before `deleted_before`.
+    /// Returns the removed media like `purge_post`.
+    async fn purge_trash(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<Vec<Media>, DataSourceError>;
+
+    /// Restore `fields` of a post to the values they had before change `change_id`.
+    /// Tags that have been deleted since are skipped.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the post or a change of the post with this id cannot be found.
+    async fn revert_post(
+        &self,
+        post_id: u64,
+        change_id: u64,
+        fields: &[PostField],
+        origin: &ChangeOrigin,
+    ) -> Result<Post, DataSourceError>;
+
+    /// Merge multiple posts into the first post of `post_ids`.
+    ///
+    /// The items of every other post are appended in the order of `post_ids`, the tags of all posts are combined,
+    /// and the merged posts are deleted.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If any of the posts cannot be found.
+    async fn merge_posts(
+        &self,
+        post_ids: &[u64],
+        origin: &ChangeOrigin,
+    ) -> Result<Post, DataSourceError>;
+
+    /// Move the items at `positions` out of a post into a new post with the same title, source and tags.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the post or any of the positions cannot be found.
+    /// - `DataSourceError::EmptyPost` => If either post would end up without items.
+    async fn split_post(
+        &self,
+        post_id: u64,
+        positions: &[i32],
+        origin: &ChangeOrigin,
+    ) -> Result<Post, DataSourceError>;
 
     async fn graph_post(&self, query: &PostGraphQuery) -> Result<Graph, DataSourceError>;
 
@@ -313,5 +689,38 @@ pub trait CrossDataSource: Sync + Send {
 
     async fn get_tag_detail(&self, tag_id: u64) -> Result<Option<TagDetail>, DataSourceError>;
 
+    /// Get the tags that are most often used together with the given tag.
+    async fn get_related_tags(
+        &self,
+        tag_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<RelatedTag>, DataSourceError>;
+
+    /// Get the tags that are most often used together with the tags of a post,
+    /// excluding the tags the post already has.
+    async fn get_suggested_tags(
+        &self,
+        post_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<RelatedTag>, DataSourceError>;
+
+    /// Get the posts most similar to a post, ranked by the weighted overlap of their tags where
+    /// rarer tags count more, with a boost for posts from the same source domain or import batch.
+    ///
+    /// ## Errors
+    /// - `DataSourceError::NotFound` => If the post cannot be found.
+    async fn get_similar_posts(
+        &self,
+        post_id: u64,
+        require_playable: bool,
+        page: &PageParams,
+    ) -> Result<Page<SearchPost>, DataSourceError>;
+
+    /// Collect statistics about the storage, posts and tags of the bucket.
+    async fn get_bucket_stats(&self) -> Result<BucketStats, DataSourceError>;
+
+    async fn get_settings(&self) -> Result<BucketSettings, DataSourceError>;
+    async fn update_settings(&self, settings: &BucketSettings) -> Result<(), DataSourceError>;
+
     async fn gc(&self) -> Result<u64, DataSourceError>;
 }
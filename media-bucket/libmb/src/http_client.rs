@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
 use mediatype::MediaTypeBuf;
 use reqwest::{
@@ -178,6 +179,14 @@ impl DataSource for HttpDataSource {
         self
     }
 
+    fn changes(&self) -> &dyn ChangeDataSource {
+        self
+    }
+
+    fn custom_fields(&self) -> &dyn CustomFieldDataSource {
+        self
+    }
+
     fn passwords(&self) -> &dyn PasswordDataSource {
         self
     }
@@ -220,6 +229,14 @@ impl MediaDataSource for HttpDataSource {
         todo!()
     }
 
+    async fn update_metadata(&self, value: &Media) -> Result<(), DataSourceError> {
+        todo!()
+    }
+
+    async fn remove_if_unused(&self, id: u64) -> Result<Option<Media>, DataSourceError> {
+        todo!()
+    }
+
     async fn get_by_id(&self, id: u64) -> Result<Option<Media>, DataSourceError> {
         let res = HttpDataSource::send_resource_request(
             self.client.get(format!("{}/media/{}", self.base, id)),
@@ -240,6 +257,81 @@ impl MediaDataSource for HttpDataSource {
     async fn get_count(&self) -> Result<u64, DataSourceError> {
         todo!()
     }
+
+    async fn get_page(&self, page: &PageParams) -> Result<Page<Media>, DataSourceError> {
+        todo!()
+    }
+
+    async fn get_text(
+        &self,
+        media_id: u64,
+        kind: MediaTextKind,
+    ) -> Result<Option<String>, DataSourceError> {
+        todo!()
+    }
+
+    async fn set_text(
+        &self,
+        media_id: u64,
+        kind: MediaTextKind,
+        text: Option<&str>,
+    ) -> Result<(), DataSourceError> {
+        todo!()
+    }
+
+    async fn get_hls_variants(&self, media_id: u64) -> Result<Vec<HlsVariant>, DataSourceError> {
+        todo!()
+    }
+
+    async fn get_hls_segments(
+        &self,
+        media_id: u64,
+        variant: i32,
+    ) -> Result<Vec<HlsSegment>, DataSourceError> {
+        todo!()
+    }
+
+    async fn get_hls_segment(
+        &self,
+        media_id: u64,
+        variant: i32,
+        position: i32,
+    ) -> Result<Option<HlsSegment>, DataSourceError> {
+        todo!()
+    }
+
+    async fn add_hls_variant(
+        &self,
+        variant: &HlsVariant,
+        segments: &[HlsSegment],
+    ) -> Result<(), DataSourceError> {
+        todo!()
+    }
+
+    async fn remove_unused_hls_segments(&self) -> Result<Vec<Uuid>, DataSourceError> {
+        // the server deletes the segments of the media it purges itself
+        Ok(Vec::new())
+    }
+
+    async fn get_resized(
+        &self,
+        media_id: u64,
+        params: &ResizeParams,
+    ) -> Result<Option<ResizedMedia>, DataSourceError> {
+        todo!()
+    }
+
+    async fn add_resized(
+        &self,
+        resized: &ResizedMedia,
+    ) -> Result<Option<Vec<Uuid>>, DataSourceError> {
+        todo!()
+    }
+
+    async fn remove_resized(&self, max_size: u64) -> Result<Vec<Uuid>, DataSourceError> {
+        // the server prunes its own cache
+        Ok(Vec::new())
+    }
 }
 
 #[async_trait]
@@ -252,6 +344,18 @@ impl ContentDataSource for HttpDataSource {
         todo!()
     }
 
+    async fn count(&self, query: &ContentQuery) -> Result<u64, DataSourceError> {
+        todo!()
+    }
+
+    async fn get_ids(
+        &self,
+        query: &ContentQuery,
+        limit: usize,
+    ) -> Result<Vec<u64>, DataSourceError> {
+        todo!()
+    }
+
     async fn update_thumbnail_id(
         &self,
         new_id: u64,
@@ -259,6 +363,26 @@ impl ContentDataSource for HttpDataSource {
     ) -> Result<(), DataSourceError> {
         todo!()
     }
+
+    async fn update_rendition_id(
+        &self,
+        new_id: Option<u64>,
+        content: &mut Content,
+    ) -> Result<(), DataSourceError> {
+        todo!()
+    }
+
+    async fn get_storyboard(&self, content_id: u64) -> Result<Option<Storyboard>, DataSourceError> {
+        todo!()
+    }
+
+    async fn set_storyboard(
+        &self,
+        content_id: u64,
+        storyboard: &Storyboard,
+    ) -> Result<(), DataSourceError> {
+        todo!()
+    }
 }
 
 #[async_trait]
@@ -267,6 +391,100 @@ impl PostItemDataSource for HttpDataSource {
         todo!()
     }
 
+    async fn insert(
+        &self,
+        value: &mut PostItem,
+        _origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let new_item: PostItem = HttpDataSource::send_request(
+            self.client
+                .post(format!("{}/posts/{}/items", self.base, value.post.id()))
+                .json(&CreatePostItemRequest {
+                    content_id: value.content.id(),
+                    position: Some(value.position),
+                    metadata: value.upload.clone(),
+                }),
+        )
+        .await?;
+
+        value.position = new_item.position;
+
+        Ok(())
+    }
+
+    async fn replace(
+        &self,
+        value: &PostItem,
+        _origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let _: PostItem = HttpDataSource::send_request(
+            self.client
+                .put(format!(
+                    "{}/posts/{}/items/{}",
+                    self.base,
+                    value.post.id(),
+                    value.position
+                ))
+                .json(&UpdatePostItemRequest {
+                    content_id: value.content.id(),
+                    metadata: value.upload.clone(),
+                }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove(
+        &self,
+        post_id: u64,
+        position: i32,
+        _origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        HttpDataSource::send_request(self.client.delete(format!(
+            "{}/posts/{}/items/{}",
+            self.base, post_id, position
+        )))
+        .await
+    }
+
+    async fn reorder(
+        &self,
+        post_id: u64,
+        from: i32,
+        to: i32,
+        _origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        HttpDataSource::send_request(
+            self.client
+                .post(format!(
+                    "{}/posts/{}/items/{}/move",
+                    self.base, post_id, from
+                ))
+                .json(&MovePostItemRequest { position: to }),
+        )
+        .await
+    }
+
+    async fn update_progress(
+        &self,
+        post_id: u64,
+        position: i32,
+        watch_position: Option<f64>,
+    ) -> Result<(), DataSourceError> {
+        HttpDataSource::send_request(
+            self.client
+                .put(format!(
+                    "{}/posts/{}/items/{}/progress",
+                    self.base, post_id, position
+                ))
+                .json(&UpdateWatchProgressRequest {
+                    position: watch_position,
+                }),
+        )
+        .await
+    }
+
     async fn get_by_id(
         &self,
         post_item: u64,
@@ -323,6 +541,42 @@ impl ImportBatchDataSource for HttpDataSource {
     async fn add(&self, value: &mut ImportBatch) -> Result<(), DataSourceError> {
         todo!()
     }
+
+    async fn update(&self, value: &ImportBatch) -> Result<(), DataSourceError> {
+        let res: ImportBatch = HttpDataSource::send_request(
+            self.client
+                .put(format!("{}/import-batches/{}", self.base, value.id))
+                .json(&UpdateImportBatchRequest {
+                    name: value.name.clone(),
+                }),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: u64) -> Result<Option<ImportBatch>, DataSourceError> {
+        HttpDataSource::send_resource_request(
+            self.client
+                .get(format!("{}/import-batches/{}", self.base, id)),
+        )
+        .await
+    }
+
+    async fn get_page(
+        &self,
+        page: &PageParams,
+    ) -> Result<Page<SearchImportBatch>, DataSourceError> {
+        let mut url = format!("{}/import-batches", self.base)
+            .parse::<Url>()
+            .expect("Cannot parse url");
+
+        url.query_pairs_mut()
+            .append_pair("offset", page.offset().to_string().as_str())
+            .append_pair("size", page.page_size().to_string().as_str());
+
+        HttpDataSource::send_request(self.client.get(url)).await
+    }
 }
 
 #[async_trait]
@@ -342,7 +596,7 @@ impl TagDataSource for HttpDataSource {
         Ok(())
     }
 
-    async fn update(&self, value: &Tag) -> Result<(), DataSourceError> {
+    async fn update(&self, value: &Tag, _origin: &ChangeOrigin) -> Result<(), DataSourceError> {
         let res: Tag = HttpDataSource::send_request(
             self.client
                 .put(format!("{}/tags/{}", self.base, value.id))
@@ -356,7 +610,7 @@ impl TagDataSource for HttpDataSource {
         Ok(())
     }
 
-    async fn delete(&self, tag_id: u64) -> Result<(), DataSourceError> {
+    async fn delete(&self, tag_id: u64, _origin: &ChangeOrigin) -> Result<(), DataSourceError> {
         todo!()
     }
 
@@ -420,6 +674,118 @@ impl TagGroupDataSource for HttpDataSource {
     }
 }
 
+#[async_trait]
+impl ChangeDataSource for HttpDataSource {
+    async fn get_page_from_post(
+        &self,
+        post_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<Change>, DataSourceError> {
+        let mut url = format!("{}/posts/{}/history", self.base, post_id)
+            .parse::<Url>()
+            .expect("Cannot parse url");
+
+        url.query_pairs_mut()
+            .append_pair("offset", page.offset().to_string().as_str())
+            .append_pair("size", page.page_size().to_string().as_str());
+
+        HttpDataSource::send_request(self.client.get(url)).await
+    }
+
+    async fn get_page_from_tag(
+        &self,
+        tag_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<Change>, DataSourceError> {
+        let mut url = format!("{}/tags/{}/history", self.base, tag_id)
+            .parse::<Url>()
+            .expect("Cannot parse url");
+
+        url.query_pairs_mut()
+            .append_pair("offset", page.offset().to_string().as_str())
+            .append_pair("size", page.page_size().to_string().as_str());
+
+        HttpDataSource::send_request(self.client.get(url)).await
+    }
+}
+
+#[async_trait]
+impl CustomFieldDataSource for HttpDataSource {
+    async fn add(&self, value: &mut CustomField) -> Result<(), DataSourceError> {
+        let new_field: CustomField =
+            HttpDataSource::send_request(self.client.post(format!("{}/fields", self.base)).json(
+                &CreateCustomFieldRequest {
+                    name: value.name.clone(),
+                    field_type: value.field_type,
+                },
+            ))
+            .await?;
+
+        value.id = new_field.id;
+        value.created_at = new_field.created_at;
+
+        Ok(())
+    }
+
+    async fn delete(&self, field_id: u64) -> Result<(), DataSourceError> {
+        HttpDataSource::send_request(
+            self.client
+                .delete(format!("{}/fields/{}", self.base, field_id)),
+        )
+        .await
+    }
+
+    async fn get_by_id(&self, field_id: u64) -> Result<Option<CustomField>, DataSourceError> {
+        HttpDataSource::send_resource_request(
+            self.client
+                .get(format!("{}/fields/{}", self.base, field_id)),
+        )
+        .await
+    }
+
+    async fn get_by_name(&self, name: &str) -> Result<Option<CustomField>, DataSourceError> {
+        Ok(self
+            .get_all()
+            .await?
+            .into_iter()
+            .find(|field| field.name == name))
+    }
+
+    async fn get_all(&self) -> Result<Vec<CustomField>, DataSourceError> {
+        HttpDataSource::send_request(self.client.get(format!("{}/fields", self.base))).await
+    }
+
+    async fn get_from_post(&self, post_id: u64) -> Result<Vec<PostCustomField>, DataSourceError> {
+        HttpDataSource::send_request(
+            self.client
+                .get(format!("{}/posts/{}/fields", self.base, post_id)),
+        )
+        .await
+    }
+
+    async fn set_value(
+        &self,
+        post_id: u64,
+        field_id: u64,
+        value: Option<&CustomFieldValue>,
+        _origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        HttpDataSource::send_request::<Vec<PostCustomField>>(
+            self.client
+                .put(format!(
+                    "{}/posts/{}/fields/{}",
+                    self.base, post_id, field_id
+                ))
+                .json(&SetCustomFieldValueRequest {
+                    value: value.cloned(),
+                }),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl PasswordDataSource for HttpDataSource {
     async fn validate_password(
@@ -456,6 +822,149 @@ impl MediaImportDataSource for HttpDataSource {
             .await
             .map_err(MediaImportError::DataSourceError)
     }
+
+    async fn extract_text(
+        &self,
+        media: &Media,
+        kind: MediaTextKind,
+    ) -> Result<bool, MediaImportError> {
+        todo!()
+    }
+
+    async fn create_rendition(&self, media: &Media) -> Result<Option<Media>, MediaImportError> {
+        todo!()
+    }
+
+    async fn reprocess(&self, content_id: u64) -> Result<Content, MediaImportError> {
+        todo!()
+    }
+
+    async fn resize_image(
+        &self,
+        media: &Media,
+        params: &ResizeParams,
+    ) -> Result<ResizedMedia, MediaImportError> {
+        todo!()
+    }
+
+    async fn create_storyboard(
+        &self,
+        media: &Media,
+    ) -> Result<Option<Storyboard>, MediaImportError> {
+        todo!()
+    }
+
+    async fn create_hls_stream(
+        &self,
+        media: &Media,
+        ladder: bool,
+    ) -> Result<Vec<HlsVariant>, MediaImportError> {
+        todo!()
+    }
+}
+
+/// Add the parameters of a post search to the query of `url`.
+fn append_search_query(url: &mut Url, query: &PostSearchQuery) {
+    let mut query_pairs = url.query_pairs_mut();
+
+    if let Some(source) = query.source.as_deref() {
+        query_pairs.append_pair("source", source);
+    }
+
+    // field predicates are sent as filters in the text
+    let field_filters = query.fields.iter().map(|predicate| {
+        format!(
+            "field:{}{}{}",
+            predicate.name,
+            predicate.operator.symbol(),
+            predicate.value.as_deref().unwrap_or_default()
+        )
+    });
+
+    let text = query
+        .text
+        .iter()
+        .cloned()
+        .chain(field_filters)
+        .collect::<Vec<_>>();
+
+    if !text.is_empty() {
+        query_pairs.append_pair("text", text.join(" ").as_str());
+    }
+
+    if let Some(min_rating) = query.min_rating {
+        query_pairs.append_pair("min_rating", min_rating.to_string().as_str());
+    }
+
+    if let Some(max_rating) = query.max_rating {
+        query_pairs.append_pair("max_rating", max_rating.to_string().as_str());
+    }
+
+    if let Some(favourite) = query.favourite {
+        query_pairs.append_pair("favourite", favourite.to_string().as_str());
+    }
+
+    if query.in_progress {
+        query_pairs.append_pair("in_progress", "true");
+    }
+
+    if let Some(batch_id) = query.batch_id {
+        query_pairs.append_pair("batch", batch_id.to_string().as_str());
+    }
+
+    if query.highlight {
+        query_pairs.append_pair("highlight", "true");
+    }
+
+    if let Some(camera) = query.camera.as_deref() {
+        query_pairs.append_pair("camera", camera);
+    }
+
+    if let Some(lens) = query.lens.as_deref() {
+        query_pairs.append_pair("lens", lens);
+    }
+
+    if let Some(min_iso) = query.min_iso {
+        query_pairs.append_pair("min_iso", min_iso.to_string().as_str());
+    }
+
+    if let Some(max_iso) = query.max_iso {
+        query_pairs.append_pair("max_iso", max_iso.to_string().as_str());
+    }
+
+    if let Some(taken_after) = query.taken_after {
+        query_pairs.append_pair("taken_after", taken_after.to_rfc3339().as_str());
+    }
+
+    if let Some(taken_before) = query.taken_before {
+        query_pairs.append_pair("taken_before", taken_before.to_rfc3339().as_str());
+    }
+
+    if let Some(order) = query.order.as_ref() {
+        let order_text = match order {
+            PostSearchQueryOrder::Newest => "newest",
+            PostSearchQueryOrder::Oldest => "oldest",
+            PostSearchQueryOrder::Relevant => "relevant",
+            PostSearchQueryOrder::Random(_) => todo!(),
+            PostSearchQueryOrder::Rating => "rating",
+            PostSearchQueryOrder::RecentlyWatched => "recently_watched",
+        };
+
+        query_pairs.append_pair("order", order_text);
+    }
+
+    if let Some(bounds) = query.bounds {
+        let bounds = format!(
+            "{},{},{},{}",
+            bounds.south, bounds.west, bounds.north, bounds.east
+        );
+        query_pairs.append_pair("bounds", bounds.as_str());
+    }
+
+    if let Some(near) = query.near {
+        let near = format!("{},{},{}", near.latitude, near.longitude, near.radius);
+        query_pairs.append_pair("near", near.as_str());
+    }
 }
 
 #[async_trait]
@@ -478,26 +987,26 @@ impl CrossDataSource for HttpDataSource {
 
             query_pairs.append_pair("offset", page.offset().to_string().as_str());
             query_pairs.append_pair("size", page.page_size().to_string().as_str());
+        }
 
-            if let Some(source) = query.source.as_deref() {
-                query_pairs.append_pair("source", source);
-            }
+        append_search_query(&mut url, query);
 
-            if let Some(text) = query.text.as_deref() {
-                query_pairs.append_pair("text", text);
-            }
+        HttpDataSource::send_request(self.client.get(url)).await
+    }
 
-            if let Some(order) = query.order.as_ref() {
-                let order_text = match order {
-                    PostSearchQueryOrder::Newest => "newest",
-                    PostSearchQueryOrder::Oldest => "oldest",
-                    PostSearchQueryOrder::Relevant => "relevant",
-                    PostSearchQueryOrder::Random(_) => todo!(),
-                };
+    async fn get_geo_clusters(
+        &self,
+        query: &PostSearchQuery,
+        zoom: u8,
+    ) -> Result<Vec<GeoCluster>, DataSourceError> {
+        let mut url = format!("{}/posts/geo", self.base)
+            .parse::<Url>()
+            .expect("Cannot parse url");
 
-                query_pairs.append_pair("order", order_text);
-            }
-        }
+        url.query_pairs_mut()
+            .append_pair("zoom", zoom.to_string().as_str());
+
+        append_search_query(&mut url, query);
 
         HttpDataSource::send_request(self.client.get(url)).await
     }
@@ -552,14 +1061,116 @@ impl CrossDataSource for HttpDataSource {
         HttpDataSource::send_request(self.client.get(url)).await
     }
 
-    async fn update_full_post(&self, value: &Post, tags: &[u64]) -> Result<(), DataSourceError> {
+    async fn update_full_post(
+        &self,
+        value: &Post,
+        tags: &[u64],
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
         todo!()
     }
 
-    async fn cascade_delete_post(&self, id: u64) -> Result<(), DataSourceError> {
+    async fn cascade_delete_post(
+        &self,
+        id: u64,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
         todo!()
     }
 
+    async fn trash_post(&self, id: u64, _origin: &ChangeOrigin) -> Result<(), DataSourceError> {
+        HttpDataSource::send_request(self.client.delete(format!("{}/posts/{}", self.base, id)))
+            .await
+    }
+
+    async fn restore_post(&self, id: u64, _origin: &ChangeOrigin) -> Result<(), DataSourceError> {
+        HttpDataSource::send_request(
+            self.client
+                .post(format!("{}/trash/{}/restore", self.base, id)),
+        )
+        .await
+    }
+
+    async fn undo_import_batch(
+        &self,
+        batch_id: u64,
+        _origin: &ChangeOrigin,
+    ) -> Result<u64, DataSourceError> {
+        HttpDataSource::send_request(
+            self.client
+                .post(format!("{}/import-batches/{}/undo", self.base, batch_id)),
+        )
+        .await
+    }
+
+    async fn purge_post(
+        &self,
+        id: u64,
+        _origin: &ChangeOrigin,
+    ) -> Result<Vec<Media>, DataSourceError> {
+        HttpDataSource::send_request::<()>(
+            self.client.delete(format!("{}/trash/{}", self.base, id)),
+        )
+        .await?;
+
+        // the server deletes the blobs of the removed media itself
+        Ok(Vec::new())
+    }
+
+    async fn purge_trash(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<Vec<Media>, DataSourceError> {
+        todo!()
+    }
+
+    async fn revert_post(
+        &self,
+        post_id: u64,
+        change_id: u64,
+        fields: &[PostField],
+        _origin: &ChangeOrigin,
+    ) -> Result<Post, DataSourceError> {
+        HttpDataSource::send_request(
+            self.client
+                .post(format!("{}/posts/{}/revert", self.base, post_id))
+                .json(&RevertPostRequest {
+                    change_id,
+                    fields: fields.to_vec(),
+                }),
+        )
+        .await
+    }
+
+    async fn merge_posts(
+        &self,
+        post_ids: &[u64],
+        _origin: &ChangeOrigin,
+    ) -> Result<Post, DataSourceError> {
+        HttpDataSource::send_request(self.client.post(format!("{}/posts/merge", self.base)).json(
+            &MergePostsRequest {
+                post_ids: post_ids.to_vec(),
+            },
+        ))
+        .await
+    }
+
+    async fn split_post(
+        &self,
+        post_id: u64,
+        positions: &[i32],
+        _origin: &ChangeOrigin,
+    ) -> Result<Post, DataSourceError> {
+        HttpDataSource::send_request(
+            self.client
+                .post(format!("{}/posts/{}/split", self.base, post_id))
+                .json(&SplitPostRequest {
+                    positions: positions.to_vec(),
+                }),
+        )
+        .await
+    }
+
     async fn graph_post(&self, query: &PostGraphQuery) -> Result<Graph, DataSourceError> {
         todo!()
     }
@@ -577,6 +1188,75 @@ impl CrossDataSource for HttpDataSource {
         todo!()
     }
 
+    async fn get_related_tags(
+        &self,
+        tag_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<RelatedTag>, DataSourceError> {
+        let mut url = format!("{}/tags/{}/related", self.base, tag_id)
+            .parse::<Url>()
+            .expect("Cannot parse url");
+
+        url.query_pairs_mut()
+            .append_pair("offset", page.offset().to_string().as_str())
+            .append_pair("size", page.page_size().to_string().as_str());
+
+        HttpDataSource::send_request(self.client.get(url)).await
+    }
+
+    async fn get_suggested_tags(
+        &self,
+        post_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<RelatedTag>, DataSourceError> {
+        let mut url = format!("{}/posts/{}/suggested-tags", self.base, post_id)
+            .parse::<Url>()
+            .expect("Cannot parse url");
+
+        url.query_pairs_mut()
+            .append_pair("offset", page.offset().to_string().as_str())
+            .append_pair("size", page.page_size().to_string().as_str());
+
+        HttpDataSource::send_request(self.client.get(url)).await
+    }
+
+    async fn get_similar_posts(
+        &self,
+        post_id: u64,
+        require_playable: bool,
+        page: &PageParams,
+    ) -> Result<Page<SearchPost>, DataSourceError> {
+        let mut url = format!("{}/posts/{}/similar", self.base, post_id)
+            .parse::<Url>()
+            .expect("Cannot parse url");
+
+        url.query_pairs_mut()
+            .append_pair("offset", page.offset().to_string().as_str())
+            .append_pair("size", page.page_size().to_string().as_str())
+            .append_pair("require_playable", require_playable.to_string().as_str());
+
+        HttpDataSource::send_request(self.client.get(url)).await
+    }
+
+    async fn get_bucket_stats(&self) -> Result<BucketStats, DataSourceError> {
+        HttpDataSource::send_request(self.client.get(format!("{}/stats", self.base))).await
+    }
+
+    async fn get_settings(&self) -> Result<BucketSettings, DataSourceError> {
+        HttpDataSource::send_request(self.client.get(format!("{}/settings", self.base))).await
+    }
+
+    async fn update_settings(&self, settings: &BucketSettings) -> Result<(), DataSourceError> {
+        let _: BucketSettings = HttpDataSource::send_request(
+            self.client
+                .put(format!("{}/settings", self.base))
+                .json(settings),
+        )
+        .await?;
+
+        Ok(())
+    }
+
     async fn gc(&self) -> Result<u64, DataSourceError> {
         let res =
             HttpDataSource::send_request(self.client.post(format!("{}/gc", self.base))).await?;
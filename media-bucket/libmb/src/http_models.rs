@@ -80,6 +80,20 @@ pub struct UpdateTagRequest {
     pub group: Option<u64>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct CreateCustomFieldRequest {
+    pub name: String,
+    pub field_type: CustomFieldType,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct SetCustomFieldValueRequest {
+    /// `None` removes the value from the post.
+    pub value: Option<CustomFieldValue>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
 pub struct UpdatePostRequest {
@@ -87,4 +101,75 @@ pub struct UpdatePostRequest {
     pub description: Option<String>,
     pub source: Option<Url>,
     pub tag_ids: Vec<u64>,
+    /// Left unchanged if absent, use the rating endpoint to remove a rating.
+    #[serde(default)]
+    pub rating: Option<u8>,
+    /// Left unchanged if absent.
+    #[serde(default)]
+    pub favourite: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct CreatePostItemRequest {
+    pub content_id: u64,
+    pub position: Option<i32>,
+    pub metadata: UploadMetadata,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct UpdatePostItemRequest {
+    pub content_id: u64,
+    pub metadata: UploadMetadata,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct MovePostItemRequest {
+    pub position: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct MergePostsRequest {
+    pub post_ids: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct SplitPostRequest {
+    pub positions: Vec<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct RevertPostRequest {
+    pub change_id: u64,
+    pub fields: Vec<PostField>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct UpdateImportBatchRequest {
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct RatePostRequest {
+    pub rating: Option<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct FavouritePostRequest {
+    pub favourite: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct UpdateWatchProgressRequest {
+    /// The playback position in seconds, `None` to forget it.
+    pub position: Option<f64>,
 }
@@ -31,6 +31,7 @@ struct InstanceConfig {
     hidden: bool,
     randomize_secret: bool,
     session_lifetime: Duration,
+    trash_retention: Option<Duration>,
 }
 
 struct StaticFilesConfig {
@@ -92,6 +93,9 @@ impl ServerConfig {
                         .session_lifetime
                         .map(|seconds| Duration::seconds(seconds as i64))
                         .unwrap_or(Duration::days(14)),
+                    trash_retention: instance
+                        .trash_retention
+                        .map(|seconds| Duration::seconds(seconds as i64)),
                 })
                 .collect(),
         })
@@ -141,6 +145,7 @@ impl ServerConfig {
                     instance_config.hidden,
                     instance_config.randomize_secret,
                     instance_config.session_lifetime,
+                    instance_config.trash_retention,
                 )
                 .await?,
             ))
@@ -26,6 +26,8 @@ pub struct InstanceConfigSection {
     pub randomize_secret: bool,
 
     pub session_lifetime: Option<u64>,
+
+    pub trash_retention: Option<u64>,
 }
 
 #[derive(Deserialize)]
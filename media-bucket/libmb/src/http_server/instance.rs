@@ -7,12 +7,14 @@ use std::sync::{Arc, RwLock};
 
 use chrono::{DateTime, Duration, Utc};
 use rand::{thread_rng, Rng};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 use url::Url;
 
 use crate::data_source::DataSourceError;
 use crate::http_server::instance::LoginError::LoadingError;
 use crate::http_server::token::AuthToken;
+use crate::model::{ChangeOrigin, ReprocessProgress};
 use crate::{Bucket, BucketError};
 
 #[derive(Error, Debug)]
@@ -61,6 +63,10 @@ impl Session {
         self.parent.deref()
     }
 
+    pub fn instance_arc(&self) -> Arc<ServerBucketInstance> {
+        self.parent.clone()
+    }
+
     pub fn token(&self) -> Option<&str> {
         self.token.as_deref()
     }
@@ -73,6 +79,18 @@ impl Session {
         self.read_only
     }
 
+    /// Describe this session for the change log.
+    /// The token is hashed so the log can tell sessions apart without storing credentials.
+    pub fn origin(&self) -> ChangeOrigin {
+        ChangeOrigin {
+            ip: Some(self.ip.to_string()),
+            session: self
+                .token
+                .as_ref()
+                .map(|token| hex::encode(&Sha256::digest(token.as_bytes())[..8])),
+        }
+    }
+
     pub fn get_session_count() {}
 }
 
@@ -89,6 +107,19 @@ pub struct ServerBucketInstance {
     randomize_secret: bool,
     last_login: AtomicU64,
     session_lifetime: Duration,
+    trash_retention: Option<Duration>,
+    reprocess_progress: RwLock<Option<ReprocessProgress>>,
+}
+
+/// Marks the reprocess of an instance as finished when it is dropped, even if the task running it panics.
+pub struct ReprocessGuard {
+    instance: Arc<ServerBucketInstance>,
+}
+
+impl Drop for ReprocessGuard {
+    fn drop(&mut self) {
+        self.instance.end_reprocess();
+    }
 }
 
 pub struct NewLogin {
@@ -100,6 +131,7 @@ pub struct NewLogin {
 }
 
 impl ServerBucketInstance {
+    #[allow(clippy::too_many_arguments)]
     pub async fn load(
         id: u64,
         name: String,
@@ -108,6 +140,7 @@ impl ServerBucketInstance {
         hidden: bool,
         randomize_secret: bool,
         session_lifetime: Duration,
+        trash_retention: Option<Duration>,
     ) -> std::io::Result<Self> {
         Ok(ServerBucketInstance {
             id,
@@ -122,6 +155,8 @@ impl ServerBucketInstance {
             randomize_secret,
             last_login: AtomicU64::new(0),
             session_lifetime,
+            trash_retention,
+            reprocess_progress: Default::default(),
         })
     }
 
@@ -129,10 +164,46 @@ impl ServerBucketInstance {
         self.password_protected
     }
 
+    /// How long posts stay in the trash before gc purges them, `None` to keep them forever.
+    pub fn trash_retention(&self) -> Option<Duration> {
+        self.trash_retention
+    }
+
     pub fn hidden(&self) -> bool {
         self.hidden
     }
 
+    /// The progress of the running or last finished reprocess, `None` if none has been started.
+    pub fn reprocess_progress(&self) -> Option<ReprocessProgress> {
+        self.reprocess_progress.read().unwrap().clone()
+    }
+
+    /// Start tracking the progress of a new reprocess, which is marked as finished when the returned
+    /// guard is dropped. Returns `None` if another reprocess is still running.
+    pub fn begin_reprocess(self: Arc<Self>) -> Option<ReprocessGuard> {
+        let mut progress = self.reprocess_progress.write().unwrap();
+
+        if progress.as_ref().is_some_and(|progress| !progress.finished) {
+            return None;
+        }
+
+        *progress = Some(ReprocessProgress::default());
+        drop(progress);
+
+        Some(ReprocessGuard { instance: self })
+    }
+
+    pub fn update_reprocess_progress(&self, progress: ReprocessProgress) {
+        *self.reprocess_progress.write().unwrap() = Some(progress);
+    }
+
+    /// Mark the running reprocess as finished, for example because it stopped with an error.
+    fn end_reprocess(&self) {
+        if let Some(progress) = self.reprocess_progress.write().unwrap().as_mut() {
+            progress.finished = true;
+        }
+    }
+
     pub fn base_url(&self) -> Option<Arc<Url>> {
         self.base_url.clone()
     }
@@ -206,7 +277,7 @@ impl ServerBucketInstance {
             drop(instance);
 
             let bucket = Bucket::open(self.location.as_str(), password).await?;
-            bucket.data_source().cross().gc().await?;
+            bucket.gc(self.trash_retention).await?;
 
             if !self.randomize_secret {
                 token_secret = bucket
@@ -3,10 +3,14 @@ use std::pin::Pin;
 
 use actix_web::dev::Payload;
 use actix_web::{web, FromRequest, HttpRequest};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use serde::Deserialize;
 
 use crate::http_server::web_error::WebError;
-use crate::model::{PostSearchQuery, PostSearchQueryOrder};
+use crate::model::{
+    CustomFieldOperator, CustomFieldPredicate, GeoBounds, GeoRadius, PostSearchQuery,
+    PostSearchQueryOrder,
+};
 
 #[derive(Deserialize)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::IntoParams))]
@@ -17,6 +21,157 @@ struct PostSearchParams {
     source: Option<String>,
     seed: Option<f32>,
     require_playable: Option<bool>,
+    batch: Option<u64>,
+    min_rating: Option<u8>,
+    max_rating: Option<u8>,
+    favourite: Option<bool>,
+    in_progress: Option<bool>,
+    highlight: Option<bool>,
+    camera: Option<String>,
+    lens: Option<String>,
+    min_iso: Option<i32>,
+    max_iso: Option<i32>,
+    taken_after: Option<DateTime<Utc>>,
+    taken_before: Option<DateTime<Utc>>,
+    /// `south,west,north,east` in degrees.
+    bounds: Option<String>,
+    /// `latitude,longitude,radius` in degrees and meters.
+    near: Option<String>,
+}
+
+/// Parses a `rating>=4` style filter into a minimum and maximum rating.
+fn parse_rating_filter(filter: &str) -> Result<(Option<u8>, Option<u8>), WebError> {
+    let parse = |value: &str| value.parse::<u8>().map_err(|_| WebError::ParseError);
+
+    if let Some(value) = filter.strip_prefix(">=") {
+        Ok((Some(parse(value)?), None))
+    } else if let Some(value) = filter.strip_prefix("<=") {
+        Ok((None, Some(parse(value)?)))
+    } else if let Some(value) = filter.strip_prefix('>') {
+        Ok((Some(parse(value)?.saturating_add(1)), None))
+    } else if let Some(value) = filter.strip_prefix('<') {
+        let value = parse(value)?.checked_sub(1).ok_or(WebError::ParseError)?;
+        Ok((None, Some(value)))
+    } else if let Some(value) = filter.strip_prefix('=') {
+        let value = parse(value)?;
+        Ok((Some(value), Some(value)))
+    } else {
+        Err(WebError::ParseError)
+    }
+}
+
+/// Parses a `name>=value` style filter on a custom field, a bare `name` checks that it has a value.
+fn parse_field_filter(filter: &str) -> Result<CustomFieldPredicate, WebError> {
+    let Some(start) = filter.find(['=', '!', '<', '>']) else {
+        return Ok(CustomFieldPredicate {
+            name: filter.to_string(),
+            operator: CustomFieldOperator::Exists,
+            value: None,
+            date_start: None,
+            date_end: None,
+        });
+    };
+
+    let (name, filter) = filter.split_at(start);
+
+    let operator = CustomFieldOperator::ALL
+        .into_iter()
+        .find(|operator| filter.starts_with(operator.symbol()))
+        .ok_or(WebError::ParseError)?;
+
+    if name.is_empty() {
+        return Err(WebError::ParseError);
+    }
+
+    let value = &filter[operator.symbol().len()..];
+
+    Ok(CustomFieldPredicate {
+        name: name.to_string(),
+        operator,
+        value: Some(value.to_string()),
+        date_start: parse_time_bound(value, false).ok(),
+        date_end: parse_time_bound(value, true).ok(),
+    })
+}
+
+/// Strips the name of a comparison filter, words like `isolation` are not filters on `iso`.
+fn strip_filter_prefix<'a>(word: &'a str, name: &str) -> Option<&'a str> {
+    word.strip_prefix(name)
+        .filter(|filter| filter.starts_with(['>', '<', '=']))
+}
+
+/// Splits a `>=value`, `<=value` or `=value` filter into a lower and an upper bound.
+fn parse_bounds_filter(filter: &str) -> Result<(Option<&str>, Option<&str>), WebError> {
+    if let Some(value) = filter.strip_prefix(">=") {
+        Ok((Some(value), None))
+    } else if let Some(value) = filter.strip_prefix("<=") {
+        Ok((None, Some(value)))
+    } else if let Some(value) = filter.strip_prefix('=') {
+        Ok((Some(value), Some(value)))
+    } else {
+        Err(WebError::ParseError)
+    }
+}
+
+/// Parses a `2023-05-01` date or an RFC 3339 time, a date includes the whole day as an upper bound.
+fn parse_time_bound(value: &str, is_upper_bound: bool) -> Result<DateTime<Utc>, WebError> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = if is_upper_bound {
+            NaiveTime::from_hms_nano_opt(23, 59, 59, 999_999_999).ok_or(WebError::ParseError)?
+        } else {
+            NaiveTime::MIN
+        };
+
+        return Ok(date.and_time(time).and_utc());
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| WebError::ParseError)
+}
+
+/// Parses a comma separated list of exactly `N` numbers.
+fn parse_numbers<const N: usize>(value: &str) -> Result<[f64; N], WebError> {
+    let numbers = value
+        .split(',')
+        .map(|number| {
+            number
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| WebError::ParseError)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    numbers.try_into().map_err(|_| WebError::ParseError)
+}
+
+fn parse_bounds(value: &str) -> Result<GeoBounds, WebError> {
+    let [south, west, north, east] = parse_numbers(value)?;
+
+    if south > north || [south, north].iter().any(|latitude| latitude.abs() > 90.0) {
+        return Err(WebError::ParseError);
+    }
+
+    Ok(GeoBounds {
+        south,
+        west,
+        north,
+        east,
+    })
+}
+
+fn parse_near(value: &str) -> Result<GeoRadius, WebError> {
+    let [latitude, longitude, radius] = parse_numbers(value)?;
+
+    if latitude.abs() > 90.0 || radius < 0.0 {
+        return Err(WebError::ParseError);
+    }
+
+    Ok(GeoRadius {
+        latitude,
+        longitude,
+        radius,
+    })
 }
 
 impl FromRequest for PostSearchQuery {
@@ -43,6 +198,74 @@ impl FromRequest for PostSearchQuery {
                 tags = Some(ids);
             }
 
+            let mut batch_id = query.batch;
+            let mut min_rating = query.min_rating;
+            let mut max_rating = query.max_rating;
+            let mut favourite = query.favourite;
+            let mut fields = Vec::new();
+            let mut camera = query.camera.clone();
+            let mut lens = query.lens.clone();
+            let mut min_iso = query.min_iso;
+            let mut max_iso = query.max_iso;
+            let mut taken_after = query.taken_after;
+            let mut taken_before = query.taken_before;
+            let mut text = None;
+
+            // `batch:<id>`, `rating>=<n>`, `favourite:<bool>`, `field:<name>=<value>`,
+            // `camera:<text>`, `lens:<text>`, `iso>=<n>` and `taken>=<date>` in the text are
+            // filters instead of words to search for
+            if let Some(query_text) = query.text.as_deref() {
+                let mut words = Vec::new();
+
+                for word in query_text.split_whitespace() {
+                    if let Some(id) = word.strip_prefix("batch:") {
+                        batch_id = Some(id.parse().map_err(|_| WebError::ParseError)?);
+                    } else if let Some(filter) = strip_filter_prefix(word, "rating") {
+                        let (min, max) = parse_rating_filter(filter)?;
+                        min_rating = min.or(min_rating);
+                        max_rating = max.or(max_rating);
+                    } else if let Some(value) = word.strip_prefix("favourite:") {
+                        favourite = Some(value.parse().map_err(|_| WebError::ParseError)?);
+                    } else if let Some(filter) = word.strip_prefix("field:") {
+                        fields.push(parse_field_filter(filter)?);
+                    } else if let Some(value) = word.strip_prefix("camera:") {
+                        camera = Some(value.to_string());
+                    } else if let Some(value) = word.strip_prefix("lens:") {
+                        lens = Some(value.to_string());
+                    } else if let Some(filter) = strip_filter_prefix(word, "iso") {
+                        let parse = |value: &str| value.parse().map_err(|_| WebError::ParseError);
+                        let (min, max) = parse_bounds_filter(filter)?;
+
+                        if let Some(min) = min {
+                            min_iso = Some(parse(min)?);
+                        }
+
+                        if let Some(max) = max {
+                            max_iso = Some(parse(max)?);
+                        }
+                    } else if let Some(filter) = strip_filter_prefix(word, "taken") {
+                        let (after, before) = parse_bounds_filter(filter)?;
+
+                        if let Some(after) = after {
+                            taken_after = Some(parse_time_bound(after, false)?);
+                        }
+
+                        if let Some(before) = before {
+                            taken_before = Some(parse_time_bound(before, true)?);
+                        }
+                    } else {
+                        words.push(word);
+                    }
+                }
+
+                if !words.is_empty() {
+                    text = Some(words.join(" "));
+                }
+            }
+
+            let bounds = query.bounds.as_deref().map(parse_bounds).transpose()?;
+            let near = query.near.as_deref().map(parse_near).transpose()?;
+
             let seed = query.seed.ok_or(WebError::ParseError);
             let require_playable = query.require_playable.unwrap_or_default();
 
@@ -51,16 +274,277 @@ impl FromRequest for PostSearchQuery {
                 Some("oldest") => Ok(PostSearchQueryOrder::Oldest),
                 Some("random") => Ok(PostSearchQueryOrder::Random(seed?)),
                 Some("relevant") => Ok(PostSearchQueryOrder::Relevant),
+                Some("rating") => Ok(PostSearchQueryOrder::Rating),
+                Some("recently_watched") => Ok(PostSearchQueryOrder::RecentlyWatched),
                 _ => Err(WebError::ParseError),
             }?;
 
             Ok(PostSearchQuery {
                 tags,
-                text: query.text.clone(),
+                text,
                 source: query.source.clone(),
                 order: Some(order),
                 require_playable,
+                trashed: false,
+                batch_id,
+                min_rating,
+                max_rating,
+                favourite,
+                in_progress: query.in_progress.unwrap_or_default(),
+                highlight: query.highlight.unwrap_or_default(),
+                fields,
+                camera,
+                lens,
+                min_iso,
+                max_iso,
+                taken_after,
+                taken_before,
+                bounds,
+                near,
             })
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use chrono::TimeZone;
+
+    use super::*;
+
+    async fn extract(uri: &str) -> Result<PostSearchQuery, WebError> {
+        let req = TestRequest::with_uri(uri).to_http_request();
+
+        PostSearchQuery::extract(&req).await
+    }
+
+    #[test]
+    fn test_parse_rating_filter() {
+        assert_eq!((Some(4), None), parse_rating_filter(">=4").unwrap());
+        assert_eq!((None, Some(2)), parse_rating_filter("<=2").unwrap());
+        assert_eq!((Some(4), None), parse_rating_filter(">3").unwrap());
+        assert_eq!((None, Some(2)), parse_rating_filter("<3").unwrap());
+        assert_eq!((Some(5), Some(5)), parse_rating_filter("=5").unwrap());
+    }
+
+    #[test]
+    fn test_parse_rating_filter_invalid() {
+        assert!(parse_rating_filter("").is_err());
+        assert!(parse_rating_filter("4").is_err());
+        assert!(parse_rating_filter(">=four").is_err());
+        assert!(parse_rating_filter("<0").is_err());
+    }
+
+    #[actix_web::test]
+    async fn rating_filter_in_text() {
+        let query = extract("/?text=cat%20rating%3E%3D4").await.unwrap();
+
+        assert_eq!(Some(4), query.min_rating);
+        assert_eq!(None, query.max_rating);
+        assert_eq!(Some("cat".to_string()), query.text);
+    }
+
+    #[actix_web::test]
+    async fn words_starting_with_rating_are_searched() {
+        let query = extract("/?text=ratings%20ratingless").await.unwrap();
+
+        assert_eq!(None, query.min_rating);
+        assert_eq!(None, query.max_rating);
+        assert_eq!(Some("ratings ratingless".to_string()), query.text);
+    }
+
+    #[actix_web::test]
+    async fn invalid_rating_filter_in_text() {
+        assert!(extract("/?text=rating%3E%3Dhigh").await.is_err());
+    }
+
+    #[test]
+    fn test_parse_field_filter() {
+        let predicate = parse_field_filter("episode>=3").unwrap();
+
+        assert_eq!("episode", predicate.name);
+        assert_eq!(CustomFieldOperator::Ge, predicate.operator);
+        assert_eq!(Some("3".to_string()), predicate.value);
+
+        let predicate = parse_field_filter("status!=done").unwrap();
+
+        assert_eq!("status", predicate.name);
+        assert_eq!(CustomFieldOperator::Ne, predicate.operator);
+        assert_eq!(Some("done".to_string()), predicate.value);
+
+        let predicate = parse_field_filter("episode<3").unwrap();
+
+        assert_eq!(CustomFieldOperator::Lt, predicate.operator);
+        assert_eq!(Some("3".to_string()), predicate.value);
+    }
+
+    #[test]
+    fn field_filter_with_date() {
+        let predicate = parse_field_filter("released=2020-01-01").unwrap();
+
+        assert_eq!(
+            parse_time_bound("2020-01-01", false).ok(),
+            predicate.date_start
+        );
+        assert_eq!(
+            parse_time_bound("2020-01-01", true).ok(),
+            predicate.date_end
+        );
+
+        let predicate = parse_field_filter("released<2020-01-01T12:00:00+02:00").unwrap();
+
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2020, 1, 1, 10, 0, 0).unwrap()),
+            predicate.date_start
+        );
+        assert_eq!(predicate.date_start, predicate.date_end);
+
+        let predicate = parse_field_filter("episode>=3").unwrap();
+
+        assert_eq!(None, predicate.date_start);
+        assert_eq!(None, predicate.date_end);
+    }
+
+    #[test]
+    fn field_filter_without_operator_checks_existence() {
+        let predicate = parse_field_filter("episode").unwrap();
+
+        assert_eq!("episode", predicate.name);
+        assert_eq!(CustomFieldOperator::Exists, predicate.operator);
+        assert_eq!(None, predicate.value);
+    }
+
+    #[test]
+    fn test_parse_field_filter_invalid() {
+        assert!(parse_field_filter("=3").is_err());
+        assert!(parse_field_filter("episode!3").is_err());
+    }
+
+    #[actix_web::test]
+    async fn field_filters_in_text() {
+        let query = extract("/?text=field%3Aepisode%3E%3D3%20field%3Aseason%20cat")
+            .await
+            .unwrap();
+
+        assert_eq!(2, query.fields.len());
+        assert_eq!("episode", query.fields[0].name);
+        assert_eq!("season", query.fields[1].name);
+        assert_eq!(Some("cat".to_string()), query.text);
+    }
+
+    #[test]
+    fn test_strip_filter_prefix() {
+        assert_eq!(Some(">=400"), strip_filter_prefix("iso>=400", "iso"));
+        assert_eq!(
+            Some("=2023-05-01"),
+            strip_filter_prefix("taken=2023-05-01", "taken")
+        );
+        assert_eq!(None, strip_filter_prefix("isolation", "iso"));
+        assert_eq!(None, strip_filter_prefix("iso", "iso"));
+    }
+
+    #[test]
+    fn test_parse_bounds_filter() {
+        assert_eq!((Some("400"), None), parse_bounds_filter(">=400").unwrap());
+        assert_eq!((None, Some("800")), parse_bounds_filter("<=800").unwrap());
+        assert_eq!(
+            (Some("100"), Some("100")),
+            parse_bounds_filter("=100").unwrap()
+        );
+        assert!(parse_bounds_filter(">400").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_bound() {
+        assert_eq!(
+            "2023-05-01T00:00:00Z",
+            parse_time_bound("2023-05-01", false)
+                .unwrap()
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        );
+        assert_eq!(
+            "2023-05-01T23:59:59.999999999Z",
+            parse_time_bound("2023-05-01", true)
+                .unwrap()
+                .to_rfc3339_opts(chrono::SecondsFormat::Nanos, true)
+        );
+        assert_eq!(
+            "2023-05-01T10:30:00Z",
+            parse_time_bound("2023-05-01T12:30:00+02:00", false)
+                .unwrap()
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        );
+        assert!(parse_time_bound("yesterday", false).is_err());
+    }
+
+    #[actix_web::test]
+    async fn iso_and_taken_filters_in_text() {
+        let query = extract("/?text=iso%3E%3D400%20taken%3D2023-05-01%20isolation")
+            .await
+            .unwrap();
+
+        assert_eq!(Some(400), query.min_iso);
+        assert_eq!(None, query.max_iso);
+        assert_eq!(
+            parse_time_bound("2023-05-01", false).ok(),
+            query.taken_after
+        );
+        assert_eq!(
+            parse_time_bound("2023-05-01", true).ok(),
+            query.taken_before
+        );
+        assert_eq!(Some("isolation".to_string()), query.text);
+    }
+
+    #[test]
+    fn test_parse_bounds() {
+        assert_eq!(
+            GeoBounds {
+                south: 47.5,
+                west: 7.0,
+                north: 48.0,
+                east: 8.5,
+            },
+            parse_bounds("47.5, 7,48,8.5").unwrap()
+        );
+
+        // the bounds may cross the antimeridian
+        assert_eq!(170.0, parse_bounds("-10,170,10,-170").unwrap().west);
+    }
+
+    #[test]
+    fn test_parse_bounds_invalid() {
+        assert!(parse_bounds("48,7,47.5,8.5").is_err());
+        assert!(parse_bounds("-91,7,48,8.5").is_err());
+        assert!(parse_bounds("47.5,7,48").is_err());
+        assert!(parse_bounds("47.5,7,48,8.5,9").is_err());
+        assert!(parse_bounds("south,7,48,8.5").is_err());
+    }
+
+    #[test]
+    fn test_parse_near() {
+        assert_eq!(
+            GeoRadius {
+                latitude: 47.37,
+                longitude: 8.54,
+                radius: 500.0,
+            },
+            parse_near("47.37,8.54,500").unwrap()
+        );
+        assert!(parse_near("91,8.54,500").is_err());
+        assert!(parse_near("47.37,8.54,-1").is_err());
+        assert!(parse_near("47.37,8.54").is_err());
+    }
+
+    #[actix_web::test]
+    async fn geo_filters_in_query() {
+        let query = extract("/?bounds=47.5,7,48,8.5&near=47.37,8.54,500")
+            .await
+            .unwrap();
+
+        assert_eq!(Some(48.0), query.bounds.map(|bounds| bounds.north));
+        assert_eq!(Some(500.0), query.near.map(|near| near.radius));
+        assert!(extract("/?near=47.37").await.is_err());
+    }
+}
@@ -8,10 +8,13 @@ use std::sync::Arc;
 
 mod buckets;
 mod content;
+mod fields;
 mod groups;
+mod import_batches;
 mod media;
 mod posts;
 mod tags;
+mod trash;
 
 async fn not_found() -> impl Responder {
     HttpResponse::from_error(WebError::EndpointNotFound)
@@ -68,38 +71,85 @@ pub fn routes() -> Scope {
                 .service(
                     web::scope("/{bucket_id}")
                         .service(buckets::bucket_details)
+                        .service(buckets::bucket_stats)
+                        .service(buckets::settings)
+                        .service(buckets::update_settings)
                         .service(buckets::gc)
+                        .service(buckets::reprocess)
+                        .service(buckets::reprocess_progress)
                         .service(
                             web::scope("/media")
                                 .service(media::file)
+                                .service(media::hls_playlist)
+                                .service(media::hls_segment)
+                                .service(media::storyboard)
                                 .service(media::show),
                         )
                         .service(
                             web::scope("/posts")
                                 .service(posts::graph)
+                                .service(posts::geo)
                                 .service(posts::index)
                                 .service(posts::index_playlist)
                                 .service(posts::store)
+                                .service(posts::merge)
+                                .service(posts::split)
                                 .service(posts::index_items)
+                                .service(posts::store_item)
+                                .service(posts::update_item)
+                                .service(posts::update_item_progress)
+                                .service(posts::delete_item)
+                                .service(posts::move_item)
                                 .service(posts::store_tags)
                                 .service(posts::show_item)
                                 .service(posts::show_item_playlist)
                                 .service(posts::show)
                                 .service(posts::show_playlist)
                                 .service(posts::show_tags)
+                                .service(posts::show_fields)
+                                .service(posts::update_field)
+                                .service(posts::suggested_tags)
+                                .service(posts::similar)
+                                .service(posts::history)
+                                .service(posts::revert)
+                                .service(posts::update_rating)
+                                .service(posts::update_favourite)
                                 .service(posts::delete)
                                 .service(posts::update),
                         )
+                        .service(
+                            web::scope("/import-batches")
+                                .service(import_batches::index)
+                                .service(import_batches::show)
+                                .service(import_batches::update)
+                                .service(import_batches::index_posts)
+                                .service(import_batches::undo),
+                        )
+                        .service(
+                            web::scope("/trash")
+                                .service(trash::index)
+                                .service(trash::restore)
+                                .service(trash::purge),
+                        )
                         .service(
                             web::scope("/tag-groups")
                                 .service(groups::index)
                                 .service(groups::show)
                                 .service(groups::store),
                         )
+                        .service(
+                            web::scope("/fields")
+                                .service(fields::index)
+                                .service(fields::show)
+                                .service(fields::store)
+                                .service(fields::delete),
+                        )
                         .service(
                             web::scope("/tags")
                                 .service(tags::index)
                                 .service(tags::show)
+                                .service(tags::history)
+                                .service(tags::related)
                                 .service(tags::delete)
                                 .service(tags::store)
                                 .service(tags::update),
@@ -124,19 +174,43 @@ fn doc() -> Scope {
     #[openapi(
         paths(
             posts::graph,
+            posts::geo,
             posts::index,
             posts::index,
             posts::store,
+            posts::merge,
+            posts::split,
             posts::index_items,
+            posts::store_item,
+            posts::update_item,
+            posts::update_item_progress,
+            posts::delete_item,
+            posts::move_item,
             posts::store_tags,
             posts::show_item,
             posts::show,
             posts::show_tags,
+            posts::show_fields,
+            posts::update_field,
+            posts::suggested_tags,
+            posts::similar,
+            posts::history,
+            posts::revert,
+            posts::update_rating,
+            posts::update_favourite,
             posts::delete,
             posts::update,
             media::file,
+            media::hls_playlist,
+            media::hls_segment,
+            media::storyboard,
             media::show,
             buckets::bucket_details,
+            buckets::bucket_stats,
+            buckets::settings,
+            buckets::update_settings,
+            buckets::reprocess,
+            buckets::reprocess_progress,
             buckets::index,
             buckets::check_auth,
             buckets::auth,
@@ -144,16 +218,34 @@ fn doc() -> Scope {
             groups::index,
             groups::show,
             groups::store,
+            fields::index,
+            fields::show,
+            fields::store,
+            fields::delete,
             tags::index,
             tags::show,
+            tags::history,
+            tags::related,
             tags::delete,
             tags::store,
             tags::update,
+            import_batches::index,
+            import_batches::show,
+            import_batches::update,
+            import_batches::index_posts,
+            import_batches::undo,
+            trash::index,
+            trash::restore,
+            trash::purge,
             content::store,
         ),
         components(schemas(
             crate::model::ImportBatch,
+            crate::model::ImportBatchOrigin,
+            crate::model::SearchImportBatch,
             crate::model::Dimensions,
+            crate::model::ExifMetadata,
+            crate::model::Animation,
             crate::model::MediaMetadata,
             crate::model::Media,
             crate::model::Content,
@@ -163,21 +255,51 @@ fn doc() -> Scope {
             crate::model::TagGroup,
             crate::model::Tag,
             crate::model::SearchTag,
+            crate::model::RelatedTag,
             crate::model::TagDetail,
+            crate::model::CustomFieldType,
+            crate::model::CustomField,
+            crate::model::CustomFieldValue,
+            crate::model::PostCustomField,
+            crate::model::CustomFieldOperator,
+            crate::model::CustomFieldPredicate,
             crate::model::PostDetail,
             crate::model::SearchPost,
+            crate::model::SearchHighlight,
+            crate::model::PostSearchColumn,
             crate::model::SearchPostItem,
+            crate::model::WatchProgress,
             crate::model::CreateFullPostItem,
             crate::model::CreateFullPost,
             crate::model::PostSearchQueryOrder,
             crate::model::PostSearchQuery,
+            crate::model::GeoBounds,
+            crate::model::GeoRadius,
+            crate::model::GeoCluster,
             crate::model::GraphValue,
             crate::model::GraphPoint,
+            crate::model::GraphSeries,
             crate::model::Graph,
             crate::model::GraphDiscriminator,
             crate::model::GraphSelect,
             crate::model::PostGraphQuery,
             crate::model::BucketDetails,
+            crate::model::BucketStats,
+            crate::model::BucketSettings,
+            crate::model::ContentQuery,
+            crate::model::ReprocessProgress,
+            crate::model::MimeTypeStats,
+            crate::model::PostStats,
+            crate::model::TagGroupStats,
+            crate::model::MonthlyGrowth,
+            crate::model::ChangeEntity,
+            crate::model::ChangeAction,
+            crate::model::PostSnapshot,
+            crate::model::TagSnapshot,
+            crate::model::ChangeSnapshot,
+            crate::model::ChangeOrigin,
+            crate::model::Change,
+            crate::model::PostField,
             crate::http_models::BucketInfo,
             crate::http_models::AuthRequest,
             crate::http_models::AuthResponse,
@@ -186,8 +308,20 @@ fn doc() -> Scope {
             crate::http_models::CreateTagGroupRequest,
             crate::http_models::CreateTagRequest,
             crate::http_models::UpdateTagRequest,
+            crate::http_models::CreateCustomFieldRequest,
+            crate::http_models::SetCustomFieldValueRequest,
             crate::http_models::UpdatePostRequest,
             crate::http_models::UpdatePostRequest,
+            crate::http_models::CreatePostItemRequest,
+            crate::http_models::UpdatePostItemRequest,
+            crate::http_models::MovePostItemRequest,
+            crate::http_models::MergePostsRequest,
+            crate::http_models::SplitPostRequest,
+            crate::http_models::RevertPostRequest,
+            crate::http_models::UpdateImportBatchRequest,
+            crate::http_models::RatePostRequest,
+            crate::http_models::FavouritePostRequest,
+            crate::http_models::UpdateWatchProgressRequest,
         ))
     )]
     struct ApiDoc;
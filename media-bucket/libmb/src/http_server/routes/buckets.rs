@@ -4,13 +4,14 @@ use std::time::Duration;
 
 use crate::http_models::{AuthRequest, AuthResponse, BucketInfo};
 use actix_web::web::Data;
-use actix_web::{get, post, web, HttpRequest, Responder};
-use log::info;
+use actix_web::{get, post, put, rt, web, HttpRequest, Responder};
+use log::{error, info};
 use tokio::time::sleep;
 
+use crate::data_source::MediaImportError;
 use crate::http_server::instance::{InstanceDataSource, ServerBucketInstance, Session};
 use crate::http_server::web_error::WebError;
-use crate::model::BucketDetails;
+use crate::model::{BucketDetails, BucketSettings, ContentQuery, ReprocessProgress};
 
 impl From<&ServerBucketInstance> for BucketInfo {
     fn from(value: &ServerBucketInstance) -> Self {
@@ -51,12 +52,67 @@ pub async fn show(
 #[post("/gc")]
 pub async fn gc(session: Session) -> Result<impl Responder, WebError> {
     info!("Running manual gc");
-    let rows_affected = session.bucket().data_source().cross().gc().await?;
+    let rows_affected = session
+        .bucket()
+        .gc(session.instance().trash_retention())
+        .await?;
     info!("Gc affected {rows_affected} row(s)");
 
     Ok(web::Json(rows_affected))
 }
 
+/// Start reading the metadata and rendering the thumbnails of the selected content again in the
+/// background, its progress is shown by `reprocess_progress`.
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[post("/reprocess")]
+pub async fn reprocess(
+    session: Session,
+    req_body: web::Json<ContentQuery>,
+) -> Result<impl Responder, WebError> {
+    let instance = session.instance_arc();
+
+    let Some(guard) = instance.clone().begin_reprocess() else {
+        return Err(WebError::Duplicate);
+    };
+
+    let bucket = session.bucket_arc();
+    let query = req_body.into_inner();
+
+    info!("Reprocessing content after id {}", query.after);
+
+    rt::spawn(async move {
+        let _guard = guard;
+
+        let on_progress =
+            |progress: &ReprocessProgress| instance.update_reprocess_progress(progress.clone());
+
+        let on_error = |content_id: u64, e: &MediaImportError| {
+            error!("Failed to reprocess content {content_id}: {e}")
+        };
+
+        match bucket.reprocess(&query, &on_progress, &on_error).await {
+            Ok(progress) => info!(
+                "Reprocessed {} content, {} failed",
+                progress.processed, progress.failed
+            ),
+            Err(e) => error!("Reprocess stopped: {e}"),
+        }
+    });
+
+    Ok(web::Json(session.instance().reprocess_progress()))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/reprocess")]
+pub async fn reprocess_progress(session: Session) -> Result<impl Responder, WebError> {
+    let progress = session
+        .instance()
+        .reprocess_progress()
+        .ok_or(WebError::ResourceNotFound)?;
+
+    Ok(web::Json(progress))
+}
+
 #[cfg_attr(feature = "http-server-spec", utoipa::path)]
 #[get("/details")]
 pub async fn bucket_details(session: Session) -> Result<impl Responder, WebError> {
@@ -76,6 +132,48 @@ pub async fn bucket_details(session: Session) -> Result<impl Responder, WebError
     }))
 }
 
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/stats")]
+pub async fn bucket_stats(session: Session) -> Result<impl Responder, WebError> {
+    let stats = session
+        .bucket()
+        .data_source()
+        .cross()
+        .get_bucket_stats()
+        .await?;
+
+    Ok(web::Json(stats))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/settings")]
+pub async fn settings(session: Session) -> Result<impl Responder, WebError> {
+    let settings = session
+        .bucket()
+        .data_source()
+        .cross()
+        .get_settings()
+        .await?;
+
+    Ok(web::Json(settings))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[put("/settings")]
+pub async fn update_settings(
+    session: Session,
+    req_body: web::Json<BucketSettings>,
+) -> Result<impl Responder, WebError> {
+    session
+        .bucket()
+        .data_source()
+        .cross()
+        .update_settings(&req_body)
+        .await?;
+
+    Ok(web::Json(req_body.into_inner()))
+}
+
 #[cfg_attr(feature = "http-server-spec", utoipa::path)]
 #[post("/{id}/auth")]
 pub async fn auth(
@@ -1,7 +1,11 @@
-use crate::{data_source::ImportSource, http_server::web_error::WebError, media_import::TmpFile};
-use actix_web::{post, web, HttpMessage, HttpRequest, Responder};
+use crate::{
+    data_source::ImportSource,
+    http_server::web_error::WebError,
+    media_import::{rendition_method, TmpFile},
+};
+use actix_web::{post, rt, web, HttpMessage, HttpRequest, Responder};
 use futures::StreamExt;
-use log::info;
+use log::{error, info};
 use mediatype::MediaTypeBuf;
 use tokio::fs::OpenOptions;
 use tokio::io::AsyncWriteExt;
@@ -50,5 +54,34 @@ pub async fn store(
 
     info!("Uploaded content {}", content.content.id());
 
+    let settings = session
+        .bucket()
+        .data_source()
+        .cross()
+        .get_settings()
+        .await?;
+
+    if let Some(media) = content.content.as_ref().obj() {
+        if settings.renditions && rendition_method(media).is_some() {
+            let bucket = session.bucket_arc();
+            let media = media.clone();
+
+            rt::spawn(async move {
+                match bucket
+                    .data_source()
+                    .media_import()
+                    .create_rendition(&media)
+                    .await
+                {
+                    Ok(Some(rendition)) => {
+                        info!("Created rendition {} of media {}", rendition.id, media.id)
+                    }
+                    Ok(None) => {}
+                    Err(e) => error!("Failed to create rendition of media {}: {}", media.id, e),
+                }
+            });
+        }
+    }
+
     Ok(web::Json(content))
 }
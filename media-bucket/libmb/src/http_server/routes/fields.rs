@@ -0,0 +1,81 @@
+use crate::http_models::CreateCustomFieldRequest;
+use crate::http_server::instance::Session;
+use crate::http_server::web_error::WebError;
+use crate::model::CustomField;
+use actix_web::{delete, get, post, web, Responder};
+use chrono::Utc;
+use log::info;
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("")]
+pub async fn index(session: Session) -> Result<impl Responder, WebError> {
+    let fields = session
+        .bucket()
+        .data_source()
+        .custom_fields()
+        .get_all()
+        .await?;
+
+    Ok(web::Json(fields))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}")]
+pub async fn show(session: Session, id: web::Path<(u64, u64)>) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    let field = session
+        .bucket()
+        .data_source()
+        .custom_fields()
+        .get_by_id(id)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    Ok(web::Json(field))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[post("")]
+pub async fn store(
+    session: Session,
+    req: web::Json<CreateCustomFieldRequest>,
+) -> Result<impl Responder, WebError> {
+    let mut field = CustomField {
+        id: 0,
+        name: req.name.clone(),
+        field_type: req.field_type,
+        created_at: Utc::now(),
+    };
+
+    session
+        .bucket()
+        .data_source()
+        .custom_fields()
+        .add(&mut field)
+        .await?;
+
+    info!("Created custom field {}", field.id);
+
+    Ok(web::Json(field))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[delete("/{id}")]
+pub async fn delete(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    session
+        .bucket()
+        .data_source()
+        .custom_fields()
+        .delete(id)
+        .await?;
+
+    info!("Deleted custom field {id}");
+
+    Ok(web::Json(()))
+}
@@ -0,0 +1,104 @@
+use actix_web::{get, post, put, web, Responder};
+use log::info;
+
+use crate::data_source::PageParams;
+use crate::http_models::UpdateImportBatchRequest;
+use crate::http_server::instance::Session;
+use crate::http_server::web_error::WebError;
+use crate::model::PostSearchQuery;
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("")]
+pub async fn index(session: Session, page: PageParams) -> Result<impl Responder, WebError> {
+    let batches = session
+        .bucket()
+        .data_source()
+        .import_batches()
+        .get_page(&page)
+        .await?;
+
+    Ok(web::Json(batches))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}")]
+pub async fn show(session: Session, id: web::Path<(u64, u64)>) -> Result<impl Responder, WebError> {
+    let batch = session
+        .bucket()
+        .data_source()
+        .import_batches()
+        .get_by_id(id.into_inner().1)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    Ok(web::Json(batch))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[put("/{id}")]
+pub async fn update(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    req: web::Json<UpdateImportBatchRequest>,
+) -> Result<impl Responder, WebError> {
+    let mut batch = session
+        .bucket()
+        .data_source()
+        .import_batches()
+        .get_by_id(id.into_inner().1)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    batch.name = req.name.clone();
+
+    session
+        .bucket()
+        .data_source()
+        .import_batches()
+        .update(&batch)
+        .await?;
+
+    info!("Updated import batch {}", batch.id);
+
+    Ok(web::Json(batch))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/posts")]
+pub async fn index_posts(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    query: PostSearchQuery,
+    page: PageParams,
+) -> Result<impl Responder, WebError> {
+    let query = PostSearchQuery {
+        batch_id: Some(id.into_inner().1),
+        ..query
+    };
+
+    let posts = session
+        .bucket()
+        .data_source()
+        .cross()
+        .search_posts(&query, &page)
+        .await?;
+
+    Ok(web::Json(posts))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[post("/{id}/undo")]
+pub async fn undo(session: Session, id: web::Path<(u64, u64)>) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    let trashed = session
+        .bucket()
+        .data_source()
+        .cross()
+        .undo_import_batch(id, &session.origin())
+        .await?;
+
+    info!("Undid import batch {id}, moved {trashed} post(s) to the trash");
+
+    Ok(web::Json(trashed))
+}
@@ -1,11 +1,18 @@
 use actix_web::body::SizedStream;
 use actix_web::http::{header, StatusCode};
-use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{get, rt, web, HttpRequest, HttpResponse, Responder};
 use http_range::HttpRange;
+use log::{error, info};
+use mediatype::{MediaType, MediaTypeList};
+use serde::Deserialize;
 
 use crate::http_server::instance::Session;
 use crate::http_server::stream_file::new_chunked_read;
+use crate::http_server::stream_playlist::{
+    new_hls_media_playlist, new_storyboard_track, HLS_CONTENT_TYPE,
+};
 use crate::http_server::web_error::WebError;
+use crate::model::{Media, ResizeFit, ResizeFormat, ResizeParams};
 
 #[cfg_attr(feature = "http-server-spec", utoipa::path)]
 #[get("/{id}")]
@@ -23,16 +30,99 @@ pub async fn show(session: Session, id: web::Path<(u64, u64)>) -> Result<impl Re
     Ok(web::Json(media))
 }
 
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rendition {
+    /// The file as it was uploaded.
+    Original,
+
+    /// The browser compatible rendition, or the original if there is none.
+    Compatible,
+}
+
+#[derive(Deserialize)]
+pub struct FileParams {
+    rendition: Option<Rendition>,
+
+    /// Resize the image to at most this width.
+    w: Option<u32>,
+
+    /// Resize the image to at most this height.
+    h: Option<u32>,
+
+    fit: Option<ResizeFit>,
+
+    /// Convert the image to this format, by default webp if the client accepts it and jpeg otherwise.
+    format: Option<ResizeFormat>,
+}
+
+impl FileParams {
+    /// The size and format the image is resized to, `None` if the file is served as it is.
+    fn resize_params(&self, req: &HttpRequest) -> Option<ResizeParams> {
+        if self.w.is_none() && self.h.is_none() && self.format.is_none() {
+            return None;
+        }
+
+        let format = self.format.unwrap_or_else(|| {
+            if accepts_explicitly(req, "image", "webp") {
+                ResizeFormat::Webp
+            } else {
+                ResizeFormat::Jpeg
+            }
+        });
+
+        Some(ResizeParams {
+            width: self.w.unwrap_or(0).min(ResizeParams::MAX_DIMENSION),
+            height: self.h.unwrap_or(0).min(ResizeParams::MAX_DIMENSION),
+            fit: self.fit.unwrap_or_default(),
+            format,
+        })
+    }
+}
+
+/// The media types listed in the `Accept` header of the request, `None` if there is none.
+fn accepted_types(req: &HttpRequest) -> Option<Vec<MediaType<'_>>> {
+    let accept = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())?;
+
+    Some(MediaTypeList::new(accept).flatten().collect())
+}
+
+/// Whether the `Accept` header of the request lists this type without a wildcard.
+fn accepts_explicitly(req: &HttpRequest, ty: &str, subty: &str) -> bool {
+    accepted_types(req)
+        .unwrap_or_default()
+        .iter()
+        .any(|accepted| accepted.ty == ty && accepted.subty == subty)
+}
+
+/// Whether the `Accept` header of the request rejects files of this type.
+fn is_rejected(req: &HttpRequest, media: &Media) -> bool {
+    let Some(accepted) = accepted_types(req) else {
+        return false;
+    };
+
+    let mime = media.mime.to_ref();
+
+    !accepted.iter().any(|accepted| {
+        (accepted.ty == "*" || accepted.ty == mime.ty)
+            && (accepted.subty == "*" || accepted.subty == mime.subty)
+    })
+}
+
 #[cfg_attr(feature = "http-server-spec", utoipa::path)]
 #[get("/{id}/file")]
 pub async fn file(
     session: Session,
     id: web::Path<(u64, u64)>,
+    params: web::Query<FileParams>,
     req: HttpRequest,
 ) -> Result<impl Responder, WebError> {
     let id = id.into_inner().1;
 
-    let media = session
+    let mut media = session
         .bucket()
         .data_source()
         .media()
@@ -40,25 +130,82 @@ pub async fn file(
         .await?
         .ok_or(WebError::ResourceNotFound)?;
 
+    // without a parameter the rendition is only served if the client cannot accept the original
+    let rendition = params.rendition.unwrap_or_else(|| {
+        if is_rejected(&req, &media) {
+            Rendition::Compatible
+        } else {
+            Rendition::Original
+        }
+    });
+
+    if rendition == Rendition::Compatible {
+        let content = session
+            .bucket()
+            .data_source()
+            .content()
+            .get_by_content_id(media.id)
+            .await?;
+
+        if let Some(rendition) = content.and_then(|content| content.rendition) {
+            media = session
+                .bucket()
+                .data_source()
+                .media()
+                .get_by_id(rendition.id())
+                .await?
+                .ok_or(WebError::ResourceNotFound)?;
+        }
+    }
+
+    let resize_params = params.resize_params(&req);
+
+    // the served file depends on the `Accept` header unless the rendition and format were requested explicitly
+    let is_negotiated =
+        params.rendition.is_none() || (resize_params.is_some() && params.format.is_none());
+
+    let (file_id, file_size, mime) = match resize_params {
+        Some(resize_params) => {
+            // resized images are cached, so they are only rendered the first time
+            let resized = session
+                .bucket()
+                .data_source()
+                .media_import()
+                .resize_image(&media, &resize_params)
+                .await?;
+
+            (
+                resized.file_id,
+                resized.file_size,
+                resize_params.format.mime(),
+            )
+        }
+        None => (media.file_id, media.file_size, media.mime),
+    };
+
     let file = session
         .bucket()
         .data_source()
         .blobs()
-        .get_by_id(&media.file_id)
+        .get_by_id(&file_id)
         .await?;
 
-    let mut length = media.file_size;
+    let mut length = file_size;
     let mut offset: usize = 0;
 
     let mut response = HttpResponse::Ok();
 
     response
         .insert_header((header::ACCEPT_RANGES, "bytes"))
-        .content_type(media.mime.as_str());
+        .content_type(mime.as_str());
+
+    if is_negotiated {
+        response.insert_header((header::VARY, "Accept"));
+    }
 
     if let Some(range_header) = req.headers().get(header::RANGE) {
         if let Ok(header_value) = range_header.to_str() {
-            if let Ok(ranges) = HttpRange::parse(header_value, media.file_size as u64) {
+            if let Ok(ranges) = HttpRange::parse(header_value, file_size as u64) {
                 if let Some(range) = ranges.first() {
                     length = range.length as usize;
                     offset = range.start as usize;
@@ -66,12 +213,7 @@ pub async fn file(
 
                 response.insert_header((
                     header::CONTENT_RANGE,
-                    format!(
-                        "bytes {}-{}/{}",
-                        offset,
-                        offset + length - 1,
-                        media.file_size
-                    ),
+                    format!("bytes {}-{}/{}", offset, offset + length - 1, file_size),
                 ));
             } else {
                 response.insert_header((header::CONTENT_RANGE, format!("bytes */{length}")));
@@ -80,7 +222,7 @@ pub async fn file(
         }
     }
 
-    if offset != 0 || length != media.file_size {
+    if offset != 0 || length != file_size {
         response.status(StatusCode::PARTIAL_CONTENT);
     }
 
@@ -88,3 +230,145 @@ pub async fn file(
 
     Ok(response.body(SizedStream::new(length as u64, stream)))
 }
+
+#[derive(Deserialize)]
+pub struct TokenParams {
+    include_token: Option<bool>,
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/hls/{variant}/index.m3u8")]
+pub async fn hls_playlist(
+    session: Session,
+    path: web::Path<(u64, u64, i32)>,
+    params: web::Query<TokenParams>,
+) -> Result<impl Responder, WebError> {
+    let (_, id, variant) = path.into_inner();
+
+    let token = if params.include_token.unwrap_or(false) && session.read_only() {
+        session.token().map(|s| s.to_string())
+    } else {
+        None
+    };
+
+    let segments = session
+        .bucket()
+        .data_source()
+        .media()
+        .get_hls_segments(id, variant)
+        .await?;
+
+    if segments.is_empty() {
+        return Err(WebError::ResourceNotFound);
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(HLS_CONTENT_TYPE)
+        .body(new_hls_media_playlist(
+            session.instance().base_url(),
+            session.instance().id(),
+            token,
+            id,
+            variant,
+            &segments,
+        )))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/hls/{variant}/{position}.ts")]
+pub async fn hls_segment(
+    session: Session,
+    path: web::Path<(u64, u64, i32, i32)>,
+) -> Result<impl Responder, WebError> {
+    let (_, id, variant, position) = path.into_inner();
+
+    let segment = session
+        .bucket()
+        .data_source()
+        .media()
+        .get_hls_segment(id, variant, position)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    let blob = session
+        .bucket()
+        .data_source()
+        .blobs()
+        .get_by_id(&segment.file_id)
+        .await?;
+
+    let length = segment.file_size as u64;
+    let stream = new_chunked_read(length, 0, Box::into_pin(blob));
+
+    Ok(HttpResponse::Ok()
+        .content_type("video/mp2t")
+        .body(SizedStream::new(length, stream)))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/storyboard.vtt")]
+pub async fn storyboard(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    params: web::Query<TokenParams>,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    let token = if params.include_token.unwrap_or(false) && session.read_only() {
+        session.token().map(|s| s.to_string())
+    } else {
+        None
+    };
+
+    let media = session
+        .bucket()
+        .data_source()
+        .media()
+        .get_by_id(id)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    if media.metadata.video_encoding().is_none() {
+        return Err(WebError::ResourceNotFound);
+    }
+
+    let storyboard = session
+        .bucket()
+        .data_source()
+        .content()
+        .get_storyboard(media.id)
+        .await?;
+
+    // the storyboard is rendered in the background when it is requested for the first time
+    let Some(storyboard) = storyboard else {
+        let bucket = session.bucket_arc();
+
+        rt::spawn(async move {
+            match bucket
+                .data_source()
+                .media_import()
+                .create_storyboard(&media)
+                .await
+            {
+                Ok(Some(_)) => info!("Rendered the storyboard of media {}", media.id),
+                Ok(None) => {}
+                Err(e) => error!(
+                    "Failed to render the storyboard of media {}: {}",
+                    media.id, e
+                ),
+            }
+        });
+
+        return Err(WebError::ResourceNotFound);
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/vtt")
+        .body(new_storyboard_track(
+            session.instance().base_url(),
+            session.instance().id(),
+            token,
+            &storyboard,
+            media.metadata.duration().unwrap_or_default(),
+        )))
+}
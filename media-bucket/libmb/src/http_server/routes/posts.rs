@@ -1,14 +1,22 @@
 use actix_web::body::BodyStream;
-use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
-use log::info;
+use actix_web::{delete, get, post, put, rt, web, HttpResponse, Responder};
+use log::{error, info};
 use serde::Deserialize;
 
-use crate::http_server::stream_playlist::new_search_playlist;
+use crate::http_server::stream_playlist::{
+    new_hls_master_playlist, new_search_playlist, HLS_CONTENT_TYPE,
+};
 use crate::http_server::web_error::WebError;
-use crate::model::{CreateFullPost, PostGraphQuery, PostSearchQuery};
+use crate::model::{
+    CreateFullPost, ImportBatchOrigin, ManyToOne, PostGraphQuery, PostItem, PostSearchQuery,
+};
 use crate::{data_source::PageParams, http_server::stream_playlist::new_post_playlist};
 use crate::{
-    http_models::{CreateFullPostResponse, UpdatePostRequest},
+    http_models::{
+        CreateFullPostResponse, CreatePostItemRequest, FavouritePostRequest, MergePostsRequest,
+        MovePostItemRequest, RatePostRequest, RevertPostRequest, SetCustomFieldValueRequest,
+        SplitPostRequest, UpdatePostItemRequest, UpdatePostRequest, UpdateWatchProgressRequest,
+    },
     http_server::stream_playlist::new_content_playlist,
 };
 use crate::{http_server::instance::Session, model::PostItemSearchQuery};
@@ -29,6 +37,28 @@ pub async fn graph(
     Ok(web::Json(graph))
 }
 
+#[derive(Deserialize)]
+pub struct GeoParams {
+    zoom: Option<u8>,
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("geo")]
+pub async fn geo(
+    session: Session,
+    query: PostSearchQuery,
+    params: web::Query<GeoParams>,
+) -> Result<impl Responder, WebError> {
+    let clusters = session
+        .bucket()
+        .data_source()
+        .cross()
+        .get_geo_clusters(&query, params.zoom.unwrap_or_default())
+        .await?;
+
+    Ok(web::Json(clusters))
+}
+
 #[cfg_attr(feature = "http-server-spec", utoipa::path)]
 #[get("")]
 pub async fn index(
@@ -173,14 +203,58 @@ pub async fn delete(
         .bucket()
         .data_source()
         .cross()
-        .cascade_delete_post(id)
+        .trash_post(id, &session.origin())
         .await?;
 
-    info!("Deleted post {id}");
+    info!("Moved post {id} to the trash");
 
     Ok(web::Json(()))
 }
 
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[post("/merge")]
+pub async fn merge(
+    session: Session,
+    req: web::Json<MergePostsRequest>,
+) -> Result<impl Responder, WebError> {
+    let post = session
+        .bucket()
+        .data_source()
+        .cross()
+        .merge_posts(&req.post_ids, &session.origin())
+        .await?;
+
+    info!("Merged posts {:?} into {}", req.post_ids, post.id);
+
+    Ok(web::Json(post))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[post("/{id}/split")]
+pub async fn split(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    req: web::Json<SplitPostRequest>,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    let post = session
+        .bucket()
+        .data_source()
+        .cross()
+        .split_post(id, &req.positions, &session.origin())
+        .await?;
+
+    info!(
+        "Split {} item(s) of post {} into {}",
+        req.positions.len(),
+        id,
+        post.id
+    );
+
+    Ok(web::Json(post))
+}
+
 #[cfg_attr(feature = "http-server-spec", utoipa::path)]
 #[get("/{id}/items/{position}")]
 pub async fn show_item(
@@ -231,6 +305,62 @@ pub async fn show_item_playlist(
         }
     }
 
+    let video = item
+        .content
+        .as_ref()
+        .obj()
+        .and_then(|content| content.content.as_ref().obj())
+        .filter(|media| media.metadata.video_encoding().is_some())
+        .cloned();
+
+    if let Some(media) = video {
+        let variants = session
+            .bucket()
+            .data_source()
+            .media()
+            .get_hls_variants(media.id)
+            .await?;
+
+        if !variants.is_empty() {
+            return Ok(HttpResponse::Ok().content_type(HLS_CONTENT_TYPE).body(
+                new_hls_master_playlist(
+                    session.instance().base_url(),
+                    session.instance().id(),
+                    token,
+                    &variants,
+                ),
+            ));
+        }
+
+        // the whole file is played until the video has been segmented in the background
+        let settings = session
+            .bucket()
+            .data_source()
+            .cross()
+            .get_settings()
+            .await?;
+        let bucket = session.bucket_arc();
+
+        rt::spawn(async move {
+            match bucket
+                .data_source()
+                .media_import()
+                .create_hls_stream(&media, settings.hls_ladder)
+                .await
+            {
+                Ok(variants) if !variants.is_empty() => {
+                    info!(
+                        "Segmented media {} into {} variant(s)",
+                        media.id,
+                        variants.len()
+                    )
+                }
+                Ok(_) => {}
+                Err(e) => error!("Failed to segment media {}: {}", media.id, e),
+            }
+        });
+    }
+
     let response = HttpResponse::Ok().body(BodyStream::new(new_content_playlist(
         session.instance().base_url(),
         session.instance().id(),
@@ -279,6 +409,122 @@ pub async fn index_items(
     Ok(web::Json(items))
 }
 
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[post("/{id}/items")]
+pub async fn store_item(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    req: web::Json<CreatePostItemRequest>,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    let mut item = PostItem {
+        post: ManyToOne::Id(id),
+        position: req.position.unwrap_or(i32::MAX),
+        content: ManyToOne::Id(req.content_id),
+        upload: req.metadata.clone(),
+    };
+
+    session
+        .bucket()
+        .data_source()
+        .post_items()
+        .insert(&mut item, &session.origin())
+        .await?;
+
+    info!("Inserted item {} into post {}", item.position, id);
+
+    Ok(web::Json(item))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[put("/{id}/items/{position}")]
+pub async fn update_item(
+    session: Session,
+    path: web::Path<(u64, u64, i32)>,
+    req: web::Json<UpdatePostItemRequest>,
+) -> Result<impl Responder, WebError> {
+    let (_, id, position) = path.into_inner();
+
+    let item = PostItem {
+        post: ManyToOne::Id(id),
+        position,
+        content: ManyToOne::Id(req.content_id),
+        upload: req.metadata.clone(),
+    };
+
+    session
+        .bucket()
+        .data_source()
+        .post_items()
+        .replace(&item, &session.origin())
+        .await?;
+
+    info!("Replaced item {} of post {}", position, id);
+
+    Ok(web::Json(item))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[put("/{id}/items/{position}/progress")]
+pub async fn update_item_progress(
+    session: Session,
+    path: web::Path<(u64, u64, i32)>,
+    req: web::Json<UpdateWatchProgressRequest>,
+) -> Result<impl Responder, WebError> {
+    let (_, id, position) = path.into_inner();
+
+    session
+        .bucket()
+        .data_source()
+        .post_items()
+        .update_progress(id, position, req.position)
+        .await?;
+
+    Ok(web::Json(()))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[delete("/{id}/items/{position}")]
+pub async fn delete_item(
+    session: Session,
+    path: web::Path<(u64, u64, i32)>,
+) -> Result<impl Responder, WebError> {
+    let (_, id, position) = path.into_inner();
+
+    session
+        .bucket()
+        .data_source()
+        .post_items()
+        .remove(id, position, &session.origin())
+        .await?;
+
+    info!("Removed item {} from post {}", position, id);
+
+    Ok(web::Json(()))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[post("/{id}/items/{position}/move")]
+pub async fn move_item(
+    session: Session,
+    path: web::Path<(u64, u64, i32)>,
+    req: web::Json<MovePostItemRequest>,
+) -> Result<impl Responder, WebError> {
+    let (_, id, position) = path.into_inner();
+
+    session
+        .bucket()
+        .data_source()
+        .post_items()
+        .reorder(id, position, req.position, &session.origin())
+        .await?;
+
+    info!("Moved item {} of post {} to {}", position, id, req.position);
+
+    Ok(web::Json(()))
+}
+
 #[derive(Deserialize)]
 pub struct CreatePostTagRequest {
     tag_id: u64,
@@ -322,11 +568,16 @@ pub async fn store(
     session: Session,
     req: web::Json<CreateFullPost>,
 ) -> Result<impl Responder, WebError> {
+    let new_post = CreateFullPost {
+        batch_origin: ImportBatchOrigin::Web,
+        ..req.into_inner()
+    };
+
     let (batch, posts) = session
         .bucket()
         .data_source()
         .cross()
-        .add_full_post(req.clone())
+        .add_full_post(new_post)
         .await?;
 
     info!("Created {} post(s) batch: {}", posts.len(), batch.id);
@@ -352,12 +603,211 @@ pub async fn update(
     post.description = req.description.clone();
     post.source = req.source.clone();
 
+    if let Some(rating) = req.rating {
+        post.rating = Some(rating);
+    }
+
+    if let Some(favourite) = req.favourite {
+        post.favourite = favourite;
+    }
+
+    session
+        .bucket()
+        .data_source()
+        .cross()
+        .update_full_post(&post, &req.tag_ids, &session.origin())
+        .await?;
+
+    Ok(web::Json(post))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/fields")]
+pub async fn show_fields(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    let post = session
+        .bucket()
+        .data_source()
+        .posts()
+        .get_by_id(id)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    let fields = session
+        .bucket()
+        .data_source()
+        .custom_fields()
+        .get_from_post(post.id)
+        .await?;
+
+    Ok(web::Json(fields))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[put("/{id}/fields/{field_id}")]
+pub async fn update_field(
+    session: Session,
+    path: web::Path<(u64, u64, u64)>,
+    req: web::Json<SetCustomFieldValueRequest>,
+) -> Result<impl Responder, WebError> {
+    let (_, id, field_id) = path.into_inner();
+
     session
+        .bucket()
+        .data_source()
+        .custom_fields()
+        .set_value(id, field_id, req.value.as_ref(), &session.origin())
+        .await?;
+
+    let fields = session
+        .bucket()
+        .data_source()
+        .custom_fields()
+        .get_from_post(id)
+        .await?;
+
+    Ok(web::Json(fields))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[put("/{id}/rating")]
+pub async fn update_rating(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    req: web::Json<RatePostRequest>,
+) -> Result<impl Responder, WebError> {
+    let mut post = session
+        .bucket()
+        .data_source()
+        .posts()
+        .get_by_id(id.into_inner().1)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    post.rating = req.rating;
+
+    session.bucket().data_source().posts().update(&post).await?;
+
+    Ok(web::Json(post))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[put("/{id}/favourite")]
+pub async fn update_favourite(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    req: web::Json<FavouritePostRequest>,
+) -> Result<impl Responder, WebError> {
+    let mut post = session
+        .bucket()
+        .data_source()
+        .posts()
+        .get_by_id(id.into_inner().1)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    post.favourite = req.favourite;
+
+    session.bucket().data_source().posts().update(&post).await?;
+
+    Ok(web::Json(post))
+}
+
+#[derive(Deserialize)]
+pub struct SimilarParams {
+    require_playable: Option<bool>,
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/similar")]
+pub async fn similar(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    params: web::Query<SimilarParams>,
+    page: PageParams,
+) -> Result<impl Responder, WebError> {
+    let posts = session
+        .bucket()
+        .data_source()
+        .cross()
+        .get_similar_posts(
+            id.into_inner().1,
+            params.require_playable.unwrap_or_default(),
+            &page,
+        )
+        .await?;
+
+    Ok(web::Json(posts))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/suggested-tags")]
+pub async fn suggested_tags(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    page: PageParams,
+) -> Result<impl Responder, WebError> {
+    let post = session
+        .bucket()
+        .data_source()
+        .posts()
+        .get_by_id(id.into_inner().1)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    let tags = session
         .bucket()
         .data_source()
         .cross()
-        .update_full_post(&post, &req.tag_ids)
+        .get_suggested_tags(post.id, &page)
         .await?;
 
+    Ok(web::Json(tags))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/history")]
+pub async fn history(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    page: PageParams,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    let changes = session
+        .bucket()
+        .data_source()
+        .changes()
+        .get_page_from_post(id, &page)
+        .await?;
+
+    Ok(web::Json(changes))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[post("/{id}/revert")]
+pub async fn revert(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    req: web::Json<RevertPostRequest>,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    let post = session
+        .bucket()
+        .data_source()
+        .cross()
+        .revert_post(id, req.change_id, &req.fields, &session.origin())
+        .await?;
+
+    info!(
+        "Reverted {:?} of post {id} to change {}",
+        req.fields, req.change_id
+    );
+
     Ok(web::Json(post))
 }
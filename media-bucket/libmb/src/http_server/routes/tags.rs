@@ -92,13 +92,62 @@ pub async fn update(
     tag.group = req.group.map(|id| ManyToOne::Id(id));
     tag.name = req.name.clone();
 
-    session.bucket().data_source().tags().update(&tag).await?;
+    session
+        .bucket()
+        .data_source()
+        .tags()
+        .update(&tag, &session.origin())
+        .await?;
 
     info!("Updated tag {}", tag.id);
 
     Ok(web::Json(tag))
 }
 
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/history")]
+pub async fn history(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    page: PageParams,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    let changes = session
+        .bucket()
+        .data_source()
+        .changes()
+        .get_page_from_tag(id, &page)
+        .await?;
+
+    Ok(web::Json(changes))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("/{id}/related")]
+pub async fn related(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+    page: PageParams,
+) -> Result<impl Responder, WebError> {
+    let tag = session
+        .bucket()
+        .data_source()
+        .tags()
+        .get_by_id(id.into_inner().1)
+        .await?
+        .ok_or(WebError::ResourceNotFound)?;
+
+    let related = session
+        .bucket()
+        .data_source()
+        .cross()
+        .get_related_tags(tag.id, &page)
+        .await?;
+
+    Ok(web::Json(related))
+}
+
 #[cfg_attr(feature = "http-server-spec", utoipa::path)]
 #[delete("/{id}")]
 pub async fn delete(
@@ -115,7 +164,12 @@ pub async fn delete(
         .await?
         .ok_or(WebError::ResourceNotFound)?;
 
-    session.bucket().data_source().tags().delete(tag.id).await?;
+    session
+        .bucket()
+        .data_source()
+        .tags()
+        .delete(tag.id, &session.origin())
+        .await?;
 
     info!("Deleted tag {}", tag.id);
 
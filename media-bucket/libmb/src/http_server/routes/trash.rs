@@ -0,0 +1,64 @@
+use actix_web::{delete, get, post, web, Responder};
+use log::info;
+
+use crate::data_source::PageParams;
+use crate::http_server::instance::Session;
+use crate::http_server::web_error::WebError;
+use crate::model::PostSearchQuery;
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[get("")]
+pub async fn index(
+    session: Session,
+    query: PostSearchQuery,
+    page: PageParams,
+) -> Result<impl Responder, WebError> {
+    let query = PostSearchQuery {
+        trashed: true,
+        ..query
+    };
+
+    let posts = session
+        .bucket()
+        .data_source()
+        .cross()
+        .search_posts(&query, &page)
+        .await?;
+
+    Ok(web::Json(posts))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[post("/{id}/restore")]
+pub async fn restore(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    session
+        .bucket()
+        .data_source()
+        .cross()
+        .restore_post(id, &session.origin())
+        .await?;
+
+    info!("Restored post {id} from the trash");
+
+    Ok(web::Json(()))
+}
+
+#[cfg_attr(feature = "http-server-spec", utoipa::path)]
+#[delete("/{id}")]
+pub async fn purge(
+    session: Session,
+    id: web::Path<(u64, u64)>,
+) -> Result<impl Responder, WebError> {
+    let id = id.into_inner().1;
+
+    session.bucket().purge_post(id, &session.origin()).await?;
+
+    info!("Purged post {id}");
+
+    Ok(web::Json(()))
+}
@@ -1,15 +1,19 @@
 mod api_urls;
+mod hls_playlist;
 mod media_playlist_stream;
 mod playlist_stream;
+mod storyboard_track;
 
 use crate::{
     data_source::{DataSourceError, PageParams},
-    model::{PostDetail, PostItem, PostItemSearchQuery},
+    model::{HlsSegment, HlsVariant, PostDetail, PostItem, PostItemSearchQuery, Storyboard},
 };
 
 use api_urls::*;
+use hls_playlist::*;
 use media_playlist_stream::*;
 use playlist_stream::*;
+use storyboard_track::*;
 
 use crate::model::PostSearchQuery;
 use crate::Bucket;
@@ -166,7 +170,7 @@ async fn get_content(item: PostItem, bucket: Arc<Bucket>) -> Result<MediaEntry,
             .obj()
             .and_then(|post| post.title.clone())
             .or(item.upload.original_filename),
-        runtime_seconds: media.metadata.duration().cloned().unwrap_or(-1),
+        runtime_seconds: media.metadata.duration().unwrap_or(-1),
         resolution: media
             .metadata
             .width()
@@ -175,6 +179,50 @@ async fn get_content(item: PostItem, bucket: Arc<Bucket>) -> Result<MediaEntry,
     })
 }
 
+/// A HLS master playlist that links the media playlist of each variant.
+pub fn new_hls_master_playlist(
+    base: Option<Arc<Url>>,
+    bucket_id: u64,
+    token: Option<String>,
+    variants: &[HlsVariant],
+) -> String {
+    write_master_playlist(&ApiUrl { bucket_id, base }, &AuthParams { token }, variants)
+}
+
+/// A HLS media playlist that links the segments of a variant.
+pub fn new_hls_media_playlist(
+    base: Option<Arc<Url>>,
+    bucket_id: u64,
+    token: Option<String>,
+    media_id: u64,
+    variant: i32,
+    segments: &[HlsSegment],
+) -> String {
+    write_media_playlist(
+        &ApiUrl { bucket_id, base },
+        &AuthParams { token },
+        media_id,
+        variant,
+        segments,
+    )
+}
+
+/// A WebVTT thumbnails track that links the frames of a storyboard.
+pub fn new_storyboard_track(
+    base: Option<Arc<Url>>,
+    bucket_id: u64,
+    token: Option<String>,
+    storyboard: &Storyboard,
+    duration: i32,
+) -> String {
+    write_storyboard_track(
+        &ApiUrl { bucket_id, base },
+        &AuthParams { token },
+        storyboard,
+        duration,
+    )
+}
+
 pub fn new_search_playlist(
     base: Option<Arc<Url>>,
     bucket_id: u64,
@@ -230,3 +278,5 @@ async fn search_posts(
 }
 
 const PLAYLIST_HEADER: &str = "#EXTM3U\r\n#EXTENC:UTF-8";
+
+pub const HLS_CONTENT_TYPE: &str = "application/vnd.apple.mpegurl";
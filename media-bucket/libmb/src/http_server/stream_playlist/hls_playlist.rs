@@ -0,0 +1,76 @@
+use crate::model::{HlsSegment, HlsVariant};
+
+use super::api_urls::{ApiUrl, AuthParams};
+
+use std::fmt::Write;
+
+const HLS_HEADER: &str = "#EXTM3U\r\n#EXT-X-VERSION:3";
+
+pub fn write_master_playlist(
+    api_url: &ApiUrl,
+    auth_params: &AuthParams,
+    variants: &[HlsVariant],
+) -> String {
+    let mut buffer = String::from(HLS_HEADER);
+
+    write!(&mut buffer, "\r\n#EXT-X-INDEPENDENT-SEGMENTS").unwrap();
+
+    for variant in variants {
+        write!(
+            &mut buffer,
+            "\r\n#EXT-X-STREAM-INF:BANDWIDTH={},AVERAGE-BANDWIDTH={},RESOLUTION={}x{}",
+            variant.bandwidth, variant.average_bandwidth, variant.dims.width, variant.dims.height
+        )
+        .unwrap();
+
+        // the token is forwarded so that the media playlist can link the segments with it
+        write!(
+            &mut buffer,
+            "\r\n{api_url}/media/{}/hls/{}/index.m3u8{}",
+            variant.media_id,
+            variant.variant,
+            auth_params.include_token()
+        )
+        .unwrap();
+    }
+
+    buffer.push_str("\r\n");
+    buffer
+}
+
+pub fn write_media_playlist(
+    api_url: &ApiUrl,
+    auth_params: &AuthParams,
+    media_id: u64,
+    variant: i32,
+    segments: &[HlsSegment],
+) -> String {
+    let target_duration = segments
+        .iter()
+        .map(|segment| segment.duration.ceil() as u64)
+        .max()
+        .unwrap_or_default();
+
+    let mut buffer = String::from(HLS_HEADER);
+
+    write!(
+        &mut buffer,
+        "\r\n#EXT-X-TARGETDURATION:{target_duration}\r\n#EXT-X-MEDIA-SEQUENCE:0\r\n#EXT-X-PLAYLIST-TYPE:VOD"
+    )
+    .unwrap();
+
+    for segment in segments {
+        write!(
+            &mut buffer,
+            "\r\n#EXTINF:{:.3},\r\n{api_url}/media/{media_id}/hls/{variant}/{}.ts{}",
+            segment.duration,
+            segment.position,
+            auth_params.without_include()
+        )
+        .unwrap();
+    }
+
+    write!(&mut buffer, "\r\n#EXT-X-ENDLIST\r\n").unwrap();
+
+    buffer
+}
@@ -0,0 +1,46 @@
+use crate::model::Storyboard;
+
+use super::api_urls::{ApiUrl, AuthParams};
+
+use std::fmt::Write;
+
+/// Format seconds as a WebVTT timestamp.
+fn vtt_timestamp(seconds: i32) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.000",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+pub fn write_storyboard_track(
+    api_url: &ApiUrl,
+    auth_params: &AuthParams,
+    storyboard: &Storyboard,
+    duration: i32,
+) -> String {
+    let mut buffer = String::from("WEBVTT\r\n");
+    let sprite_id = storyboard.sprite.id();
+
+    for frame in 0..storyboard.frame_count {
+        let start = frame * storyboard.interval;
+        let end = ((frame + 1) * storyboard.interval)
+            .min(duration)
+            .max(start + 1);
+        let (x, y) = storyboard.frame_offset(frame);
+
+        write!(
+            &mut buffer,
+            "\r\n{} --> {}\r\n{api_url}/media/{sprite_id}/file{}#xywh={x},{y},{},{}\r\n",
+            vtt_timestamp(start),
+            vtt_timestamp(end),
+            auth_params.without_include(),
+            storyboard.tile.width,
+            storyboard.tile.height
+        )
+        .unwrap();
+    }
+
+    buffer
+}
@@ -53,6 +53,21 @@ pub enum WebError {
     #[error("The submitted data conflicts with existing data")]
     Duplicate,
 
+    #[error("A post must contain at least one item")]
+    EmptyPost,
+
+    #[error("A rating must be between 0 and 5")]
+    InvalidRating,
+
+    #[error("A watch position must be a positive number of seconds")]
+    InvalidWatchPosition,
+
+    #[error("A field name may only contain letters, digits, '_' and '-'")]
+    InvalidFieldName,
+
+    #[error("The value does not match the type of the field")]
+    FieldTypeMismatch,
+
     #[error("Password required")]
     PasswordRequired,
 
@@ -83,6 +98,11 @@ impl From<DataSourceError> for WebError {
         match value {
             DataSourceError::Duplicate => Self::Duplicate,
             DataSourceError::NotFound => Self::ResourceNotFound,
+            DataSourceError::EmptyPost => Self::EmptyPost,
+            DataSourceError::InvalidRating => Self::InvalidRating,
+            DataSourceError::InvalidWatchPosition => Self::InvalidWatchPosition,
+            DataSourceError::InvalidFieldName => Self::InvalidFieldName,
+            DataSourceError::FieldTypeMismatch => Self::FieldTypeMismatch,
 
             e => Self::InternalDataSourceError(e),
         }
@@ -136,6 +156,11 @@ impl actix_web::error::ResponseError for WebError {
             WebError::InternalDataSourceError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             WebError::ResourceNotFound => StatusCode::NOT_FOUND,
             WebError::Duplicate => StatusCode::CONFLICT,
+            WebError::EmptyPost => StatusCode::UNPROCESSABLE_ENTITY,
+            WebError::InvalidRating => StatusCode::UNPROCESSABLE_ENTITY,
+            WebError::InvalidWatchPosition => StatusCode::UNPROCESSABLE_ENTITY,
+            WebError::InvalidFieldName => StatusCode::UNPROCESSABLE_ENTITY,
+            WebError::FieldTypeMismatch => StatusCode::UNPROCESSABLE_ENTITY,
             WebError::PasswordRequired => StatusCode::UNPROCESSABLE_ENTITY,
             WebError::InvalidPassword => StatusCode::UNAUTHORIZED,
             WebError::EndpointNotFound => StatusCode::NOT_FOUND,
@@ -16,6 +16,12 @@ pub mod local;
 
 pub mod model;
 
+#[cfg(feature = "local")]
+pub use media_import::{
+    AudioImporter, FfmpegImporter, ImageMagickImporter, MediaImporter, MediaImporterRegistry,
+    OfficeDocumentImporter, PdfImporter,
+};
+
 #[cfg(feature = "local")]
 mod media_import;
 
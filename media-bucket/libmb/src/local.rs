@@ -1,14 +1,26 @@
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 use async_trait::async_trait;
 use mediatype::MediaTypeBuf;
 use thiserror::Error;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
 
 use uuid::Uuid;
 
 use crate::local::sqlite::{SqliteError, SqliteIndex};
-use crate::media_import::{import_file_with_thumbnail, MediaImportOutput};
-use crate::model::{Content, ManyToOne, Media};
+use crate::media_import::{
+    extract_document_text, extract_ocr_text, hls_variant_specs, import_file_with_thumbnail,
+    import_rendition, import_storyboard, render_resized_image, rendition_method, reprocess_file,
+    segment_hls, strip_gps, supports_gps_stripping, supports_resizing, supports_text,
+    MediaImportOutput, MediaImporterRegistry, TmpDir,
+};
+use crate::model::{
+    Content, Dimensions, HlsSegment, HlsVariant, ManyToOne, Media, MediaTextKind, ResizeParams,
+    ResizedMedia, Storyboard,
+};
 use crate::{data_source::*, media_import::TmpFile};
 
 #[cfg(feature = "encryption")]
@@ -49,6 +61,9 @@ pub enum LocalDataSourceError {
     SqliteError(#[from] SqliteError),
 }
 
+/// The number of videos that are converted or segmented in the background at the same time.
+const MAX_CONCURRENT_TRANSCODES: usize = 1;
+
 #[cfg(feature = "encryption")]
 pub type EncryptedLocalDataSource =
     LocalDataSource<encrypted_fs_storage::EncryptedFileDataSource, secret::EncryptionMetadata>;
@@ -57,6 +72,39 @@ pub struct LocalDataSource<FileStorage: BlobDataSource, Passwords: PasswordDataS
     passwords: Passwords,
     storage: FileStorage,
     sqlite: SqliteIndex,
+    importers: MediaImporterRegistry,
+    /// The ids of the videos that are being segmented for HLS right now.
+    segmenting: Mutex<HashSet<u64>>,
+    /// The ids of the videos whose storyboard is being rendered right now.
+    storyboarding: Mutex<HashSet<u64>>,
+    /// Limits the ffmpeg runs that convert or segment videos, further runs wait for a permit.
+    transcodes: Semaphore,
+}
+
+/// Marks a video as being processed until it is dropped.
+struct InFlightGuard<'a> {
+    media_ids: &'a Mutex<HashSet<u64>>,
+    media_id: u64,
+}
+
+impl<'a> InFlightGuard<'a> {
+    /// Returns `None` if the video is already being processed.
+    fn new(media_ids: &'a Mutex<HashSet<u64>>, media_id: u64) -> Option<Self> {
+        if !media_ids.lock().unwrap().insert(media_id) {
+            return None;
+        }
+
+        Some(Self {
+            media_ids,
+            media_id,
+        })
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.media_ids.lock().unwrap().remove(&self.media_id);
+    }
 }
 
 #[cfg(feature = "encryption")]
@@ -103,6 +151,10 @@ impl EncryptedLocalDataSource {
             storage,
             sqlite,
             passwords: encryption_metadata,
+            importers: MediaImporterRegistry::default(),
+            segmenting: Mutex::new(HashSet::new()),
+            storyboarding: Mutex::new(HashSet::new()),
+            transcodes: Semaphore::new(MAX_CONCURRENT_TRANSCODES),
         })
     }
 
@@ -138,6 +190,10 @@ impl EncryptedLocalDataSource {
             storage,
             sqlite,
             passwords: encryption_metadata,
+            importers: MediaImporterRegistry::default(),
+            segmenting: Mutex::new(HashSet::new()),
+            storyboarding: Mutex::new(HashSet::new()),
+            transcodes: Semaphore::new(MAX_CONCURRENT_TRANSCODES),
         })
     }
 }
@@ -176,6 +232,14 @@ impl<FileStorage: BlobDataSource, Passwords: PasswordDataSource> DataSource
         &self.sqlite
     }
 
+    fn changes(&self) -> &dyn ChangeDataSource {
+        &self.sqlite
+    }
+
+    fn custom_fields(&self) -> &dyn CustomFieldDataSource {
+        &self.sqlite
+    }
+
     fn passwords(&self) -> &dyn PasswordDataSource {
         &self.passwords
     }
@@ -212,6 +276,19 @@ impl<FileStorage: BlobDataSource, Passwords: PasswordDataSource> MediaImportData
             ImportSource::File(f) => f,
         };
 
+        let settings = self.sqlite.get_settings().await?;
+
+        // the location is removed before anything is read, so it is not kept in the index either
+        let mut _stripped_file = None;
+        let path = if settings.strip_gps && supports_gps_stripping(&mime.to_ref()) {
+            let stripped_file = _stripped_file.insert(TmpFile::new().await?);
+            strip_gps(path, stripped_file.path()).await?;
+
+            stripped_file.path()
+        } else {
+            path
+        };
+
         let media_id = Uuid::new_v4();
         let media_writer = self.blobs().add(&media_id).await?;
 
@@ -222,6 +299,7 @@ impl<FileStorage: BlobDataSource, Passwords: PasswordDataSource> MediaImportData
             mut content,
             mut thumbnail,
         } = import_file_with_thumbnail(
+            &self.importers,
             path,
             mime,
             media_id,
@@ -234,6 +312,16 @@ impl<FileStorage: BlobDataSource, Passwords: PasswordDataSource> MediaImportData
         content = self.add_or_get_media(content).await?;
         thumbnail = self.add_or_get_media(thumbnail).await?;
 
+        // the text only makes the file searchable, failing to extract it should not fail the import
+        let _ = self
+            .store_text(&content, path, MediaTextKind::Document)
+            .await;
+
+        // the same goes for ocr, the media is already stored and a missing tesseract would fail every upload
+        if settings.ocr {
+            let _ = self.store_text(&content, path, MediaTextKind::Ocr).await;
+        }
+
         if let Some(mut existing) = self.content().get_by_content_id(content.id).await? {
             if existing.thumbnail.id() != thumbnail.id {
                 self.content()
@@ -244,11 +332,13 @@ impl<FileStorage: BlobDataSource, Passwords: PasswordDataSource> MediaImportData
             Ok(Content {
                 content: ManyToOne::Obj(content),
                 thumbnail: ManyToOne::Obj(thumbnail),
+                rendition: existing.rendition,
             })
         } else {
             let mut new_content = Content {
                 content: ManyToOne::Obj(content),
                 thumbnail: ManyToOne::Obj(thumbnail),
+                rendition: None,
             };
 
             self.content().add(&mut new_content).await?;
@@ -256,11 +346,458 @@ impl<FileStorage: BlobDataSource, Passwords: PasswordDataSource> MediaImportData
             Ok(new_content)
         }
     }
+
+    async fn extract_text(
+        &self,
+        media: &Media,
+        kind: MediaTextKind,
+    ) -> Result<bool, MediaImportError> {
+        if !supports_text(&media.mime.to_ref(), kind) {
+            return Ok(false);
+        }
+
+        let tmp_file = self.copy_to_tmp_file(media).await?;
+
+        self.store_text(media, tmp_file.path(), kind).await
+    }
+
+    async fn create_rendition(&self, media: &Media) -> Result<Option<Media>, MediaImportError> {
+        if rendition_method(media).is_none() {
+            return Ok(None);
+        }
+
+        // queued conversions look the content up once they run, so a finished rendition is not created twice
+        let _permit = self
+            .transcodes
+            .acquire()
+            .await
+            .expect("the transcode semaphore is never closed");
+
+        let mut content = self
+            .content()
+            .get_by_content_id(media.id)
+            .await?
+            .ok_or(DataSourceError::NotFound)?;
+
+        if content.rendition.is_some() {
+            return Ok(None);
+        }
+
+        let tmp_file = self.copy_to_tmp_file(media).await?;
+
+        let rendition_id = Uuid::new_v4();
+        let rendition_writer = self.blobs().add(&rendition_id).await?;
+
+        let rendition = import_rendition(
+            &self.importers,
+            media,
+            tmp_file.path(),
+            rendition_id,
+            Box::into_pin(rendition_writer),
+        )
+        .await;
+
+        let rendition = match rendition {
+            Ok(Some(rendition)) => rendition,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                // the blob was created before converting, so it is removed again
+                let _ = self.blobs().delete(&rendition_id).await;
+                return Err(e);
+            }
+        };
+
+        let rendition = self.add_or_get_media(rendition).await?;
+
+        self.content()
+            .update_rendition_id(Some(rendition.id), &mut content)
+            .await?;
+
+        Ok(Some(rendition))
+    }
+
+    async fn reprocess(&self, content_id: u64) -> Result<Content, MediaImportError> {
+        let mut content = self
+            .content()
+            .get_by_content_id(content_id)
+            .await?
+            .ok_or(DataSourceError::NotFound)?;
+
+        let media = self
+            .media()
+            .get_by_id(content_id)
+            .await?
+            .ok_or(DataSourceError::NotFound)?;
+
+        let tmp_file = self.copy_to_tmp_file(&media).await?;
+
+        let thumb_id = Uuid::new_v4();
+        let thumb_writer = self.blobs().add(&thumb_id).await?;
+
+        let output = reprocess_file(
+            &self.importers,
+            &media,
+            tmp_file.path(),
+            thumb_id,
+            Box::into_pin(thumb_writer),
+        )
+        .await;
+
+        let MediaImportOutput {
+            content: media,
+            thumbnail,
+        } = match output {
+            Ok(output) => output,
+            Err(e) => {
+                let _ = self.blobs().delete(&thumb_id).await;
+                return Err(e);
+            }
+        };
+
+        self.media().update_metadata(&media).await?;
+
+        let thumbnail = self.add_or_get_media(thumbnail).await?;
+        let previous_thumbnail_id = content.thumbnail.id();
+
+        if previous_thumbnail_id != thumbnail.id {
+            self.content()
+                .update_thumbnail_id(thumbnail.id, &mut content)
+                .await?;
+
+            // thumbnails with the same hash are shared, so the previous one may still be in use
+            if let Some(previous) = self.media().remove_if_unused(previous_thumbnail_id).await? {
+                self.blobs().delete(&previous.file_id).await?;
+            }
+        }
+
+        Ok(Content {
+            content: ManyToOne::Obj(media),
+            thumbnail: ManyToOne::Obj(thumbnail),
+            rendition: content.rendition,
+        })
+    }
+
+    async fn resize_image(
+        &self,
+        media: &Media,
+        params: &ResizeParams,
+    ) -> Result<ResizedMedia, MediaImportError> {
+        if !supports_resizing(&media.mime.to_ref()) {
+            return Err(MediaImportError::UnsupportedMimeType);
+        }
+
+        if let Some(resized) = self.media().get_resized(media.id, params).await? {
+            return Ok(resized);
+        }
+
+        let tmp_file = self.copy_to_tmp_file(media).await?;
+        let output = TmpFile::new().await?;
+
+        render_resized_image(&media.mime.to_ref(), tmp_file.path(), params, output.path()).await?;
+
+        let file_id = Uuid::new_v4();
+        let file_size = match self.store_blob(&file_id, output.path()).await {
+            Ok(file_size) => file_size,
+            Err(e) => {
+                let _ = self.blobs().delete(&file_id).await;
+                return Err(e);
+            }
+        };
+
+        let resized = ResizedMedia {
+            media_id: media.id,
+            params: *params,
+            file_id,
+            file_size,
+        };
+
+        if let Some(evicted) = self.media().add_resized(&resized).await? {
+            for file_id in evicted.iter() {
+                let _ = self.blobs().delete(file_id).await;
+            }
+
+            return Ok(resized);
+        }
+
+        // another request resized the same image in the meantime
+        let _ = self.blobs().delete(&file_id).await;
+
+        Ok(self
+            .media()
+            .get_resized(media.id, params)
+            .await?
+            .ok_or(DataSourceError::NotFound)?)
+    }
+
+    async fn create_storyboard(
+        &self,
+        media: &Media,
+    ) -> Result<Option<Storyboard>, MediaImportError> {
+        if media.metadata.video_encoding().is_none() {
+            return Ok(None);
+        }
+
+        let Some(_guard) = InFlightGuard::new(&self.storyboarding, media.id) else {
+            return Ok(None);
+        };
+
+        // rendering shares the permits of the rendition conversions
+        let _permit = self
+            .transcodes
+            .acquire()
+            .await
+            .expect("the transcode semaphore is never closed");
+
+        if let Some(storyboard) = self.content().get_storyboard(media.id).await? {
+            return Ok(Some(storyboard));
+        }
+
+        self.content()
+            .get_by_content_id(media.id)
+            .await?
+            .ok_or(DataSourceError::NotFound)?;
+
+        let tmp_file = self.copy_to_tmp_file(media).await?;
+
+        let sprite_id = Uuid::new_v4();
+        let sprite_writer = self.blobs().add(&sprite_id).await?;
+
+        let storyboard = import_storyboard(
+            media,
+            tmp_file.path(),
+            sprite_id,
+            Box::into_pin(sprite_writer),
+        )
+        .await;
+
+        let mut storyboard = match storyboard {
+            Ok(Some(storyboard)) => storyboard,
+            Ok(None) => {
+                let _ = self.blobs().delete(&sprite_id).await;
+                return Ok(None);
+            }
+            Err(e) => {
+                let _ = self.blobs().delete(&sprite_id).await;
+                return Err(e);
+            }
+        };
+
+        if let ManyToOne::Obj(sprite) = storyboard.sprite {
+            storyboard.sprite = ManyToOne::Obj(self.add_or_get_media(sprite).await?);
+        }
+
+        self.content().set_storyboard(media.id, &storyboard).await?;
+
+        Ok(Some(storyboard))
+    }
+
+    async fn create_hls_stream(
+        &self,
+        media: &Media,
+        ladder: bool,
+    ) -> Result<Vec<HlsVariant>, MediaImportError> {
+        let specs = hls_variant_specs(media, ladder);
+
+        if specs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let Some(_guard) = InFlightGuard::new(&self.segmenting, media.id) else {
+            return Ok(Vec::new());
+        };
+
+        // segmenting shares the permits of the rendition conversions
+        let _permit = self
+            .transcodes
+            .acquire()
+            .await
+            .expect("the transcode semaphore is never closed");
+
+        let existing = self.media().get_hls_variants(media.id).await?;
+
+        if !existing.is_empty() {
+            return Ok(existing);
+        }
+
+        let (Some(width), Some(height)) = (media.metadata.width(), media.metadata.height()) else {
+            return Ok(Vec::new());
+        };
+
+        let original = Dimensions {
+            width: *width,
+            height: *height,
+        };
+
+        let tmp_file = self.copy_to_tmp_file(media).await?;
+        let mut variants: Vec<(HlsVariant, Vec<HlsSegment>)> = Vec::with_capacity(specs.len());
+
+        // all variants are segmented before any is stored, so a failure leaves no partial stream
+        for (position, spec) in specs.iter().enumerate() {
+            let dir = TmpDir::new().await?;
+
+            let segments = match segment_hls(media, tmp_file.path(), spec, dir.path()).await {
+                Ok(segment_files) => self.store_hls_segments(&segment_files).await,
+                Err(e) => Err(e),
+            };
+
+            let segments = match segments {
+                Ok(segments) => segments,
+                Err(e) => {
+                    for (_, segments) in variants.iter() {
+                        self.delete_hls_segments(segments).await;
+                    }
+
+                    return Err(e);
+                }
+            };
+
+            let variant = HlsVariant {
+                media_id: media.id,
+                variant: position as i32,
+                dims: spec.dims(&original),
+                bandwidth: peak_bandwidth(&segments),
+                average_bandwidth: average_bandwidth(&segments),
+            };
+
+            variants.push((variant, segments));
+        }
+
+        for (variant, segments) in variants.iter() {
+            self.media().add_hls_variant(variant, segments).await?;
+        }
+
+        Ok(variants.into_iter().map(|(variant, _)| variant).collect())
+    }
+}
+
+/// The highest bitrate of a segment in bits per second.
+fn peak_bandwidth(segments: &[HlsSegment]) -> u64 {
+    segments
+        .iter()
+        .filter(|segment| segment.duration > 0.0)
+        .map(|segment| (segment.file_size as f64 * 8.0 / segment.duration).ceil() as u64)
+        .max()
+        .unwrap_or_default()
+}
+
+/// The bitrate of all segments together in bits per second.
+fn average_bandwidth(segments: &[HlsSegment]) -> u64 {
+    let duration: f64 = segments.iter().map(|segment| segment.duration).sum();
+    let size: usize = segments.iter().map(|segment| segment.file_size).sum();
+
+    if duration > 0.0 {
+        (size as f64 * 8.0 / duration).ceil() as u64
+    } else {
+        0
+    }
 }
 
 impl<FileStorage: BlobDataSource, Passwords: PasswordDataSource>
     LocalDataSource<FileStorage, Passwords>
 {
+    /// Copy the blob of a media file to a temporary file, so that it can be read by other programs.
+    async fn copy_to_tmp_file(&self, media: &Media) -> Result<TmpFile, MediaImportError> {
+        let tmp_file = TmpFile::new().await?;
+        let mut file = tokio::fs::File::create(tmp_file.path()).await?;
+        let source_stream = self.blobs().get_by_id(&media.file_id).await?;
+
+        tokio::io::copy(&mut Box::into_pin(source_stream), &mut file).await?;
+
+        Ok(tmp_file)
+    }
+
+    /// Write segment files as blobs, the blobs are deleted again if one cannot be written.
+    async fn store_hls_segments(
+        &self,
+        segment_files: &[(f64, PathBuf)],
+    ) -> Result<Vec<HlsSegment>, MediaImportError> {
+        let mut segments = Vec::with_capacity(segment_files.len());
+
+        for (position, (duration, path)) in segment_files.iter().enumerate() {
+            let file_id = Uuid::new_v4();
+
+            let file_size = match self.store_blob(&file_id, path).await {
+                Ok(file_size) => file_size,
+                Err(e) => {
+                    let _ = self.blobs().delete(&file_id).await;
+                    self.delete_hls_segments(&segments).await;
+
+                    return Err(e);
+                }
+            };
+
+            segments.push(HlsSegment {
+                position: position as i32,
+                duration: *duration,
+                file_id,
+                file_size,
+            });
+        }
+
+        Ok(segments)
+    }
+
+    async fn store_blob(&self, file_id: &Uuid, path: &Path) -> Result<usize, MediaImportError> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut writer = Box::into_pin(self.blobs().add(file_id).await?);
+
+        let file_size = tokio::io::copy(&mut file, &mut writer).await?;
+        writer.flush().await?;
+
+        Ok(file_size as usize)
+    }
+
+    async fn delete_hls_segments(&self, segments: &[HlsSegment]) {
+        for segment in segments {
+            let _ = self.blobs().delete(&segment.file_id).await;
+        }
+    }
+
+    /// Replace the importers that are used to import media, e.g. to add support for another file type.
+    pub fn set_importers(&mut self, importers: MediaImporterRegistry) {
+        self.importers = importers;
+    }
+
+    /// Extract the text of the media file at `path` and store it.
+    /// Pdfs are only recognised with ocr if they have no text layer.
+    async fn store_text(
+        &self,
+        media: &Media,
+        path: &Path,
+        kind: MediaTextKind,
+    ) -> Result<bool, MediaImportError> {
+        let text = match kind {
+            MediaTextKind::Document => extract_document_text(media.mime.to_ref(), path).await?,
+            MediaTextKind::Ocr => {
+                let has_text_layer = self
+                    .media()
+                    .get_text(media.id, MediaTextKind::Document)
+                    .await?
+                    .is_some();
+
+                if has_text_layer {
+                    return Ok(false);
+                }
+
+                extract_ocr_text(media.mime.to_ref(), path).await?
+            }
+        };
+
+        let Some(text) = text else {
+            return Ok(false);
+        };
+
+        self.media()
+            .set_text(
+                media.id,
+                kind,
+                Some(text.as_str()).filter(|text| !text.is_empty()),
+            )
+            .await?;
+
+        Ok(true)
+    }
+
     async fn add_or_get_media(&self, mut media: Media) -> Result<Media, DataSourceError> {
         if let Some(existing) = self.media().get_by_sha256(&media.sha256).await? {
             self.blobs().delete(&media.file_id).await?;
@@ -4,19 +4,71 @@ use std::str::FromStr;
 use std::time::Duration;
 
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use futures::TryStreamExt;
 use mediatype::MediaTypeBuf;
 use sqlx::sqlite::{
     SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow, SqliteSynchronous,
 };
-use sqlx::{ConnectOptions, Executor, Row, Sqlite, SqlitePool};
+use sqlx::{ConnectOptions, Executor, Row, Sqlite, SqliteConnection, SqlitePool, ValueRef};
 use thiserror::Error;
 use uuid::Uuid;
 
 use crate::data_source::*;
 use crate::model::*;
 
+/// The length of a degree of latitude in meters.
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// The columns besides those of `posts p` that are needed to map a `SearchPost`.
+/// Gifs that were imported before the frames were counted are assumed to be animated.
+const SEARCH_POST_COLUMNS: &str = "m.*, pi.original_name,
+        (SELECT COUNT(*) FROM post_items pi WHERE pi.post_id = p.post_id) as 'item_count',
+        (SELECT SUM(c.duration) FROM post_items pi JOIN media c ON pi.content_id = c.media_id WHERE pi.post_id = p.post_id) as 'total_duration',
+        (SELECT COUNT(*) FROM media WHERE media_id IN (SELECT content_id FROM post_items WHERE post_id = p.post_id) AND mime_type = 'image' AND NOT COALESCE(frame_count > 1, mime_sub_type = 'gif')) as 'contains_image',
+        (SELECT COUNT(*) FROM media WHERE media_id IN (SELECT content_id FROM post_items WHERE post_id = p.post_id) AND mime_type = 'video') as 'contains_video',
+        (SELECT COUNT(*) FROM media WHERE media_id IN (SELECT content_id FROM post_items WHERE post_id = p.post_id) AND mime_type = 'application' AND mime_sub_type != 'pdf') as 'contains_document',
+        (SELECT COUNT(*) FROM media WHERE media_id IN (SELECT content_id FROM post_items WHERE post_id = p.post_id) AND mime_type = 'image' AND COALESCE(frame_count > 1, mime_sub_type = 'gif')) as 'contains_moving_image',
+        (SELECT COUNT(*) FROM media WHERE media_id IN (SELECT content_id FROM post_items WHERE post_id = p.post_id) AND mime_type = 'audio') as 'contains_audio'";
+
+/// The joins that select the first item and its thumbnail for `SEARCH_POST_COLUMNS`.
+const SEARCH_POST_JOINS: &str = "LEFT JOIN (SELECT * FROM post_items ORDER BY item_order ASC) pi ON pi.post_id = p.post_id AND pi.item_order = 0
+        LEFT JOIN content c ON pi.content_id = c.content_id
+        LEFT JOIN media m ON c.thumbnail_id = m.media_id";
+
+/// How often the time a resized image was last used is written, in seconds.
+const RESIZED_MEDIA_TOUCH_INTERVAL_SECS: i64 = 60 * 10;
+
+/// The fragment of the best matching column that is returned with text searches.
+const SEARCH_SNIPPET: &str = "snippet(posts_vtab, -1, '\u{2}', '\u{3}', '…', 32)";
+
+/// The markers placed around every match of a text search, replaced with `<mark>` once the text is escaped.
+const SEARCH_MATCH_START: char = '\u{2}';
+const SEARCH_MATCH_END: char = '\u{3}';
+
+/// The `posts_vtab` columns that are highlighted with their column index
+/// and whether they are long enough to be shortened to a snippet.
+const SEARCH_HIGHLIGHT_COLUMNS: [(PostSearchColumn, usize, bool); 12] = [
+    (PostSearchColumn::Source, 1, false),
+    (PostSearchColumn::Title, 2, false),
+    (PostSearchColumn::Description, 3, true),
+    (PostSearchColumn::Tags, 6, false),
+    (PostSearchColumn::OriginalName, 7, false),
+    (PostSearchColumn::OriginalDirectory, 8, false),
+    (PostSearchColumn::DocumentTitle, 9, false),
+    (PostSearchColumn::DocumentAuthor, 10, false),
+    (PostSearchColumn::ContentText, 11, true),
+    (PostSearchColumn::AudioArtist, 12, false),
+    (PostSearchColumn::AudioAlbum, 13, false),
+    (PostSearchColumn::AudioTitle, 14, false),
+];
+
+/// The score added to similar posts that link to the same domain.
+const SIMILAR_SOURCE_BOOST: f64 = 2.0;
+
+/// The score added to similar posts that were imported in the same batch.
+const SIMILAR_BATCH_BOOST: f64 = 1.0;
+
 #[derive(Error, Debug)]
 pub enum SqliteError {
     #[error("Invalid path")]
@@ -160,6 +212,29 @@ impl SqliteIndex {
                         width: row.try_get("width")?,
                         height: row.try_get("height")?,
                     },
+                    exif: ExifMetadata {
+                        camera_make: row.try_get("camera_make")?,
+                        camera_model: row.try_get("camera_model")?,
+                        lens: row.try_get("lens")?,
+                        exposure_time: row.try_get("exposure_time")?,
+                        f_number: row.try_get("f_number")?,
+                        iso: row.try_get("iso")?,
+                        focal_length: row.try_get("focal_length")?,
+                        orientation: row.try_get("orientation")?,
+                        taken_at: row.try_get("taken_at")?,
+                        latitude: row.try_get("latitude")?,
+                        longitude: row.try_get("longitude")?,
+                    },
+                    animation: match (
+                        row.try_get("frame_count")?,
+                        row.try_get("loop_duration_ms")?,
+                    ) {
+                        (Some(frame_count), Some(loop_duration_ms)) => Some(Animation {
+                            frame_count,
+                            loop_duration_ms,
+                        }),
+                        _ => None,
+                    },
                 },
                 (_, "pdf") => MediaMetadata::Document {
                     pages: row.try_get("document_pages")?,
@@ -170,6 +245,15 @@ impl SqliteIndex {
                         height: row.try_get("page_height")?,
                     },
                 },
+                ("audio", _) => MediaMetadata::Audio {
+                    duration: row.try_get("duration")?,
+                    bitrate: row.try_get("audio_bitrate")?,
+                    sample_rate: row.try_get("audio_sample_rate")?,
+                    artist: row.try_get("audio_artist")?,
+                    album: row.try_get("audio_album")?,
+                    title: row.try_get("audio_title")?,
+                    track: row.try_get("audio_track")?,
+                },
                 _ => MediaMetadata::Unknown,
             },
             file_id: Uuid::from_bytes(file_id.try_into().unwrap()),
@@ -205,6 +289,9 @@ impl SqliteIndex {
             description: row.try_get("description")?,
             import_batch: ManyToOne::Id(row.try_get::<'_, i64, _>("import_batch_id")? as u64),
             created_at: row.try_get("created_at")?,
+            deleted_at: row.try_get("deleted_at")?,
+            rating: row.try_get("rating")?,
+            favourite: row.try_get("favourite")?,
         })
     }
 
@@ -212,6 +299,9 @@ impl SqliteIndex {
         Ok(Content {
             content: ManyToOne::Id(row.try_get::<'_, i64, _>("content_id")? as u64),
             thumbnail: ManyToOne::Id(row.try_get::<'_, i64, _>("thumbnail_id")? as u64),
+            rendition: row
+                .try_get::<'_, Option<i64>, _>("compatibility_content_id")?
+                .map(|id| ManyToOne::Id(id as u64)),
         })
     }
 
@@ -233,6 +323,23 @@ impl SqliteIndex {
         })
     }
 
+    /// lift = P(a and b) / (P(a) * P(b)) = (co-occurrences / usage of a) * posts / usage of b
+    fn map_related_tag(
+        row: &SqliteRow,
+        post_count: u64,
+        other_tag_count: u64,
+    ) -> Result<RelatedTag, DataSourceError> {
+        let tag = Self::map_search_tag(row)?;
+        let confidence: f64 = row.try_get("confidence")?;
+
+        Ok(RelatedTag {
+            count: row.try_get::<'_, i64, _>("co_count")? as u64,
+            lift: (confidence / other_tag_count as f64 * post_count as f64
+                / tag.linked_posts.max(1) as f64) as f32,
+            tag,
+        })
+    }
+
     fn map_full_tag(row: &SqliteRow) -> Result<Tag, DataSourceError> {
         let group: Option<i64> = row.try_get("group_id").ok();
         let group_color: Option<String> = row.try_get("color").ok();
@@ -266,6 +373,198 @@ impl SqliteIndex {
         })
     }
 
+    fn map_mime_type_stats(row: &SqliteRow) -> Result<MimeTypeStats, DataSourceError> {
+        Ok(MimeTypeStats {
+            mime_type: row.try_get("mime_type")?,
+            mime_sub_type: row.try_get("mime_sub_type")?,
+            file_count: row.try_get::<'_, i64, _>("file_count")? as u64,
+            total_file_size: row.try_get::<'_, i64, _>("total_file_size")? as u64,
+        })
+    }
+
+    fn map_post_stats(row: &SqliteRow) -> Result<PostStats, DataSourceError> {
+        Ok(PostStats {
+            post: Self::map_post(row)?,
+            item_count: row.try_get::<'_, i64, _>("item_count")? as u64,
+            total_file_size: row.try_get::<'_, i64, _>("total_file_size")? as u64,
+        })
+    }
+
+    fn map_tag_group_stats(row: &SqliteRow) -> Result<TagGroupStats, DataSourceError> {
+        let group_id: Option<i64> = row.try_get("group_id")?;
+
+        Ok(TagGroupStats {
+            group: group_id.map(|_| Self::map_tag_group(row)).transpose()?,
+            tag_count: row.try_get::<'_, i64, _>("tag_count")? as u64,
+            post_count: row.try_get::<'_, i64, _>("post_count")? as u64,
+        })
+    }
+
+    fn map_monthly_growth(row: &SqliteRow) -> Result<MonthlyGrowth, DataSourceError> {
+        Ok(MonthlyGrowth {
+            month: row.try_get("month")?,
+            post_count: row.try_get::<'_, i64, _>("post_count")? as u64,
+            item_count: row.try_get::<'_, i64, _>("item_count")? as u64,
+            total_file_size: row.try_get::<'_, i64, _>("total_file_size")? as u64,
+        })
+    }
+
+    fn map_import_batch(row: &SqliteRow) -> Result<ImportBatch, DataSourceError> {
+        let origin: &str = row.try_get("origin")?;
+
+        Ok(ImportBatch {
+            id: row.try_get::<'_, i64, _>("import_batch_id")? as u64,
+            name: row.try_get("name")?,
+            origin: match origin {
+                "web" => ImportBatchOrigin::Web,
+                "sync" => ImportBatchOrigin::Sync,
+                _ => ImportBatchOrigin::Unknown,
+            },
+            started_at: row.try_get("started_at")?,
+            finished_at: row.try_get("finished_at")?,
+        })
+    }
+
+    fn map_search_import_batch(row: &SqliteRow) -> Result<SearchImportBatch, DataSourceError> {
+        Ok(SearchImportBatch {
+            batch: Self::map_import_batch(row)?,
+            post_count: row.try_get::<'_, i64, _>("post_count")? as u64,
+        })
+    }
+
+    fn map_hls_segment(
+        (position, duration, file_id, file_size): (i32, f64, Vec<u8>, i64),
+    ) -> HlsSegment {
+        HlsSegment {
+            position,
+            duration,
+            file_id: Uuid::from_bytes(file_id.try_into().unwrap()),
+            file_size: file_size as usize,
+        }
+    }
+
+    fn media_text_kind_str(kind: MediaTextKind) -> &'static str {
+        match kind {
+            MediaTextKind::Document => "document",
+            MediaTextKind::Ocr => "ocr",
+        }
+    }
+
+    fn custom_field_type_str(field_type: CustomFieldType) -> &'static str {
+        match field_type {
+            CustomFieldType::String => "string",
+            CustomFieldType::Number => "number",
+            CustomFieldType::Date => "date",
+            CustomFieldType::Url => "url",
+        }
+    }
+
+    /// Dates are stored in UTC with whole seconds, so comparing them as text orders them by time.
+    fn custom_date_str(date: &DateTime<Utc>) -> String {
+        date.to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+
+    /// The condition on the value of a date field, bound with `custom_date_bounds`.
+    fn custom_date_condition(operator: CustomFieldOperator) -> &'static str {
+        match operator {
+            CustomFieldOperator::Exists => "1",
+            CustomFieldOperator::Eq => "(pf.value >= ? AND pf.value <= ?)",
+            CustomFieldOperator::Ne => "NOT (pf.value >= ? AND pf.value <= ?)",
+            CustomFieldOperator::Lt => "pf.value < ?",
+            CustomFieldOperator::Le => "pf.value <= ?",
+            CustomFieldOperator::Gt => "pf.value > ?",
+            CustomFieldOperator::Ge => "pf.value >= ?",
+        }
+    }
+
+    fn custom_date_bounds(predicate: &CustomFieldPredicate) -> Vec<Option<String>> {
+        let start = predicate.date_start.as_ref().map(Self::custom_date_str);
+        let end = predicate.date_end.as_ref().map(Self::custom_date_str);
+
+        match predicate.operator {
+            CustomFieldOperator::Exists => vec![],
+            CustomFieldOperator::Eq | CustomFieldOperator::Ne => vec![start, end],
+            CustomFieldOperator::Lt | CustomFieldOperator::Ge => vec![start],
+            CustomFieldOperator::Le | CustomFieldOperator::Gt => vec![end],
+        }
+    }
+
+    fn map_custom_field(row: &SqliteRow) -> Result<CustomField, DataSourceError> {
+        let field_type: String = row.try_get("field_type")?;
+
+        Ok(CustomField {
+            id: row.try_get::<'_, i64, _>("field_id")? as u64,
+            name: row.try_get("name")?,
+            field_type: match field_type.as_str() {
+                "string" => CustomFieldType::String,
+                "number" => CustomFieldType::Number,
+                "date" => CustomFieldType::Date,
+                "url" => CustomFieldType::Url,
+                _ => {
+                    return Err(DataSourceError::UnhandledError {
+                        message: format!("unknown custom field type \"{field_type}\""),
+                        inner_error: None,
+                    })
+                }
+            },
+            created_at: row.try_get("created_at")?,
+        })
+    }
+
+    fn map_post_custom_field(row: &SqliteRow) -> Result<PostCustomField, DataSourceError> {
+        let field = Self::map_custom_field(row)?;
+
+        let value = match field.field_type {
+            CustomFieldType::Number => CustomFieldValue::Number(row.try_get("value")?),
+            CustomFieldType::String => CustomFieldValue::String(row.try_get("value")?),
+            CustomFieldType::Date => {
+                let value: String = row.try_get("value")?;
+                CustomFieldValue::Date(
+                    DateTime::parse_from_rfc3339(&value)
+                        .map_err(|e| DataSourceError::UnhandledError {
+                            message: format!("invalid date \"{value}\""),
+                            inner_error: Some(e.to_string()),
+                        })?
+                        .with_timezone(&Utc),
+                )
+            }
+            CustomFieldType::Url => {
+                let value: String = row.try_get("value")?;
+                CustomFieldValue::Url(value.parse().map_err(|e: url::ParseError| {
+                    DataSourceError::UnhandledError {
+                        message: format!("invalid url \"{value}\""),
+                        inner_error: Some(e.to_string()),
+                    }
+                })?)
+            }
+        };
+
+        Ok(PostCustomField { field, value })
+    }
+
+    /// Copy the custom field values of one post to another, keeping the values the target already has.
+    async fn copy_custom_fields(
+        conn: &mut SqliteConnection,
+        from_post_id: u64,
+        to_post_id: u64,
+    ) -> Result<(), DataSourceError> {
+        sqlx::query("INSERT OR IGNORE INTO posts_custom_fields(post_id, field_id, value) SELECT ?, field_id, value FROM posts_custom_fields WHERE post_id = ?")
+            .bind(to_post_id as i64)
+            .bind(from_post_id as i64)
+            .execute(conn)
+            .await?;
+
+        Ok(())
+    }
+
+    fn import_batch_origin_str(origin: ImportBatchOrigin) -> &'static str {
+        match origin {
+            ImportBatchOrigin::Web => "web",
+            ImportBatchOrigin::Sync => "sync",
+            ImportBatchOrigin::Unknown => "unknown",
+        }
+    }
+
     fn map_search_post(row: &SqliteRow) -> Result<SearchPost, DataSourceError> {
         let thumbnail = (row.try_get::<'_, Option<i64>, _>("media_id")?)
             .and_then(|_| Self::map_media(row).ok());
@@ -276,29 +575,108 @@ impl SqliteIndex {
             contains_video: row.try_get("contains_video")?,
             contains_document: row.try_get("contains_document")?,
             contains_moving_image: row.try_get("contains_moving_image")?,
+            contains_audio: row.try_get("contains_audio")?,
             item_count: row.try_get::<'_, i64, _>("item_count")? as usize,
             duration: row.try_get("total_duration")?,
             thumbnail,
             file_name: row.try_get("original_name")?,
+            snippet: row
+                .try_get::<'_, Option<String>, _>("snippet")?
+                .map(|snippet| Self::mark_matches(&snippet)),
+            highlights: Vec::new(),
         })
     }
 
-    fn map_graph_point(row: &SqliteRow) -> Result<GraphPoint, DataSourceError> {
-        let kind: &str = row.try_get(2)?;
-
-        Ok(GraphPoint {
-            y: row.try_get(0)?,
-            x: match kind {
-                "date" => GraphValue::Date(row.try_get(1)?),
-                "none" => GraphValue::None,
-                _ => {
-                    return Err(DataSourceError::SQLError(sqlx::Error::ColumnDecode {
-                        source: "map_graph_point".into(),
-                        index: String::from("2"),
-                    }));
+    fn search_highlight_columns() -> String {
+        SEARCH_HIGHLIGHT_COLUMNS
+            .iter()
+            .map(|(_, index, is_long)| {
+                if *is_long {
+                    format!("snippet(posts_vtab, {index}, '{SEARCH_MATCH_START}', '{SEARCH_MATCH_END}', '…', 64) as 'highlight_{index}'")
+                } else {
+                    format!("highlight(posts_vtab, {index}, '{SEARCH_MATCH_START}', '{SEARCH_MATCH_END}') as 'highlight_{index}'")
                 }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn map_search_highlights(row: &SqliteRow) -> Result<Vec<SearchHighlight>, DataSourceError> {
+        let mut highlights = Vec::new();
+
+        for (column, index, _) in SEARCH_HIGHLIGHT_COLUMNS {
+            let fragment: Option<String> = row.try_get(format!("highlight_{index}").as_str())?;
+
+            // columns without a match are returned unmarked
+            if let Some(fragment) = fragment.filter(|f| f.contains(SEARCH_MATCH_START)) {
+                highlights.push(SearchHighlight {
+                    column,
+                    fragment: Self::mark_matches(&fragment),
+                });
+            }
+        }
+
+        Ok(highlights)
+    }
+
+    /// HTML-escape a matched fragment and wrap its matches in `<mark>`.
+    fn mark_matches(fragment: &str) -> String {
+        let mut marked = String::with_capacity(fragment.len());
+
+        for c in fragment.chars() {
+            match c {
+                SEARCH_MATCH_START => marked.push_str("<mark>"),
+                SEARCH_MATCH_END => marked.push_str("</mark>"),
+                '&' => marked.push_str("&amp;"),
+                '<' => marked.push_str("&lt;"),
+                '>' => marked.push_str("&gt;"),
+                '"' => marked.push_str("&quot;"),
+                '\'' => marked.push_str("&#39;"),
+                c => marked.push(c),
+            }
+        }
+
+        marked
+    }
+
+    fn map_graph_row(
+        row: &SqliteRow,
+        series_count: usize,
+    ) -> Result<(GraphValue, Vec<f32>), DataSourceError> {
+        let kind: &str = row.try_get("kind")?;
+        let is_null = row.try_get_raw("x")?.is_null();
+
+        let x = match kind {
+            _ if is_null => GraphValue::None,
+            "date" => GraphValue::Date(row.try_get("x")?),
+            "tag" => GraphValue::Tag {
+                id: row.try_get::<'_, i64, _>("x")? as u64,
+                name: row.try_get("x_name")?,
             },
-        })
+            "tag_group" => GraphValue::TagGroup {
+                id: row.try_get::<'_, i64, _>("x")? as u64,
+                name: row.try_get("x_name")?,
+            },
+            "mime_type" => GraphValue::MimeType(row.try_get("x")?),
+            "import_batch" => GraphValue::ImportBatch {
+                id: row.try_get::<'_, i64, _>("x")? as u64,
+                name: row.try_get("x_name")?,
+            },
+            "dimension" => GraphValue::Dimension(row.try_get::<'_, i64, _>("x")? as u32),
+            "none" => GraphValue::None,
+            _ => {
+                return Err(DataSourceError::SQLError(sqlx::Error::ColumnDecode {
+                    source: "map_graph_row".into(),
+                    index: String::from("kind"),
+                }));
+            }
+        };
+
+        let ys = (0..series_count)
+            .map(|i| row.try_get(format!("y{i}").as_str()))
+            .collect::<Result<Vec<f32>, _>>()?;
+
+        Ok((x, ys))
     }
 
     fn map_full_post_item(row: &SqliteRow) -> Result<PostItem, DataSourceError> {
@@ -308,6 +686,9 @@ impl SqliteIndex {
             content: ManyToOne::Obj(Content {
                 content: ManyToOne::Obj(Self::map_media(row)?),
                 thumbnail: ManyToOne::Id(row.try_get::<'_, i64, _>("thumbnail_id")? as u64),
+                rendition: row
+                    .try_get::<'_, Option<i64>, _>("compatibility_content_id")?
+                    .map(|id| ManyToOne::Id(id as u64)),
             }),
             upload: UploadMetadata {
                 original_filename: row.try_get("original_name")?,
@@ -324,17 +705,33 @@ impl SqliteIndex {
             .parse()
             .unwrap();
 
+        // gifs that were imported before the frames were counted are assumed to be animated
+        let is_moving_image = mime.ty() == mediatype::names::IMAGE
+            && row
+                .try_get::<'_, Option<i32>, _>("content_frame_count")?
+                .map_or(mime.subty() == mediatype::names::GIF, |frames| frames > 1);
+
         Ok(SearchPostItem {
             item: Self::map_post_item(row)?,
-            contains_image: mime.ty() == mediatype::names::IMAGE
-                && mime.subty() != mediatype::names::GIF,
+            contains_image: mime.ty() == mediatype::names::IMAGE && !is_moving_image,
             contains_video: mime.ty() == mediatype::names::VIDEO,
-            contains_moving_image: mime.subty() == mediatype::names::GIF,
+            contains_moving_image: is_moving_image,
             contains_document: mime.ty() != mediatype::names::APPLICATION
                 && mime.subty() == mediatype::names::PDF,
+            contains_audio: mime.ty() == mediatype::names::AUDIO,
             duration: row.try_get("content_duration")?,
             thumbnail: (row.try_get::<'_, Option<i64>, _>("media_id")?)
                 .and_then(|_| Self::map_media(row).ok()),
+            progress: match (
+                row.try_get("watch_position")?,
+                row.try_get("watch_updated_at")?,
+            ) {
+                (Some(position), Some(updated_at)) => Some(WatchProgress {
+                    position,
+                    updated_at,
+                }),
+                _ => None,
+            },
         })
     }
 
@@ -343,18 +740,27 @@ impl SqliteIndex {
         before_where: &'a str,
         after_where: &'a str,
     ) -> String {
-        let mut where_clause = String::new();
+        let mut where_clause = String::from("WHERE");
+
+        if query.trashed {
+            where_clause
+                .push_str(" p.post_id IN (SELECT post_id FROM posts WHERE deleted_at IS NOT NULL)");
+        } else {
+            where_clause.push_str(
+                " p.post_id NOT IN (SELECT post_id FROM posts WHERE deleted_at IS NOT NULL)",
+            );
+        }
 
         if query.has_criteria() {
-            let mut is_first = true;
+            where_clause.push_str(" AND");
 
-            where_clause.push_str("WHERE");
+            let mut is_first = true;
 
             if let Some(text) = query.text.as_deref() {
                 let query_is_empty = text.len() < 3;
 
                 if query_is_empty {
-                    where_clause.push_str(" (p.source LIKE ? OR p.title LIKE ? OR p.description LIKE ? OR p.tags LIKE ? OR p.original_name LIKE ? OR p.original_directory LIKE ? OR p.document_title LIKE ? OR p.document_author LIKE ?)");
+                    where_clause.push_str(" (p.source LIKE ? OR p.title LIKE ? OR p.description LIKE ? OR p.tags LIKE ? OR p.original_name LIKE ? OR p.original_directory LIKE ? OR p.document_title LIKE ? OR p.document_author LIKE ? OR p.content_text LIKE ? OR p.audio_artist LIKE ? OR p.audio_album LIKE ? OR p.audio_title LIKE ?)");
                 } else {
                     where_clause.push_str(" posts_vtab MATCH (?)")
                 }
@@ -381,7 +787,7 @@ impl SqliteIndex {
                     where_clause.push_str(" AND")
                 }
 
-                where_clause.push_str(" total_duration > 0");
+                where_clause.push_str(" EXISTS(SELECT pi.post_id FROM post_items pi JOIN media m ON pi.content_id = m.media_id WHERE pi.post_id = p.post_id AND m.duration > 0)");
 
                 is_first = false;
             }
@@ -392,6 +798,123 @@ impl SqliteIndex {
                 }
 
                 where_clause.push_str(" p.source = ?");
+
+                is_first = false;
+            }
+
+            if query.batch_id.is_some() {
+                if !is_first {
+                    where_clause.push_str(" AND")
+                }
+
+                where_clause.push_str(" p.import_batch_id = ?");
+
+                is_first = false;
+            }
+
+            // the virtual table does not mirror the rating columns
+            if query.min_rating.is_some() {
+                if !is_first {
+                    where_clause.push_str(" AND")
+                }
+
+                where_clause
+                    .push_str(" p.post_id IN (SELECT post_id FROM posts WHERE rating >= ?)");
+
+                is_first = false;
+            }
+
+            if query.max_rating.is_some() {
+                if !is_first {
+                    where_clause.push_str(" AND")
+                }
+
+                where_clause
+                    .push_str(" p.post_id IN (SELECT post_id FROM posts WHERE rating <= ?)");
+
+                is_first = false;
+            }
+
+            if query.favourite.is_some() {
+                if !is_first {
+                    where_clause.push_str(" AND")
+                }
+
+                where_clause
+                    .push_str(" p.post_id IN (SELECT post_id FROM posts WHERE favourite = ?)");
+
+                is_first = false;
+            }
+
+            if query.in_progress {
+                if !is_first {
+                    where_clause.push_str(" AND")
+                }
+
+                where_clause.push_str(" EXISTS(SELECT pi.post_id FROM post_items pi LEFT JOIN media m ON pi.content_id = m.media_id WHERE pi.post_id = p.post_id AND pi.watch_position > 0 AND (m.duration IS NULL OR pi.watch_position < m.duration))");
+
+                is_first = false;
+            }
+
+            // numbers are stored as REAL so the value is cast to compare them numerically,
+            // dates are compared with the range of time the value covers
+            for predicate in query.fields.iter() {
+                if !is_first {
+                    where_clause.push_str(" AND")
+                }
+
+                where_clause.push_str(" EXISTS(SELECT pf.post_id FROM posts_custom_fields pf JOIN custom_fields f ON f.field_id = pf.field_id WHERE pf.post_id = p.post_id AND f.name = ?");
+
+                if predicate.operator != CustomFieldOperator::Exists {
+                    where_clause.push_str(&format!(
+                        " AND (CASE f.field_type WHEN 'date' THEN {} ELSE pf.value {} (CASE f.field_type WHEN 'number' THEN CAST(? AS REAL) ELSE ? END) END)",
+                        Self::custom_date_condition(predicate.operator),
+                        predicate.operator.symbol()
+                    ));
+                }
+
+                where_clause.push(')');
+
+                is_first = false;
+            }
+
+            let crosses_antimeridian = query.bounds.is_some_and(|b| b.west > b.east);
+
+            // the distance to a point is approximated on a plane, which is close enough for
+            // the radius of a city, the latitude range lets the location index be used and the
+            // longitude difference wraps around the antimeridian
+            let media_filters = [
+                (
+                    query.camera.is_some(),
+                    "(m.camera_make LIKE ? OR m.camera_model LIKE ?)",
+                ),
+                (query.lens.is_some(), "m.lens LIKE ?"),
+                (query.min_iso.is_some(), "m.iso >= ?"),
+                (query.max_iso.is_some(), "m.iso <= ?"),
+                (query.taken_after.is_some(), "m.taken_at >= ?"),
+                (query.taken_before.is_some(), "m.taken_at <= ?"),
+                (
+                    query.bounds.is_some() && !crosses_antimeridian,
+                    "m.latitude BETWEEN ? AND ? AND m.longitude BETWEEN ? AND ?",
+                ),
+                (
+                    query.bounds.is_some() && crosses_antimeridian,
+                    "m.latitude BETWEEN ? AND ? AND (m.longitude >= ? OR m.longitude <= ?)",
+                ),
+                (
+                    query.near.is_some(),
+                    "m.latitude BETWEEN ? AND ? AND (m.latitude - ?) * (m.latitude - ?) + (180 - ABS(180 - ABS(m.longitude - ?))) * (180 - ABS(180 - ABS(m.longitude - ?))) * ? <= ?",
+                ),
+            ];
+
+            for (_, filter) in media_filters.iter().filter(|(enabled, _)| *enabled) {
+                if !is_first {
+                    where_clause.push_str(" AND")
+                }
+
+                where_clause.push_str(&format!(" EXISTS(SELECT pi.post_id FROM post_items pi JOIN media m ON pi.content_id = m.media_id WHERE pi.post_id = p.post_id AND {filter})"));
+
+                is_first = false;
             }
         }
 
@@ -420,9 +943,9 @@ impl SqliteIndex {
                     .filter(|x| !x.is_empty())
                     .map(|text| {
                         if text.contains(" ") {
-                            format!("({{title description source tags original_name original_directory document_title document_author}}: NEAR({}, 1000))", text)
+                            format!("({{title description source tags original_name original_directory document_title document_author content_text audio_artist audio_album audio_title}}: NEAR({}, 1000))", text)
                         } else {
-                            format!("({{title description source tags original_name original_directory document_title document_author}}: \"{}\")", text)
+                            format!("({{title description source tags original_name original_directory document_title document_author content_text audio_artist audio_album audio_title}}: \"{}\")", text)
                         }
                     })
                     .collect::<Vec<_>>()
@@ -438,6 +961,10 @@ impl SqliteIndex {
                 query = query.bind(format!("%{text}%"));
                 query = query.bind(format!("%{text}%"));
                 query = query.bind(format!("%{text}%"));
+                query = query.bind(format!("%{text}%"));
+                query = query.bind(format!("%{text}%"));
+                query = query.bind(format!("%{text}%"));
+                query = query.bind(format!("%{text}%"));
             }
         }
 
@@ -451,448 +978,532 @@ impl SqliteIndex {
             query = query.bind(source.as_str());
         }
 
-        query
-    }
+        if let Some(batch_id) = query_values.batch_id {
+            query = query.bind(batch_id as i64);
+        }
 
-    async fn post_update<'a, E: Executor<'a, Database = Sqlite>>(
-        &self,
-        value: &Post,
-        executor: E,
-    ) -> Result<(), DataSourceError> {
-        sqlx::query("UPDATE posts SET source = ?, title = ?, description = ?, created_at = ?, import_batch_id = ? WHERE post_id = ?")
-            .bind(value.source.as_ref().map(|url| url.as_str()))
-            .bind(value.title.as_deref())
-            .bind(value.description.as_deref())
-            .bind(value.created_at)
-            .bind(value.import_batch.id() as i64)
-            .bind(value.id as i64)
-            .execute(executor)
-            .await?;
+        if let Some(min_rating) = query_values.min_rating {
+            query = query.bind(min_rating);
+        }
 
-        Ok(())
-    }
-}
+        if let Some(max_rating) = query_values.max_rating {
+            query = query.bind(max_rating);
+        }
 
-#[async_trait]
-impl MediaDataSource for SqliteIndex {
-    async fn add(&self, value: &mut Media) -> Result<(), DataSourceError> {
-        let id = sqlx::query("INSERT INTO media(width, height, duration, mime_type, mime_sub_type, file_size, file_id, sha256, md5, sha1, document_pages, document_title, document_author, page_width, page_height, video_encoding) VALUES(?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)")
-            .bind(value.metadata.width())
-            .bind(value.metadata.height())
-            .bind(value.metadata.duration())
-            .bind(value.mime.ty().as_str())
-            .bind(value.mime.subty().as_str())
-            .bind(value.file_size as i64)
-            .bind(value.file_id.as_bytes().as_slice())
-            .bind(value.sha256.as_str())
-            .bind(value.md5.as_str())
-            .bind(value.sha1.as_str())
-            .bind(value.metadata.pages())
-            .bind(value.metadata.title())
-            .bind(value.metadata.author())
-            .bind(value.metadata.page_size().map(|s| s.width))
-            .bind(value.metadata.page_size().map(|s| s.height))
-            .bind(value.metadata.video_encoding())
-            .execute(&self.write_pool)
-            .await?
-            .last_insert_rowid();
+        if let Some(favourite) = query_values.favourite {
+            query = query.bind(favourite);
+        }
 
-        value.id = id as u64;
+        for predicate in query_values.fields.iter() {
+            query = query.bind(predicate.name.as_str());
 
-        Ok(())
-    }
+            for bound in Self::custom_date_bounds(predicate) {
+                query = query.bind(bound);
+            }
 
-    async fn remove(&self, value: &Media) -> Result<(), DataSourceError> {
-        sqlx::query("DELETE FROM media WHERE media_id = ?")
-            .bind(value.id as i64)
-            .execute(&self.write_pool)
-            .await?;
+            if predicate.operator != CustomFieldOperator::Exists {
+                let value = predicate.value.as_deref().unwrap_or_default();
+                query = query.bind(value).bind(value);
+            }
+        }
 
-        Ok(())
-    }
+        if let Some(camera) = query_values.camera.as_deref() {
+            query = query
+                .bind(format!("%{camera}%"))
+                .bind(format!("%{camera}%"));
+        }
 
-    async fn get_by_id(&self, id: u64) -> Result<Option<Media>, DataSourceError> {
-        let mut rows = sqlx::query("SELECT * FROM media WHERE media_id = ?")
-            .bind(id as i64)
-            .map(|r| Self::map_media(&r))
-            .fetch(&self.read_pool);
+        if let Some(lens) = query_values.lens.as_deref() {
+            query = query.bind(format!("%{lens}%"));
+        }
 
-        if let Some(row) = rows.try_next().await? {
-            Ok(Some(row?))
-        } else {
-            Ok(None)
+        if let Some(min_iso) = query_values.min_iso {
+            query = query.bind(min_iso);
         }
-    }
 
-    async fn get_by_sha256(&self, sha256: &str) -> Result<Option<Media>, DataSourceError> {
-        let mut rows = sqlx::query("SELECT * FROM media WHERE sha256 = ?")
-            .bind(sha256)
-            .map(|r| Self::map_media(&r))
-            .fetch(&self.read_pool);
+        if let Some(max_iso) = query_values.max_iso {
+            query = query.bind(max_iso);
+        }
 
-        if let Some(row) = rows.try_next().await? {
-            Ok(Some(row?))
-        } else {
-            Ok(None)
+        if let Some(taken_after) = query_values.taken_after {
+            query = query.bind(taken_after);
         }
-    }
 
-    async fn get_total_size(&self) -> Result<u64, DataSourceError> {
-        let total_size: (i64,) = sqlx::query_as("SELECT SUM(file_size) FROM media")
-            .fetch_one(&self.read_pool)
-            .await?;
+        if let Some(taken_before) = query_values.taken_before {
+            query = query.bind(taken_before);
+        }
 
-        Ok(total_size.0 as u64)
-    }
+        if let Some(bounds) = query_values.bounds {
+            query = query
+                .bind(bounds.south)
+                .bind(bounds.north)
+                .bind(bounds.west)
+                .bind(bounds.east);
+        }
 
-    async fn get_count(&self) -> Result<u64, DataSourceError> {
-        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM media")
-            .fetch_one(&self.read_pool)
-            .await?;
+        if let Some(near) = query_values.near {
+            let radius = near.radius / METERS_PER_DEGREE;
+            // a degree of longitude gets shorter towards the poles
+            let longitude_scale = near.latitude.to_radians().cos().powi(2);
+
+            query = query
+                .bind(near.latitude - radius)
+                .bind(near.latitude + radius)
+                .bind(near.latitude)
+                .bind(near.latitude)
+                .bind(near.longitude)
+                .bind(near.longitude)
+                .bind(longitude_scale)
+                .bind(radius * radius);
+        }
 
-        Ok(count.0 as u64)
+        query
     }
-}
 
-#[async_trait]
-impl PostItemDataSource for SqliteIndex {
-    async fn add(&self, value: &mut PostItem) -> Result<(), DataSourceError> {
-        sqlx::query("INSERT INTO post_items(post_id, item_order, content_id) VALUES(?,?,?)")
-            .bind(value.post.id() as i64)
-            .bind(value.position)
-            .bind(value.content.id() as i64)
-            .execute(&self.write_pool)
-            .await?;
+    /// Page through the tags of a co-occurrence query, which must select `tag_id`, `co_count`
+    /// and `confidence` (the co-occurrences divided by the usage of the other tags) and
+    /// contain `id_binds` parameters that are all bound to `id`.
+    async fn get_co_occurring_tags(
+        &self,
+        co_occurrences: &str,
+        id: u64,
+        id_binds: usize,
+        other_tag_count: u64,
+        page: &PageParams,
+    ) -> Result<Page<RelatedTag>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
 
-        Ok(())
-    }
+        let (post_count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM posts")
+            .fetch_one(conn.deref_mut())
+            .await?;
 
-    async fn get_by_id(
-        &self,
-        post_item: u64,
-        position: i32,
-    ) -> Result<Option<PostItem>, DataSourceError> {
-        let mut rows = sqlx::query("SELECT * FROM post_items WHERE post_id = ? AND item_order = ?")
-            .bind(post_item as i64)
-            .bind(position)
-            .map(|r| Self::map_post_item(&r))
-            .fetch(&self.read_pool);
+        let count_query_str = format!("SELECT COUNT(*) FROM ({co_occurrences})");
+        let mut count_query = sqlx::query_as(&count_query_str);
 
-        if let Some(row) = rows.try_next().await? {
-            Ok(Some(row?))
-        } else {
-            Ok(None)
+        for _ in 0..id_binds {
+            count_query = count_query.bind(id as i64);
         }
-    }
 
-    async fn get_page_from_post(
-        &self,
-        post_id: u64,
-        page: &PageParams,
-    ) -> Result<Page<PostItem>, DataSourceError> {
-        let mut conn = self.read_pool.acquire().await?;
+        let (total_row_count,): (i64,) = count_query.fetch_one(conn.deref_mut()).await?;
 
-        let total_row_count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM post_items WHERE post_id = ?")
-                .bind(post_id as i64)
-                .fetch_one(conn.deref_mut())
-                .await?;
+        let query_str = format!("SELECT t.*, g.name as g_name, g.color as color, g.created_at as g_created_at, (SELECT COUNT(*) FROM tags_posts tpc WHERE tpc.tag_id = t.tag_id) as 'linked_posts', co.co_count, co.confidence FROM ({co_occurrences}) co
+            JOIN tags t ON t.tag_id = co.tag_id
+            LEFT JOIN tag_group g ON t.group_id = g.group_id
+            ORDER BY co.co_count DESC, linked_posts ASC
+            LIMIT ? OFFSET ?");
+        let mut query = sqlx::query(&query_str);
 
-        let rows = sqlx::query(
-            "SELECT * FROM post_items WHERE post_id = ? ORDER BY item_order ASC LIMIT ? OFFSET ?",
-        )
-        .bind(post_id as i64)
-        .bind(page.page_size() as i64)
-        .bind(page.offset() as i64)
-        .map(|r| Self::map_post_item(&r))
-        .fetch_all(conn.deref_mut())
-        .await?;
+        for _ in 0..id_binds {
+            query = query.bind(id as i64);
+        }
+
+        let rows = query
+            .bind(page.page_size() as i64)
+            .bind(page.offset() as i64)
+            .map(|r| Self::map_related_tag(&r, post_count as u64, other_tag_count))
+            .fetch_all(conn.deref_mut())
+            .await?
+            .into_iter()
+            .collect::<Result<Vec<_>, DataSourceError>>()?;
 
         Ok(Page {
             page_size: page.page_size(),
             page_number: page.offset(),
-            total_row_count: total_row_count.0 as usize,
-            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+            total_row_count: total_row_count as usize,
+            data: rows,
         })
     }
-}
-
-#[async_trait]
-impl PostDataSource for SqliteIndex {
-    async fn add(&self, value: &mut Post) -> Result<(), DataSourceError> {
-        let id = sqlx::query("INSERT INTO posts(source, title, description, import_batch_id, created_at) VALUES(?,?,?,?,?)")
-            .bind(value.source.as_ref().map(|url| url.as_str()))
-            .bind(value.title.as_deref())
-            .bind(value.description.as_deref())
-            .bind(value.import_batch.id() as i64)
-            .bind(value.created_at)
-            .execute(&self.write_pool)
-            .await?
-            .last_insert_rowid();
 
-        value.id = id as u64;
-
-        Ok(())
-    }
+    /// Bind the values of the similarity query built by `get_similar_posts`, in order of the
+    /// tag weights, the batch boost, the source boost and the filter.
+    fn add_similarity_values<'a>(
+        str: &'a str,
+        tag_weights: &[(i64, f64)],
+        import_batch_id: u64,
+        domain_patterns: Option<&(String, String)>,
+        post_id: u64,
+        require_playable: bool,
+    ) -> sqlx::query::Query<'a, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'a>> {
+        let mut query = sqlx::query(str);
 
-    async fn update(&self, value: &Post) -> Result<(), DataSourceError> {
-        self.post_update(value, &self.write_pool).await?;
-        Ok(())
-    }
+        for (tag_id, weight) in tag_weights.iter() {
+            query = query.bind(*tag_id).bind(*weight);
+        }
 
-    async fn get_by_id(&self, id: u64) -> Result<Option<Post>, DataSourceError> {
-        let mut rows = sqlx::query(" SELECT * FROM posts WHERE post_id = ?")
-            .bind(id as i64)
-            .map(|r| Self::map_post(&r))
-            .fetch(&self.read_pool);
+        query = query.bind(SIMILAR_BATCH_BOOST).bind(import_batch_id as i64);
 
-        if let Some(row) = rows.try_next().await? {
-            Ok(Some(row?))
-        } else {
-            Ok(None)
+        if let Some((with_path, without_path)) = domain_patterns {
+            query = query
+                .bind(SIMILAR_SOURCE_BOOST)
+                .bind(with_path.clone())
+                .bind(without_path.clone());
         }
+
+        query.bind(post_id as i64).bind(!require_playable)
     }
 
-    async fn get_page(&self, page: PageParams) -> Result<Page<Post>, DataSourceError> {
-        let mut conn = self.read_pool.acquire().await?;
+    /// Build a query of the content selected by `query`, `select` is selected from `content c`
+    /// joined with its `media m`.
+    fn content_query_str(select: &str, query: &ContentQuery) -> String {
+        let mut str = format!(
+            "SELECT {select} FROM content c JOIN media m ON m.media_id = c.content_id WHERE c.content_id > ?"
+        );
 
-        let total_row_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM posts")
-            .fetch_one(conn.deref_mut())
-            .await?;
+        if !query.content_ids.is_empty() {
+            let placeholders = vec!["?"; query.content_ids.len()].join(",");
+            str.push_str(&format!(" AND c.content_id IN ({placeholders})"));
+        }
 
-        let rows = sqlx::query("SELECT * FROM posts LIMIT ? OFFSET ?")
-            .bind(page.page_size() as i64)
-            .bind(page.offset() as i64)
-            .map(|r| Self::map_post(&r))
-            .fetch_all(conn.deref_mut())
-            .await?;
+        match query.mime.as_deref().map(|mime| mime.split_once('/')) {
+            Some(Some(_)) => str.push_str(" AND m.mime_type = ? AND m.mime_sub_type = ?"),
+            Some(None) => str.push_str(" AND m.mime_type = ?"),
+            None => {}
+        }
 
-        Ok(Page {
-            page_size: page.page_size(),
-            page_number: page.offset(),
-            total_row_count: total_row_count.0 as usize,
-            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
-        })
+        str
     }
-}
-
-#[async_trait]
-impl ContentDataSource for SqliteIndex {
-    async fn add(&self, value: &mut Content) -> Result<(), DataSourceError> {
-        sqlx::query(
-            "INSERT INTO content(content_id, thumbnail_id, compatibility_content_id) VALUES(?,?,?)",
-        )
-        .bind(value.content.id() as i64)
-        .bind(value.thumbnail.id() as i64)
-        .bind(Option::<i64>::None)
-        .execute(&self.write_pool)
-        .await?;
 
-        Ok(())
-    }
+    /// Bind the values of a query built by `content_query_str`.
+    fn add_content_query_values<'a>(
+        query_values: &'a ContentQuery,
+        str: &'a str,
+    ) -> sqlx::query::Query<'a, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'a>> {
+        let mut query = sqlx::query(str).bind(query_values.after as i64);
 
-    async fn get_by_content_id(&self, id: u64) -> Result<Option<Content>, DataSourceError> {
-        let mut rows = sqlx::query("SELECT * FROM content WHERE content_id = ?")
-            .bind(id as i64)
-            .map(|r| Self::map_content(&r))
-            .fetch(&self.read_pool);
+        for content_id in query_values.content_ids.iter() {
+            query = query.bind(*content_id as i64);
+        }
 
-        if let Some(row) = rows.try_next().await? {
-            Ok(Some(row?))
-        } else {
-            Ok(None)
+        if let Some(mime) = query_values.mime.as_deref() {
+            match mime.split_once('/') {
+                Some((ty, subty)) => query = query.bind(ty).bind(subty),
+                None => query = query.bind(mime),
+            }
         }
+
+        query
     }
 
-    async fn update_thumbnail_id(
+    async fn post_update<'a, E: Executor<'a, Database = Sqlite>>(
         &self,
-        new_id: u64,
-        content: &mut Content,
+        value: &Post,
+        executor: E,
     ) -> Result<(), DataSourceError> {
-        sqlx::query("UPDATE content SET thumbnail_id = ? WHERE content_id = ?")
-            .bind(new_id as i64)
-            .bind(content.content.id() as i64)
-            .execute(&self.write_pool)
-            .await?;
+        if value.rating.is_some_and(|rating| rating > Post::MAX_RATING) {
+            return Err(DataSourceError::InvalidRating);
+        }
 
-        content.thumbnail = ManyToOne::Id(new_id);
+        sqlx::query("UPDATE posts SET source = ?, title = ?, description = ?, created_at = ?, import_batch_id = ?, rating = ?, favourite = ? WHERE post_id = ?")
+            .bind(value.source.as_ref().map(|url| url.as_str()))
+            .bind(value.title.as_deref())
+            .bind(value.description.as_deref())
+            .bind(value.created_at)
+            .bind(value.import_batch.id() as i64)
+            .bind(value.rating)
+            .bind(value.favourite)
+            .bind(value.id as i64)
+            .execute(executor)
+            .await?;
 
         Ok(())
     }
-}
-
-#[async_trait]
-impl ImportBatchDataSource for SqliteIndex {
-    async fn add(&self, value: &mut ImportBatch) -> Result<(), DataSourceError> {
-        let id = sqlx::query("INSERT INTO import_batches DEFAULT VALUES")
-            .execute(&self.write_pool)
-            .await?
-            .last_insert_rowid();
 
-        value.id = id as u64;
+    async fn count_post_items(
+        conn: &mut SqliteConnection,
+        post_id: u64,
+    ) -> Result<i32, DataSourceError> {
+        let count: (i32,) = sqlx::query_as("SELECT COUNT(*) FROM post_items WHERE post_id = ?")
+            .bind(post_id as i64)
+            .fetch_one(conn)
+            .await?;
 
-        Ok(())
+        Ok(count.0)
     }
-}
-
-#[async_trait]
-impl TagDataSource for SqliteIndex {
-    async fn add(&self, value: &mut Tag) -> Result<(), DataSourceError> {
-        let id = sqlx::query("INSERT INTO tags(name, group_id, created_at) VALUES(?, ?, ?)")
-            .bind(value.name.as_str())
-            .bind(value.group.as_ref().map(|g| g.id() as i64))
-            .bind(value.created_at)
-            .execute(&self.write_pool)
-            .await?
-            .last_insert_rowid();
-
-        value.id = id as u64;
 
-        Ok(())
-    }
+    /// Move all items of a post in the range `from..to` by `delta` positions.
+    ///
+    /// The items are first moved to negative positions so that the primary key of `post_items`
+    /// is never violated while the range is being shifted.
+    async fn shift_post_items(
+        conn: &mut SqliteConnection,
+        post_id: u64,
+        from: i32,
+        to: i32,
+        delta: i32,
+    ) -> Result<(), DataSourceError> {
+        sqlx::query("UPDATE post_items SET item_order = -item_order - 1 WHERE post_id = ? AND item_order >= ? AND item_order < ?")
+            .bind(post_id as i64)
+            .bind(from)
+            .bind(to)
+            .execute(&mut *conn)
+            .await?;
 
-    async fn update(&self, value: &Tag) -> Result<(), DataSourceError> {
-        sqlx::query("UPDATE tags SET name = ?, group_id = ? WHERE tag_id = ?")
-            .bind(value.name.as_str())
-            .bind(value.group.as_ref().map(|g| g.id() as i64))
-            .bind(value.id as i64)
-            .execute(&self.write_pool)
+        sqlx::query("UPDATE post_items SET item_order = -item_order - 1 + ? WHERE post_id = ? AND item_order < 0 AND item_order >= ?")
+            .bind(delta)
+            .bind(post_id as i64)
+            .bind(-to)
+            .execute(&mut *conn)
             .await?;
 
         Ok(())
     }
 
-    async fn delete(&self, tag_id: u64) -> Result<(), DataSourceError> {
-        let mut tx = self.write_pool.begin().await?;
+    /// Renumber the items of a post so that their positions start at 0 and have no gaps.
+    async fn compact_post_items(
+        conn: &mut SqliteConnection,
+        post_id: u64,
+    ) -> Result<(), DataSourceError> {
+        let positions: Vec<(i32,)> = sqlx::query_as(
+            "SELECT item_order FROM post_items WHERE post_id = ? ORDER BY item_order ASC",
+        )
+        .bind(post_id as i64)
+        .fetch_all(&mut *conn)
+        .await?;
 
-        sqlx::query("DELETE FROM tags_posts WHERE tag_id = ?")
-            .bind(tag_id as i64)
-            .execute(tx.deref_mut())
+        sqlx::query("UPDATE post_items SET item_order = -item_order - 1 WHERE post_id = ?")
+            .bind(post_id as i64)
+            .execute(&mut *conn)
             .await?;
 
-        sqlx::query("DELETE FROM tags WHERE tag_id = ?")
-            .bind(tag_id as i64)
-            .execute(tx.deref_mut())
+        for (new_position, (old_position,)) in positions.into_iter().enumerate() {
+            sqlx::query(
+                "UPDATE post_items SET item_order = ? WHERE post_id = ? AND item_order = ?",
+            )
+            .bind(new_position as i32)
+            .bind(post_id as i64)
+            .bind(-old_position - 1)
+            .execute(&mut *conn)
             .await?;
-
-        tx.commit().await?;
+        }
 
         Ok(())
     }
 
-    async fn get_by_id(&self, id: u64) -> Result<Option<Tag>, DataSourceError> {
-        let mut rows = sqlx::query("SELECT * FROM tags WHERE tag_id = ?")
-            .bind(id as i64)
-            .map(|r| Self::map_tag(&r))
-            .fetch(&self.read_pool);
+    async fn content_exists(
+        conn: &mut SqliteConnection,
+        content_id: u64,
+    ) -> Result<bool, DataSourceError> {
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM content WHERE content_id = ?")
+            .bind(content_id as i64)
+            .fetch_one(conn)
+            .await?;
 
-        if let Some(row) = rows.try_next().await? {
-            Ok(Some(row?))
-        } else {
-            Ok(None)
-        }
+        Ok(count.0 > 0)
     }
 
-    async fn add_tag_to_post(&self, tag_id: u64, post_id: u64) -> Result<(), DataSourceError> {
-        sqlx::query("INSERT INTO tags_posts(tag_id, post_id) VALUES(?, ?)")
-            .bind(tag_id as i64)
-            .bind(post_id as i64)
-            .execute(&self.write_pool)
-            .await?;
-
-        Ok(())
+    fn map_change(row: &SqliteRow) -> Result<Change, DataSourceError> {
+        let entity: &str = row.try_get("entity_type")?;
+        let action: &str = row.try_get("action")?;
+        let previous: Option<&str> = row.try_get("previous")?;
+        let current: Option<&str> = row.try_get("current")?;
+
+        Ok(Change {
+            id: row.try_get::<'_, i64, _>("change_id")? as u64,
+            entity: match entity {
+                "tag" => ChangeEntity::Tag,
+                _ => ChangeEntity::Post,
+            },
+            entity_id: row.try_get::<'_, i64, _>("entity_id")? as u64,
+            action: match action {
+                "create" => ChangeAction::Create,
+                "delete" => ChangeAction::Delete,
+                "revert" => ChangeAction::Revert,
+                "trash" => ChangeAction::Trash,
+                "restore" => ChangeAction::Restore,
+                _ => ChangeAction::Update,
+            },
+            previous: previous.map(Self::parse_snapshot).transpose()?,
+            current: current.map(Self::parse_snapshot).transpose()?,
+            origin: ChangeOrigin {
+                ip: row.try_get("ip")?,
+                session: row.try_get("session")?,
+            },
+            created_at: row.try_get("created_at")?,
+        })
     }
 
-    async fn remove_tag_to_post(&self, tag_id: u64, post_id: u64) -> Result<(), DataSourceError> {
-        sqlx::query("DELETE FROM tags_posts WHERE tag_id = ? AND post_id = ?")
-            .bind(tag_id as i64)
-            .bind(post_id as i64)
-            .execute(&self.write_pool)
-            .await?;
+    fn parse_snapshot(value: &str) -> Result<ChangeSnapshot, DataSourceError> {
+        serde_json::from_str(value).map_err(|e| DataSourceError::UnhandledError {
+            message: "invalid change log snapshot".to_string(),
+            inner_error: Some(e.to_string()),
+        })
+    }
 
-        Ok(())
+    fn serialize_snapshot(value: Option<&ChangeSnapshot>) -> Option<String> {
+        value.map(|snapshot| serde_json::to_string(snapshot).unwrap())
     }
-}
 
-#[async_trait]
-impl TagGroupDataSource for SqliteIndex {
-    async fn add(&self, value: &mut TagGroup) -> Result<(), DataSourceError> {
-        let id = sqlx::query("INSERT INTO tag_group(name, color, created_at) VALUES(?, ?, ?)")
-            .bind(value.name.as_str())
-            .bind(value.hex_color.as_str())
-            .bind(value.created_at)
-            .execute(&self.write_pool)
+    async fn post_snapshot(
+        conn: &mut SqliteConnection,
+        post_id: u64,
+    ) -> Result<Option<PostSnapshot>, DataSourceError> {
+        let Some(post) = sqlx::query("SELECT * FROM posts WHERE post_id = ?")
+            .bind(post_id as i64)
+            .map(|r| Self::map_post(&r))
+            .fetch_optional(&mut *conn)
             .await?
-            .last_insert_rowid();
+            .transpose()?
+        else {
+            return Ok(None);
+        };
 
-        value.id = id as u64;
+        let tag_ids: Vec<(i64,)> =
+            sqlx::query_as("SELECT tag_id FROM tags_posts WHERE post_id = ? ORDER BY tag_id ASC")
+                .bind(post_id as i64)
+                .fetch_all(&mut *conn)
+                .await?;
 
-        Ok(())
+        let item_ids: Vec<(i64,)> = sqlx::query_as(
+            "SELECT content_id FROM post_items WHERE post_id = ? ORDER BY item_order ASC",
+        )
+        .bind(post_id as i64)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let custom_fields = sqlx::query("SELECT f.*, pf.value FROM posts_custom_fields pf JOIN custom_fields f ON f.field_id = pf.field_id WHERE pf.post_id = ?")
+            .bind(post_id as i64)
+            .map(|r| Self::map_post_custom_field(&r))
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .map(|field| field.map(|field| (field.field.id, field.value)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(PostSnapshot {
+            source: post.source,
+            title: post.title,
+            description: post.description,
+            tag_ids: tag_ids.into_iter().map(|(id,)| id as u64).collect(),
+            item_ids: item_ids.into_iter().map(|(id,)| id as u64).collect(),
+            custom_fields,
+        }))
     }
 
-    async fn get_by_id(&self, id: u64) -> Result<Option<TagGroup>, DataSourceError> {
-        let mut rows = sqlx::query("SELECT * FROM tag_group WHERE group_id = ?")
-            .bind(id as i64)
-            .map(|r| Self::map_tag_group(&r))
-            .fetch(&self.read_pool);
+    async fn tag_snapshot(
+        conn: &mut SqliteConnection,
+        tag_id: u64,
+    ) -> Result<Option<TagSnapshot>, DataSourceError> {
+        let tag = sqlx::query("SELECT * FROM tags WHERE tag_id = ?")
+            .bind(tag_id as i64)
+            .map(|r| Self::map_tag(&r))
+            .fetch_optional(conn)
+            .await?
+            .transpose()?;
 
-        if let Some(row) = rows.try_next().await? {
-            Ok(Some(row?))
-        } else {
-            Ok(None)
-        }
+        Ok(tag.map(|tag| TagSnapshot {
+            name: tag.name,
+            group_id: tag.group.map(|g| g.id()),
+        }))
     }
 
-    async fn search(
-        &self,
-        page: &PageParams,
-        query: &str,
-        exact: bool,
-    ) -> Result<Page<TagGroup>, DataSourceError> {
-        let mut conn = self.read_pool.acquire().await?;
+    /// Append an entry to the change log. Changes that leave the entity untouched are not recorded.
+    async fn log_change(
+        conn: &mut SqliteConnection,
+        entity: ChangeEntity,
+        entity_id: u64,
+        action: ChangeAction,
+        previous: Option<&ChangeSnapshot>,
+        current: Option<&ChangeSnapshot>,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        if previous == current {
+            return Ok(());
+        }
 
-        let total_row_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tag_group")
-            .fetch_one(conn.deref_mut())
+        sqlx::query("INSERT INTO change_log(entity_type, entity_id, action, previous, current, ip, session, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+            .bind(match entity {
+                ChangeEntity::Post => "post",
+                ChangeEntity::Tag => "tag",
+            })
+            .bind(entity_id as i64)
+            .bind(match action {
+                ChangeAction::Create => "create",
+                ChangeAction::Update => "update",
+                ChangeAction::Delete => "delete",
+                ChangeAction::Revert => "revert",
+                ChangeAction::Trash => "trash",
+                ChangeAction::Restore => "restore",
+            })
+            .bind(Self::serialize_snapshot(previous))
+            .bind(Self::serialize_snapshot(current))
+            .bind(origin.ip.as_deref())
+            .bind(origin.session.as_deref())
+            .bind(Utc::now())
+            .execute(conn)
             .await?;
 
-        let query_is_empty = query.len() < 3;
+        Ok(())
+    }
 
-        let where_clause = if exact {
-            " WHERE name = ?"
-        } else {
-            if query_is_empty {
-                " WHERE name LIKE ?"
-            } else {
-                "WHERE tag_groups_vtab MATCH (?)"
-            }
-        };
+    /// Log the difference between `previous` and the current state of a post as `action`.
+    async fn log_post_change(
+        conn: &mut SqliteConnection,
+        post_id: u64,
+        action: ChangeAction,
+        previous: Option<PostSnapshot>,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let current = Self::post_snapshot(conn, post_id).await?;
+
+        Self::log_change(
+            conn,
+            ChangeEntity::Post,
+            post_id,
+            action,
+            previous.map(ChangeSnapshot::Post).as_ref(),
+            current.map(ChangeSnapshot::Post).as_ref(),
+            origin,
+        )
+        .await
+    }
 
-        let query_str =
-            format!("SELECT * FROM tag_groups_vtab {where_clause} ORDER BY rank LIMIT ? OFFSET ?");
+    /// Remove the least recently used resized images that do not fit into `max_size` bytes together
+    /// with the more recently used ones, `keep` is never removed. Returns the ids of their blobs.
+    async fn remove_least_recently_resized(
+        conn: &mut SqliteConnection,
+        max_size: i64,
+        keep: Option<&Uuid>,
+    ) -> Result<Vec<Uuid>, DataSourceError> {
+        let file_ids: Vec<(Vec<u8>,)> = sqlx::query_as(
+            "DELETE FROM resized_media WHERE file_id IN (
+                SELECT file_id FROM (
+                    SELECT file_id, SUM(file_size) OVER (ORDER BY last_used_at DESC, file_id) AS total_size FROM resized_media
+                ) WHERE total_size > ?
+            ) AND file_id IS NOT ? RETURNING file_id",
+        )
+        .bind(max_size)
+        .bind(keep.map(|file_id| file_id.as_bytes().to_vec()))
+        .fetch_all(conn)
+        .await?;
 
-        let mut sql_query = sqlx::query(query_str.as_str());
+        Ok(file_ids
+            .into_iter()
+            .map(|(file_id,)| Uuid::from_bytes(file_id.try_into().unwrap()))
+            .collect())
+    }
 
-        if exact {
-            sql_query = sql_query.bind(query);
-        } else {
-            if !query_is_empty {
-                let value = query
-                    .trim()
-                    .split(' ')
-                    .map(|word| format!("{{name}}: \"{word}\""))
-                    .collect::<Vec<String>>()
-                    .join(" OR ");
+    async fn get_change_page(
+        &self,
+        entity: &str,
+        entity_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<Change>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
 
-                sql_query = sql_query.bind(value);
-            } else {
-                sql_query = sql_query.bind(format!("%{query}%"))
-            }
-        }
+        let total_row_count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM change_log WHERE entity_type = ? AND entity_id = ?",
+        )
+        .bind(entity)
+        .bind(entity_id as i64)
+        .fetch_one(conn.deref_mut())
+        .await?;
 
-        let rows = sql_query
+        let rows = sqlx::query("SELECT * FROM change_log WHERE entity_type = ? AND entity_id = ? ORDER BY change_id DESC LIMIT ? OFFSET ?")
+            .bind(entity)
+            .bind(entity_id as i64)
             .bind(page.page_size() as i64)
             .bind(page.offset() as i64)
-            .map(|r| Self::map_tag_group(&r))
+            .map(|r| Self::map_change(&r))
             .fetch_all(conn.deref_mut())
             .await?;
 
@@ -900,165 +1511,352 @@ impl TagGroupDataSource for SqliteIndex {
             page_size: page.page_size(),
             page_number: page.offset(),
             total_row_count: total_row_count.0 as usize,
-            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+            data: rows.into_iter().collect::<Result<_, _>>()?,
         })
     }
-}
 
-#[async_trait]
-impl CrossDataSource for SqliteIndex {
-    async fn get_post_detail(&self, post_id: u64) -> Result<Option<PostDetail>, DataSourceError> {
-        let mut get_post_query = sqlx::query(" SELECT *, (SELECT COUNT(*) FROM post_items pi WHERE pi.post_id = p.post_id) as 'item_count' FROM posts p WHERE p.post_id = ?")
-            .bind(post_id as i64)
-            .fetch(&self.read_pool);
+    /// Update a post, replace its tags and record the change as `action`.
+    async fn write_full_post(
+        &self,
+        conn: &mut SqliteConnection,
+        value: &Post,
+        tags: &[u64],
+        action: ChangeAction,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let previous = Self::post_snapshot(conn, value.id).await?;
 
-        if let Some(row) = get_post_query.try_next().await? {
-            let post = Self::map_post(&row)?;
+        self.post_update(value, &mut *conn).await?;
 
-            let item_count: i64 = row.try_get("item_count")?;
+        sqlx::query("DELETE FROM tags_posts WHERE post_id = ?")
+            .bind(value.id as i64)
+            .execute(&mut *conn)
+            .await?;
 
-            drop(row);
-            drop(get_post_query);
+        if !tags.is_empty() {
+            let value_binds = tags.iter().map(|_| "(?, ?)").collect::<Vec<_>>().join(", ");
+            let insert_query =
+                format!("INSERT INTO tags_posts(post_id, tag_id) VALUES {value_binds}");
 
-            let tags = CrossDataSource::get_tags_from_post(self, post_id).await?;
+            let mut query = sqlx::query(&insert_query);
 
-            Ok(Some(PostDetail {
-                post,
-                tags,
-                item_count: item_count as usize,
-            }))
-        } else {
-            Ok(None)
+            for tag in tags {
+                query = query.bind(value.id as i64).bind(*tag as i64);
+            }
+
+            query.execute(&mut *conn).await?;
         }
-    }
 
-    async fn search_posts(
-        &self,
-        query: &PostSearchQuery,
-        page: &PageParams,
-    ) -> Result<Page<SearchPost>, DataSourceError> {
-        let mut conn = self.read_pool.acquire().await?;
+        if let Some(previous) = previous {
+            let current = Self::post_snapshot(conn, value.id).await?;
+
+            Self::log_change(
+                conn,
+                ChangeEntity::Post,
+                value.id,
+                action,
+                Some(&ChangeSnapshot::Post(previous)),
+                current.map(ChangeSnapshot::Post).as_ref(),
+                origin,
+            )
+            .await?;
+        }
 
-        let order = match (query.order, query.text.is_some()) {
-            (Some(PostSearchQueryOrder::Newest), _)
-            | (None, _)
-            | (Some(PostSearchQueryOrder::Relevant), false) => "p.created_at DESC",
-            (Some(PostSearchQueryOrder::Oldest), _) => "p.created_at ASC",
-            (Some(PostSearchQueryOrder::Relevant), true) => "rank ASC, p.created_at DESC",
-            (Some(PostSearchQueryOrder::Random(_)), _) => {
-                "substr(p.post_id * ?, length(p.post_id) + 2)"
-            }
-        };
+        Ok(())
+    }
+    /// Delete a post with its items and tags and record the deletion in the change log.
+    async fn delete_post(
+        conn: &mut SqliteConnection,
+        id: u64,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let previous = Self::post_snapshot(conn, id).await?;
 
-        let table = if query.text.is_none() {
-            "posts"
-        } else {
-            "posts_vtab"
-        };
+        sqlx::query("DELETE FROM post_items WHERE post_id = ?")
+            .bind(id as i64)
+            .execute(&mut *conn)
+            .await?;
 
-        let after_where = format!("ORDER BY {order} LIMIT ? OFFSET ?");
+        sqlx::query("DELETE FROM tags_posts WHERE post_id = ?")
+            .bind(id as i64)
+            .execute(&mut *conn)
+            .await?;
 
-        let search_query_str = SqliteIndex::create_search_query_str(query, &format!("SELECT p.*, m.*, pi.original_name,
-        (SELECT COUNT(*) FROM post_items pi WHERE pi.post_id = p.post_id) as 'item_count',
-        (SELECT SUM(c.duration) FROM post_items pi JOIN media c ON pi.content_id = c.media_id WHERE pi.post_id = p.post_id) as 'total_duration',
-        (SELECT COUNT(*) FROM media WHERE media_id IN (SELECT content_id FROM post_items WHERE post_id = p.post_id) AND mime_type = 'image' AND mime_sub_type != 'gif') as 'contains_image',
-        (SELECT COUNT(*) FROM media WHERE media_id IN (SELECT content_id FROM post_items WHERE post_id = p.post_id) AND mime_type = 'video') as 'contains_video',
-        (SELECT COUNT(*) FROM media WHERE media_id IN (SELECT content_id FROM post_items WHERE post_id = p.post_id) AND mime_type = 'application' AND mime_sub_type != 'pdf') as 'contains_document',
-        (SELECT COUNT(*) FROM media WHERE media_id IN (SELECT content_id FROM post_items WHERE post_id = p.post_id) AND mime_type = 'image' AND mime_sub_type = 'gif') as 'contains_moving_image'
-        FROM {table} p
-        LEFT JOIN (SELECT * FROM post_items ORDER BY item_order ASC) pi ON pi.post_id = p.post_id AND pi.item_order = 0
-        LEFT JOIN content c ON pi.content_id = c.content_id
-        LEFT JOIN media m ON c.thumbnail_id = m.media_id"), &after_where);
+        sqlx::query("DELETE FROM posts WHERE post_id = ?")
+            .bind(id as i64)
+            .execute(&mut *conn)
+            .await?;
 
-        let mut search_query =
-            SqliteIndex::add_search_query_values(query, search_query_str.as_str());
+        if let Some(previous) = previous {
+            Self::log_change(
+                conn,
+                ChangeEntity::Post,
+                id,
+                ChangeAction::Delete,
+                Some(&ChangeSnapshot::Post(previous)),
+                None,
+                origin,
+            )
+            .await?;
+        }
 
-        if let Some(PostSearchQueryOrder::Random(seed)) = query.order {
-            search_query = search_query.bind(seed);
+        Ok(())
+    }
+
+    /// Delete posts permanently, together with the content and media that no other post references.
+    /// Returns the removed media so that their blobs can be deleted.
+    async fn purge_posts(
+        conn: &mut SqliteConnection,
+        post_ids: &[u64],
+        origin: &ChangeOrigin,
+    ) -> Result<Vec<Media>, DataSourceError> {
+        let mut content_ids = Vec::new();
+
+        for post_id in post_ids {
+            let ids: Vec<(i64,)> =
+                sqlx::query_as("SELECT DISTINCT content_id FROM post_items WHERE post_id = ?")
+                    .bind(*post_id as i64)
+                    .fetch_all(&mut *conn)
+                    .await?;
+
+            content_ids.extend(ids.into_iter().map(|(id,)| id));
+
+            Self::delete_post(conn, *post_id, origin).await?;
         }
 
-        let rows = search_query
-            .bind(page.page_size() as i64)
-            .bind(page.offset() as i64)
-            .map(|r| Self::map_search_post(&r))
-            .fetch_all(conn.deref_mut())
-            .await?;
+        content_ids.sort();
+        content_ids.dedup();
 
-        let query_row_count_str = SqliteIndex::create_search_query_str(
-            query,
-            &format!("SELECT COUNT(*) FROM {table} p"),
-            "",
-        );
-        let query_row_count = SqliteIndex::add_search_query_values(query, &query_row_count_str);
+        let mut media_ids = Vec::new();
 
-        let total_row_count: i64 = query_row_count
-            .map(|r| r.get(0))
-            .fetch_one(conn.deref_mut())
+        for content_id in content_ids {
+            let content: Option<(i64, i64, Option<i64>)> = sqlx::query_as(
+                "SELECT content_id, thumbnail_id, compatibility_content_id FROM content c WHERE content_id = ? AND NOT EXISTS(SELECT 1 FROM post_items pi WHERE pi.content_id = c.content_id)",
+            )
+            .bind(content_id)
+            .fetch_optional(&mut *conn)
             .await?;
 
-        Ok(Page {
-            page_size: page.page_size(),
-            page_number: page.offset(),
-            total_row_count: total_row_count as usize,
-            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
-        })
-    }
+            let Some((content_id, thumbnail_id, compatibility_id)) = content else {
+                continue;
+            };
 
-    async fn search_items(
-        &self,
-        post_id: u64,
-        query: &PostItemSearchQuery,
-        page: PageParams,
-    ) -> Result<Page<SearchPostItem>, DataSourceError> {
-        let mut conn = self.read_pool.acquire().await?;
+            let storyboard: Option<(i64,)> =
+                sqlx::query_as("DELETE FROM storyboards WHERE content_id = ? RETURNING media_id")
+                    .bind(content_id)
+                    .fetch_optional(&mut *conn)
+                    .await?;
 
-        let total_row_count: (i64,) =
-            sqlx::query_as("SELECT COUNT(*) FROM post_items WHERE post_id = ?")
-                .bind(post_id as i64)
-                .fetch_one(conn.deref_mut())
+            sqlx::query("DELETE FROM content WHERE content_id = ?")
+                .bind(content_id)
+                .execute(&mut *conn)
                 .await?;
 
-        let rows = sqlx::query(
-            "SELECT pi.*, m.*, cm.mime_type || '/' || cm.mime_sub_type as content_mime_type, cm.duration as 'content_duration' FROM post_items pi
-        LEFT JOIN content c ON pi.content_id = c.content_id
-        LEFT JOIN media cm ON pi.content_id = cm.media_id
-        LEFT JOIN media m ON c.thumbnail_id = m.media_id
-        WHERE pi.post_id = ? AND (? != 0 OR content_duration > 0)
-        ORDER BY pi.item_order ASC
-        LIMIT ? OFFSET ?",
-        )
-            .bind(post_id as i64)
-            .bind(!query.require_playable)
-            .bind(page.page_size() as i64)
-            .bind(page.offset() as i64)
-            .map(|r| Self::map_search_post_item(&r))
-            .fetch_all(conn.deref_mut())
-            .await?;
+            media_ids.push(content_id);
+            media_ids.push(thumbnail_id);
+            media_ids.extend(compatibility_id);
+            media_ids.extend(storyboard.map(|(id,)| id));
+        }
 
-        Ok(Page {
-            page_size: page.page_size(),
-            page_number: page.offset(),
-            total_row_count: total_row_count.0 as usize,
-            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
-        })
+        media_ids.sort();
+        media_ids.dedup();
+
+        let mut removed = Vec::new();
+
+        for media_id in media_ids {
+            let media = sqlx::query(
+                "SELECT * FROM media m WHERE media_id = ? AND NOT EXISTS(SELECT 1 FROM content c WHERE c.content_id = m.media_id OR c.thumbnail_id = m.media_id OR c.compatibility_content_id = m.media_id) AND NOT EXISTS(SELECT 1 FROM storyboards s WHERE s.media_id = m.media_id)",
+            )
+            .bind(media_id)
+            .map(|r| Self::map_media(&r))
+            .fetch_optional(&mut *conn)
+            .await?
+            .transpose()?;
+
+            let Some(media) = media else {
+                continue;
+            };
+
+            sqlx::query("DELETE FROM media WHERE media_id = ?")
+                .bind(media_id)
+                .execute(&mut *conn)
+                .await?;
+
+            removed.push(media);
+        }
+
+        Ok(removed)
     }
 
-    async fn get_full_post_item(
+    async fn set_post_deleted_at(
+        conn: &mut SqliteConnection,
+        id: u64,
+        deleted_at: Option<DateTime<Utc>>,
+        action: ChangeAction,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        // only trash posts outside the trash and only restore posts inside of it
+        let rows_affected = sqlx::query(
+            "UPDATE posts SET deleted_at = ? WHERE post_id = ? AND (deleted_at IS NULL) = (? IS NOT NULL)",
+        )
+        .bind(deleted_at)
+        .bind(id as i64)
+        .bind(deleted_at)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Err(DataSourceError::NotFound);
+        }
+
+        let snapshot = Self::post_snapshot(&mut *conn, id)
+            .await?
+            .map(ChangeSnapshot::Post);
+        let (previous, current) = match action {
+            ChangeAction::Trash => (snapshot.as_ref(), None),
+            _ => (None, snapshot.as_ref()),
+        };
+
+        Self::log_change(
+            conn,
+            ChangeEntity::Post,
+            id,
+            action,
+            previous,
+            current,
+            origin,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ChangeDataSource for SqliteIndex {
+    async fn get_page_from_post(
         &self,
         post_id: u64,
-        position: i32,
-    ) -> Result<Option<PostItem>, DataSourceError> {
+        page: &PageParams,
+    ) -> Result<Page<Change>, DataSourceError> {
+        self.get_change_page("post", post_id, page).await
+    }
+
+    async fn get_page_from_tag(
+        &self,
+        tag_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<Change>, DataSourceError> {
+        self.get_change_page("tag", tag_id, page).await
+    }
+}
+
+#[async_trait]
+impl MediaDataSource for SqliteIndex {
+    async fn add(&self, value: &mut Media) -> Result<(), DataSourceError> {
+        let exif = value.metadata.exif();
+
+        let id = sqlx::query("INSERT INTO media(width, height, duration, mime_type, mime_sub_type, file_size, file_id, sha256, md5, sha1, document_pages, document_title, document_author, page_width, page_height, video_encoding, audio_bitrate, audio_sample_rate, audio_artist, audio_album, audio_title, audio_track, camera_make, camera_model, lens, exposure_time, f_number, iso, focal_length, orientation, taken_at, latitude, longitude, frame_count, loop_duration_ms) VALUES(?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?,?)")
+            .bind(value.metadata.width())
+            .bind(value.metadata.height())
+            .bind(value.metadata.duration())
+            .bind(value.mime.ty().as_str())
+            .bind(value.mime.subty().as_str())
+            .bind(value.file_size as i64)
+            .bind(value.file_id.as_bytes().as_slice())
+            .bind(value.sha256.as_str())
+            .bind(value.md5.as_str())
+            .bind(value.sha1.as_str())
+            .bind(value.metadata.pages())
+            .bind(value.metadata.title())
+            .bind(value.metadata.author())
+            .bind(value.metadata.page_size().map(|s| s.width))
+            .bind(value.metadata.page_size().map(|s| s.height))
+            .bind(value.metadata.video_encoding())
+            .bind(value.metadata.bitrate())
+            .bind(value.metadata.sample_rate())
+            .bind(value.metadata.artist())
+            .bind(value.metadata.album())
+            .bind(value.metadata.audio_title())
+            .bind(value.metadata.track())
+            .bind(exif.and_then(|e| e.camera_make.as_deref()))
+            .bind(exif.and_then(|e| e.camera_model.as_deref()))
+            .bind(exif.and_then(|e| e.lens.as_deref()))
+            .bind(exif.and_then(|e| e.exposure_time))
+            .bind(exif.and_then(|e| e.f_number))
+            .bind(exif.and_then(|e| e.iso))
+            .bind(exif.and_then(|e| e.focal_length))
+            .bind(exif.and_then(|e| e.orientation))
+            .bind(exif.and_then(|e| e.taken_at))
+            .bind(exif.and_then(|e| e.latitude))
+            .bind(exif.and_then(|e| e.longitude))
+            .bind(value.metadata.frame_count())
+            .bind(value.metadata.animation().map(|a| a.loop_duration_ms))
+            .execute(&self.write_pool)
+            .await?
+            .last_insert_rowid();
+
+        value.id = id as u64;
+
+        Ok(())
+    }
+
+    async fn remove(&self, value: &Media) -> Result<(), DataSourceError> {
+        sqlx::query("DELETE FROM media WHERE media_id = ?")
+            .bind(value.id as i64)
+            .execute(&self.write_pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_metadata(&self, value: &Media) -> Result<(), DataSourceError> {
+        let exif = value.metadata.exif();
+
+        sqlx::query("UPDATE media SET width = ?, height = ?, duration = ?, document_pages = ?, document_title = ?, document_author = ?, page_width = ?, page_height = ?, video_encoding = ?, audio_bitrate = ?, audio_sample_rate = ?, audio_artist = ?, audio_album = ?, audio_title = ?, audio_track = ?, camera_make = ?, camera_model = ?, lens = ?, exposure_time = ?, f_number = ?, iso = ?, focal_length = ?, orientation = ?, taken_at = ?, latitude = ?, longitude = ?, frame_count = ?, loop_duration_ms = ? WHERE media_id = ?")
+            .bind(value.metadata.width())
+            .bind(value.metadata.height())
+            .bind(value.metadata.duration())
+            .bind(value.metadata.pages())
+            .bind(value.metadata.title())
+            .bind(value.metadata.author())
+            .bind(value.metadata.page_size().map(|s| s.width))
+            .bind(value.metadata.page_size().map(|s| s.height))
+            .bind(value.metadata.video_encoding())
+            .bind(value.metadata.bitrate())
+            .bind(value.metadata.sample_rate())
+            .bind(value.metadata.artist())
+            .bind(value.metadata.album())
+            .bind(value.metadata.audio_title())
+            .bind(value.metadata.track())
+            .bind(exif.and_then(|e| e.camera_make.as_deref()))
+            .bind(exif.and_then(|e| e.camera_model.as_deref()))
+            .bind(exif.and_then(|e| e.lens.as_deref()))
+            .bind(exif.and_then(|e| e.exposure_time))
+            .bind(exif.and_then(|e| e.f_number))
+            .bind(exif.and_then(|e| e.iso))
+            .bind(exif.and_then(|e| e.focal_length))
+            .bind(exif.and_then(|e| e.orientation))
+            .bind(exif.and_then(|e| e.taken_at))
+            .bind(exif.and_then(|e| e.latitude))
+            .bind(exif.and_then(|e| e.longitude))
+            .bind(value.metadata.frame_count())
+            .bind(value.metadata.animation().map(|a| a.loop_duration_ms))
+            .bind(value.id as i64)
+            .execute(&self.write_pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove_if_unused(&self, id: u64) -> Result<Option<Media>, DataSourceError> {
         let mut rows = sqlx::query(
-            "SELECT * FROM post_items pi
-            LEFT JOIN posts p ON p.post_id = pi.post_id
-            LEFT JOIN content c ON c.content_id = pi.content_id
-            LEFT JOIN media m ON m.media_id = pi.content_id
-            WHERE pi.post_id = ? AND pi.item_order = ?",
+            "DELETE FROM media AS m WHERE media_id = ? AND NOT EXISTS(SELECT 1 FROM content c WHERE c.content_id = m.media_id OR c.thumbnail_id = m.media_id OR c.compatibility_content_id = m.media_id) AND NOT EXISTS(SELECT 1 FROM storyboards s WHERE s.media_id = m.media_id) RETURNING *",
         )
-        .bind(post_id as i64)
-        .bind(position as i64)
-        .map(|r| Self::map_full_post_item(&r))
-        .fetch(&self.read_pool);
+        .bind(id as i64)
+        .map(|r| Self::map_media(&r))
+        .fetch(&self.write_pool);
 
         if let Some(row) = rows.try_next().await? {
             Ok(Some(row?))
@@ -1067,350 +1865,3352 @@ impl CrossDataSource for SqliteIndex {
         }
     }
 
-    async fn add_full_post(
-        &self,
-        data: CreateFullPost,
-    ) -> Result<(ImportBatch, Vec<Post>), DataSourceError> {
-        let created_at = data.created_at.unwrap_or_else(Utc::now);
+    async fn get_by_id(&self, id: u64) -> Result<Option<Media>, DataSourceError> {
+        let mut rows = sqlx::query("SELECT * FROM media WHERE media_id = ?")
+            .bind(id as i64)
+            .map(|r| Self::map_media(&r))
+            .fetch(&self.read_pool);
 
-        let mut tx = self.write_pool.begin().await?;
+        if let Some(row) = rows.try_next().await? {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
 
-        let batch_id = match data.batch_id {
-            None => sqlx::query("INSERT INTO import_batches DEFAULT VALUES")
-                .execute(tx.deref_mut())
-                .await?
-                .last_insert_rowid() as u64,
-            Some(v) => v,
-        };
+    async fn get_by_sha256(&self, sha256: &str) -> Result<Option<Media>, DataSourceError> {
+        let mut rows = sqlx::query("SELECT * FROM media WHERE sha256 = ?")
+            .bind(sha256)
+            .map(|r| Self::map_media(&r))
+            .fetch(&self.read_pool);
 
-        let batch = ImportBatch { id: batch_id };
+        if let Some(row) = rows.try_next().await? {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
 
-        let amount_of_posts_to_create = data.items.len().max(1);
-        let mut posts = Vec::with_capacity(amount_of_posts_to_create);
+    async fn get_total_size(&self) -> Result<u64, DataSourceError> {
+        let total_size: (i64,) = sqlx::query_as("SELECT SUM(file_size) FROM media")
+            .fetch_one(&self.read_pool)
+            .await?;
 
-        for _ in 0..amount_of_posts_to_create {
-            let id = sqlx::query("INSERT INTO posts(source, title, description, import_batch_id, created_at) VALUES(?,?,?,?,?)")
-                .bind(data.source.as_ref().map(|url| url.as_str()))
-                .bind(data.title.as_deref())
-                .bind(data.description.as_deref())
-                .bind(batch.id as i64)
-                .bind(created_at)
-                .execute(tx.deref_mut())
-                .await?
-                .last_insert_rowid();
+        Ok(total_size.0 as u64)
+    }
 
-            let post = Post {
-                id: id as u64,
-                source: data.source.clone(),
-                title: data.title.clone(),
-                description: data.description.clone(),
-                import_batch: ManyToOne::Obj(batch.clone()),
-                created_at,
-            };
+    async fn get_count(&self) -> Result<u64, DataSourceError> {
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM media")
+            .fetch_one(&self.read_pool)
+            .await?;
 
-            posts.push(post);
+        Ok(count.0 as u64)
+    }
 
-            if !data.flatten {
-                break;
-            }
-        }
+    async fn get_page(&self, page: &PageParams) -> Result<Page<Media>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
 
-        let mut items = Vec::with_capacity(data.items.len());
+        let total_row_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM media")
+            .fetch_one(conn.deref_mut())
+            .await?;
 
-        if !data.flatten {
-            for post in posts.iter() {
-                for (position, item) in data.items.iter().enumerate() {
-                    items.push((post.id, item, position));
-                }
-            }
+        let rows = sqlx::query("SELECT * FROM media ORDER BY media_id ASC LIMIT ? OFFSET ?")
+            .bind(page.page_size() as i64)
+            .bind(page.offset() as i64)
+            .map(|r| Self::map_media(&r))
+            .fetch_all(conn.deref_mut())
+            .await?;
+
+        Ok(Page {
+            page_size: page.page_size(),
+            page_number: page.offset(),
+            total_row_count: total_row_count.0 as usize,
+            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+        })
+    }
+
+    async fn get_text(
+        &self,
+        media_id: u64,
+        kind: MediaTextKind,
+    ) -> Result<Option<String>, DataSourceError> {
+        let text: Option<(String,)> =
+            sqlx::query_as("SELECT text FROM media_text WHERE media_id = ? AND kind = ?")
+                .bind(media_id as i64)
+                .bind(Self::media_text_kind_str(kind))
+                .fetch_optional(&self.read_pool)
+                .await?;
+
+        Ok(text.map(|(text,)| text))
+    }
+
+    async fn set_text(
+        &self,
+        media_id: u64,
+        kind: MediaTextKind,
+        text: Option<&str>,
+    ) -> Result<(), DataSourceError> {
+        let kind = Self::media_text_kind_str(kind);
+
+        if let Some(text) = text {
+            sqlx::query(
+                "INSERT INTO media_text(media_id, kind, text) VALUES(?,?,?)
+                ON CONFLICT(media_id, kind) DO UPDATE SET text = excluded.text",
+            )
+            .bind(media_id as i64)
+            .bind(kind)
+            .bind(text)
+            .execute(&self.write_pool)
+            .await?;
         } else {
-            for (i, post) in posts.iter().enumerate() {
-                let item = &data.items[i];
-                items.push((post.id, item, 0));
-            }
+            sqlx::query("DELETE FROM media_text WHERE media_id = ? AND kind = ?")
+                .bind(media_id as i64)
+                .bind(kind)
+                .execute(&self.write_pool)
+                .await?;
         }
 
-        if !data.items.is_empty() {
-            let insert_str = "INSERT INTO post_items(post_id, item_order, content_id, original_name, original_accessed, original_modified, original_directory, uploaded_at) VALUES \n";
-            let row_value_str = "(?, ?, ?, ?,?,?,?,?),\n";
+        Ok(())
+    }
 
-            let mut insert_items_query =
-                String::with_capacity(insert_str.len() + row_value_str.len() * items.len());
+    async fn get_hls_variants(&self, media_id: u64) -> Result<Vec<HlsVariant>, DataSourceError> {
+        let variants: Vec<(i32, i32, i32, i64, i64)> = sqlx::query_as(
+            "SELECT variant, width, height, bandwidth, average_bandwidth FROM hls_variants WHERE media_id = ? ORDER BY variant",
+        )
+        .bind(media_id as i64)
+        .fetch_all(&self.read_pool)
+        .await?;
 
-            insert_items_query.push_str(insert_str);
+        Ok(variants
+            .into_iter()
+            .map(
+                |(variant, width, height, bandwidth, average_bandwidth)| HlsVariant {
+                    media_id,
+                    variant,
+                    dims: Dimensions { width, height },
+                    bandwidth: bandwidth as u64,
+                    average_bandwidth: average_bandwidth as u64,
+                },
+            )
+            .collect())
+    }
 
-            for _ in items.iter() {
-                insert_items_query.push_str(row_value_str);
-            }
+    async fn get_hls_segments(
+        &self,
+        media_id: u64,
+        variant: i32,
+    ) -> Result<Vec<HlsSegment>, DataSourceError> {
+        let segments: Vec<(i32, f64, Vec<u8>, i64)> = sqlx::query_as(
+            "SELECT position, duration, file_id, file_size FROM hls_segments WHERE media_id = ? AND variant = ? ORDER BY position",
+        )
+        .bind(media_id as i64)
+        .bind(variant)
+        .fetch_all(&self.read_pool)
+        .await?;
 
-            // Remove the last newline and comma
-            insert_items_query.pop();
-            insert_items_query.pop();
+        Ok(segments.into_iter().map(Self::map_hls_segment).collect())
+    }
 
-            let mut query = sqlx::query(insert_items_query.as_str());
+    async fn get_hls_segment(
+        &self,
+        media_id: u64,
+        variant: i32,
+        position: i32,
+    ) -> Result<Option<HlsSegment>, DataSourceError> {
+        let segment: Option<(i32, f64, Vec<u8>, i64)> = sqlx::query_as(
+            "SELECT position, duration, file_id, file_size FROM hls_segments WHERE media_id = ? AND variant = ? AND position = ?",
+        )
+        .bind(media_id as i64)
+        .bind(variant)
+        .bind(position)
+        .fetch_optional(&self.read_pool)
+        .await?;
 
-            for (post_id, item, position) in items {
-                query = query.bind(post_id as i64);
-                query = query.bind(position as i64);
-                query = query.bind(item.content_id as i64);
+        Ok(segment.map(Self::map_hls_segment))
+    }
 
-                query = query.bind(item.metadata.original_filename.as_deref());
-                query = query.bind(item.metadata.original_accessed_at);
-                query = query.bind(item.metadata.original_modified_at);
-                query = query.bind(item.metadata.original_directory.as_deref());
-                query = query.bind(Utc::now());
-            }
+    async fn add_hls_variant(
+        &self,
+        variant: &HlsVariant,
+        segments: &[HlsSegment],
+    ) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        sqlx::query("INSERT INTO hls_variants(media_id, variant, width, height, bandwidth, average_bandwidth) VALUES(?,?,?,?,?,?)")
+            .bind(variant.media_id as i64)
+            .bind(variant.variant)
+            .bind(variant.dims.width)
+            .bind(variant.dims.height)
+            .bind(variant.bandwidth as i64)
+            .bind(variant.average_bandwidth as i64)
+            .execute(&mut *tx)
+            .await?;
+
+        for segment in segments {
+            sqlx::query("INSERT INTO hls_segments(media_id, variant, position, duration, file_id, file_size) VALUES(?,?,?,?,?,?)")
+                .bind(variant.media_id as i64)
+                .bind(variant.variant)
+                .bind(segment.position)
+                .bind(segment.duration)
+                .bind(segment.file_id.as_bytes().as_slice())
+                .bind(segment.file_size as i64)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn remove_unused_hls_segments(&self) -> Result<Vec<Uuid>, DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let file_ids: Vec<(Vec<u8>,)> = sqlx::query_as(
+            "SELECT file_id FROM hls_segments s WHERE NOT EXISTS(SELECT 1 FROM media m WHERE m.media_id = s.media_id)",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM hls_segments WHERE media_id NOT IN (SELECT media_id FROM media)")
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM hls_variants WHERE media_id NOT IN (SELECT media_id FROM media)")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(file_ids
+            .into_iter()
+            .map(|(file_id,)| Uuid::from_bytes(file_id.try_into().unwrap()))
+            .collect())
+    }
+
+    async fn get_resized(
+        &self,
+        media_id: u64,
+        params: &ResizeParams,
+    ) -> Result<Option<ResizedMedia>, DataSourceError> {
+        let resized: Option<(Vec<u8>, i64, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT file_id, file_size, last_used_at FROM resized_media WHERE media_id = ? AND width = ? AND height = ? AND fit = ? AND format = ?",
+        )
+        .bind(media_id as i64)
+        .bind(params.width)
+        .bind(params.height)
+        .bind(params.fit.as_str())
+        .bind(params.format.as_str())
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        let Some((file_id, file_size, last_used_at)) = resized else {
+            return Ok(None);
+        };
+
+        let file_id = Uuid::from_bytes(file_id.try_into().unwrap());
+        let now = Utc::now();
+
+        // the order of the cache only has to be roughly right, so most hits do not have to write
+        if now - last_used_at > chrono::Duration::seconds(RESIZED_MEDIA_TOUCH_INTERVAL_SECS) {
+            sqlx::query("UPDATE resized_media SET last_used_at = ? WHERE file_id = ?")
+                .bind(now)
+                .bind(file_id.as_bytes().as_slice())
+                .execute(&self.write_pool)
+                .await?;
+        }
+
+        Ok(Some(ResizedMedia {
+            media_id,
+            params: *params,
+            file_id,
+            file_size: file_size as usize,
+        }))
+    }
+
+    async fn add_resized(
+        &self,
+        resized: &ResizedMedia,
+    ) -> Result<Option<Vec<Uuid>>, DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let rows_affected = sqlx::query(
+            "INSERT INTO resized_media(media_id, width, height, fit, format, file_id, file_size, last_used_at) VALUES(?,?,?,?,?,?,?,?)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(resized.media_id as i64)
+        .bind(resized.params.width)
+        .bind(resized.params.height)
+        .bind(resized.params.fit.as_str())
+        .bind(resized.params.format.as_str())
+        .bind(resized.file_id.as_bytes().as_slice())
+        .bind(resized.file_size as i64)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Ok(None);
+        }
+
+        let (max_size,): (i64,) = sqlx::query_as("SELECT resize_cache_size FROM bucket_settings")
+            .fetch_one(&mut *tx)
+            .await?;
+
+        let file_ids =
+            Self::remove_least_recently_resized(tx.deref_mut(), max_size, Some(&resized.file_id))
+                .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(file_ids))
+    }
+
+    async fn remove_resized(&self, max_size: u64) -> Result<Vec<Uuid>, DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let file_ids: Vec<(Vec<u8>,)> = sqlx::query_as(
+            "DELETE FROM resized_media WHERE media_id NOT IN (SELECT media_id FROM media) RETURNING file_id",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let least_recently_used =
+            Self::remove_least_recently_resized(tx.deref_mut(), max_size as i64, None).await?;
+
+        tx.commit().await?;
+
+        let mut file_ids: Vec<Uuid> = file_ids
+            .into_iter()
+            .map(|(file_id,)| Uuid::from_bytes(file_id.try_into().unwrap()))
+            .collect();
+        file_ids.extend(least_recently_used);
+
+        Ok(file_ids)
+    }
+}
+
+#[async_trait]
+impl PostItemDataSource for SqliteIndex {
+    async fn add(&self, value: &mut PostItem) -> Result<(), DataSourceError> {
+        sqlx::query("INSERT INTO post_items(post_id, item_order, content_id) VALUES(?,?,?)")
+            .bind(value.post.id() as i64)
+            .bind(value.position)
+            .bind(value.content.id() as i64)
+            .execute(&self.write_pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn insert(
+        &self,
+        value: &mut PostItem,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let Some(previous) = Self::post_snapshot(tx.deref_mut(), value.post.id()).await? else {
+            return Err(DataSourceError::NotFound);
+        };
+
+        if !Self::content_exists(tx.deref_mut(), value.content.id()).await? {
+            return Err(DataSourceError::NotFound);
+        }
+
+        let item_count = Self::count_post_items(tx.deref_mut(), value.post.id()).await?;
+        value.position = value.position.clamp(0, item_count);
+
+        Self::shift_post_items(
+            tx.deref_mut(),
+            value.post.id(),
+            value.position,
+            item_count,
+            1,
+        )
+        .await?;
+
+        sqlx::query("INSERT INTO post_items(post_id, item_order, content_id, original_name, original_accessed, original_modified, original_directory, uploaded_at) VALUES(?,?,?,?,?,?,?,?)")
+            .bind(value.post.id() as i64)
+            .bind(value.position)
+            .bind(value.content.id() as i64)
+            .bind(value.upload.original_filename.as_deref())
+            .bind(value.upload.original_accessed_at)
+            .bind(value.upload.original_modified_at)
+            .bind(value.upload.original_directory.as_deref())
+            .bind(Utc::now())
+            .execute(tx.deref_mut())
+            .await?;
+
+        Self::log_post_change(
+            tx.deref_mut(),
+            value.post.id(),
+            ChangeAction::Update,
+            Some(previous),
+            origin,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn replace(
+        &self,
+        value: &PostItem,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let previous = Self::post_snapshot(tx.deref_mut(), value.post.id()).await?;
+
+        if !Self::content_exists(tx.deref_mut(), value.content.id()).await? {
+            return Err(DataSourceError::NotFound);
+        }
+
+        let rows_affected = sqlx::query("UPDATE post_items SET content_id = ?, original_name = ?, original_accessed = ?, original_modified = ?, original_directory = ?, uploaded_at = ?, watch_position = NULL, watch_updated_at = NULL WHERE post_id = ? AND item_order = ?")
+            .bind(value.content.id() as i64)
+            .bind(value.upload.original_filename.as_deref())
+            .bind(value.upload.original_accessed_at)
+            .bind(value.upload.original_modified_at)
+            .bind(value.upload.original_directory.as_deref())
+            .bind(Utc::now())
+            .bind(value.post.id() as i64)
+            .bind(value.position)
+            .execute(tx.deref_mut())
+            .await?
+            .rows_affected();
+
+        if rows_affected == 0 {
+            return Err(DataSourceError::NotFound);
+        }
+
+        Self::log_post_change(
+            tx.deref_mut(),
+            value.post.id(),
+            ChangeAction::Update,
+            previous,
+            origin,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn remove(
+        &self,
+        post_id: u64,
+        position: i32,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let item_count = Self::count_post_items(tx.deref_mut(), post_id).await?;
+
+        if position < 0 || position >= item_count {
+            return Err(DataSourceError::NotFound);
+        }
+
+        if item_count == 1 {
+            return Err(DataSourceError::EmptyPost);
+        }
+
+        let previous = Self::post_snapshot(tx.deref_mut(), post_id).await?;
+
+        sqlx::query("DELETE FROM post_items WHERE post_id = ? AND item_order = ?")
+            .bind(post_id as i64)
+            .bind(position)
+            .execute(tx.deref_mut())
+            .await?;
+
+        Self::shift_post_items(tx.deref_mut(), post_id, position + 1, item_count, -1).await?;
+
+        Self::log_post_change(
+            tx.deref_mut(),
+            post_id,
+            ChangeAction::Update,
+            previous,
+            origin,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn reorder(
+        &self,
+        post_id: u64,
+        from: i32,
+        to: i32,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let item_count = Self::count_post_items(tx.deref_mut(), post_id).await?;
+
+        if !(0..item_count).contains(&from) || !(0..item_count).contains(&to) {
+            return Err(DataSourceError::NotFound);
+        }
+
+        if from == to {
+            return Ok(());
+        }
+
+        let previous = Self::post_snapshot(tx.deref_mut(), post_id).await?;
+
+        // park the moved item below every position `shift_post_items` can touch
+        let parked_position = -item_count - 1;
+
+        sqlx::query("UPDATE post_items SET item_order = ? WHERE post_id = ? AND item_order = ?")
+            .bind(parked_position)
+            .bind(post_id as i64)
+            .bind(from)
+            .execute(tx.deref_mut())
+            .await?;
+
+        if from < to {
+            Self::shift_post_items(tx.deref_mut(), post_id, from + 1, to + 1, -1).await?;
+        } else {
+            Self::shift_post_items(tx.deref_mut(), post_id, to, from, 1).await?;
+        }
+
+        sqlx::query("UPDATE post_items SET item_order = ? WHERE post_id = ? AND item_order = ?")
+            .bind(to)
+            .bind(post_id as i64)
+            .bind(parked_position)
+            .execute(tx.deref_mut())
+            .await?;
+
+        Self::log_post_change(
+            tx.deref_mut(),
+            post_id,
+            ChangeAction::Update,
+            previous,
+            origin,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update_progress(
+        &self,
+        post_id: u64,
+        position: i32,
+        watch_position: Option<f64>,
+    ) -> Result<(), DataSourceError> {
+        if watch_position.is_some_and(|p| !p.is_finite() || p < 0.0) {
+            return Err(DataSourceError::InvalidWatchPosition);
+        }
+
+        let rows_affected = sqlx::query(
+            "UPDATE post_items SET watch_position = ?, watch_updated_at = ? WHERE post_id = ? AND item_order = ?",
+        )
+        .bind(watch_position)
+        .bind(watch_position.map(|_| Utc::now()))
+        .bind(post_id as i64)
+        .bind(position)
+        .execute(&self.write_pool)
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Err(DataSourceError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn get_by_id(
+        &self,
+        post_item: u64,
+        position: i32,
+    ) -> Result<Option<PostItem>, DataSourceError> {
+        let mut rows = sqlx::query("SELECT * FROM post_items WHERE post_id = ? AND item_order = ?")
+            .bind(post_item as i64)
+            .bind(position)
+            .map(|r| Self::map_post_item(&r))
+            .fetch(&self.read_pool);
+
+        if let Some(row) = rows.try_next().await? {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_page_from_post(
+        &self,
+        post_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<PostItem>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
+
+        let total_row_count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM post_items WHERE post_id = ?")
+                .bind(post_id as i64)
+                .fetch_one(conn.deref_mut())
+                .await?;
+
+        let rows = sqlx::query(
+            "SELECT * FROM post_items WHERE post_id = ? ORDER BY item_order ASC LIMIT ? OFFSET ?",
+        )
+        .bind(post_id as i64)
+        .bind(page.page_size() as i64)
+        .bind(page.offset() as i64)
+        .map(|r| Self::map_post_item(&r))
+        .fetch_all(conn.deref_mut())
+        .await?;
+
+        Ok(Page {
+            page_size: page.page_size(),
+            page_number: page.offset(),
+            total_row_count: total_row_count.0 as usize,
+            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl PostDataSource for SqliteIndex {
+    async fn add(&self, value: &mut Post) -> Result<(), DataSourceError> {
+        if value.rating.is_some_and(|rating| rating > Post::MAX_RATING) {
+            return Err(DataSourceError::InvalidRating);
+        }
+
+        let id = sqlx::query("INSERT INTO posts(source, title, description, import_batch_id, created_at, rating, favourite) VALUES(?,?,?,?,?,?,?)")
+            .bind(value.source.as_ref().map(|url| url.as_str()))
+            .bind(value.title.as_deref())
+            .bind(value.description.as_deref())
+            .bind(value.import_batch.id() as i64)
+            .bind(value.created_at)
+            .bind(value.rating)
+            .bind(value.favourite)
+            .execute(&self.write_pool)
+            .await?
+            .last_insert_rowid();
+
+        value.id = id as u64;
+
+        Ok(())
+    }
+
+    async fn update(&self, value: &Post) -> Result<(), DataSourceError> {
+        self.post_update(value, &self.write_pool).await?;
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: u64) -> Result<Option<Post>, DataSourceError> {
+        let mut rows = sqlx::query(" SELECT * FROM posts WHERE post_id = ?")
+            .bind(id as i64)
+            .map(|r| Self::map_post(&r))
+            .fetch(&self.read_pool);
+
+        if let Some(row) = rows.try_next().await? {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_page(&self, page: PageParams) -> Result<Page<Post>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
+
+        let total_row_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM posts")
+            .fetch_one(conn.deref_mut())
+            .await?;
+
+        let rows = sqlx::query("SELECT * FROM posts LIMIT ? OFFSET ?")
+            .bind(page.page_size() as i64)
+            .bind(page.offset() as i64)
+            .map(|r| Self::map_post(&r))
+            .fetch_all(conn.deref_mut())
+            .await?;
+
+        Ok(Page {
+            page_size: page.page_size(),
+            page_number: page.offset(),
+            total_row_count: total_row_count.0 as usize,
+            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl ContentDataSource for SqliteIndex {
+    async fn add(&self, value: &mut Content) -> Result<(), DataSourceError> {
+        sqlx::query(
+            "INSERT INTO content(content_id, thumbnail_id, compatibility_content_id) VALUES(?,?,?)",
+        )
+        .bind(value.content.id() as i64)
+        .bind(value.thumbnail.id() as i64)
+        .bind(
+            value
+                .rendition
+                .as_ref()
+                .map(|rendition| rendition.id() as i64),
+        )
+        .execute(&self.write_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_by_content_id(&self, id: u64) -> Result<Option<Content>, DataSourceError> {
+        let mut rows = sqlx::query("SELECT * FROM content WHERE content_id = ?")
+            .bind(id as i64)
+            .map(|r| Self::map_content(&r))
+            .fetch(&self.read_pool);
+
+        if let Some(row) = rows.try_next().await? {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn update_thumbnail_id(
+        &self,
+        new_id: u64,
+        content: &mut Content,
+    ) -> Result<(), DataSourceError> {
+        sqlx::query("UPDATE content SET thumbnail_id = ? WHERE content_id = ?")
+            .bind(new_id as i64)
+            .bind(content.content.id() as i64)
+            .execute(&self.write_pool)
+            .await?;
+
+        content.thumbnail = ManyToOne::Id(new_id);
+
+        Ok(())
+    }
+
+    async fn count(&self, query: &ContentQuery) -> Result<u64, DataSourceError> {
+        let str = Self::content_query_str("COUNT(*)", query);
+
+        let count: i64 = Self::add_content_query_values(query, &str)
+            .fetch_one(&self.read_pool)
+            .await?
+            .try_get(0)?;
+
+        Ok(count as u64)
+    }
+
+    async fn get_ids(
+        &self,
+        query: &ContentQuery,
+        limit: usize,
+    ) -> Result<Vec<u64>, DataSourceError> {
+        let str = format!(
+            "{} ORDER BY c.content_id LIMIT ?",
+            Self::content_query_str("c.content_id", query)
+        );
+
+        let rows = Self::add_content_query_values(query, &str)
+            .bind(limit as i64)
+            .fetch_all(&self.read_pool)
+            .await?;
+
+        rows.into_iter()
+            .map(|row| Ok(row.try_get::<i64, _>(0)? as u64))
+            .collect()
+    }
+
+    async fn update_rendition_id(
+        &self,
+        new_id: Option<u64>,
+        content: &mut Content,
+    ) -> Result<(), DataSourceError> {
+        sqlx::query("UPDATE content SET compatibility_content_id = ? WHERE content_id = ?")
+            .bind(new_id.map(|id| id as i64))
+            .bind(content.content.id() as i64)
+            .execute(&self.write_pool)
+            .await?;
+
+        content.rendition = new_id.map(ManyToOne::Id);
+
+        Ok(())
+    }
+
+    async fn get_storyboard(&self, content_id: u64) -> Result<Option<Storyboard>, DataSourceError> {
+        let storyboard: Option<(i64, i32, i32, i32, i32, i32)> = sqlx::query_as(
+            "SELECT media_id, interval, tile_width, tile_height, columns, frame_count FROM storyboards WHERE content_id = ?",
+        )
+        .bind(content_id as i64)
+        .fetch_optional(&self.read_pool)
+        .await?;
+
+        Ok(storyboard.map(
+            |(media_id, interval, tile_width, tile_height, columns, frame_count)| Storyboard {
+                sprite: ManyToOne::Id(media_id as u64),
+                interval,
+                tile: Dimensions {
+                    width: tile_width,
+                    height: tile_height,
+                },
+                columns,
+                frame_count,
+            },
+        ))
+    }
+
+    async fn set_storyboard(
+        &self,
+        content_id: u64,
+        storyboard: &Storyboard,
+    ) -> Result<(), DataSourceError> {
+        sqlx::query(
+            "INSERT INTO storyboards(content_id, media_id, interval, tile_width, tile_height, columns, frame_count) VALUES(?,?,?,?,?,?,?)
+            ON CONFLICT(content_id) DO UPDATE SET media_id = excluded.media_id, interval = excluded.interval, tile_width = excluded.tile_width,
+            tile_height = excluded.tile_height, columns = excluded.columns, frame_count = excluded.frame_count",
+        )
+        .bind(content_id as i64)
+        .bind(storyboard.sprite.id() as i64)
+        .bind(storyboard.interval)
+        .bind(storyboard.tile.width)
+        .bind(storyboard.tile.height)
+        .bind(storyboard.columns)
+        .bind(storyboard.frame_count)
+        .execute(&self.write_pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ImportBatchDataSource for SqliteIndex {
+    async fn add(&self, value: &mut ImportBatch) -> Result<(), DataSourceError> {
+        let started_at = *value.started_at.get_or_insert_with(Utc::now);
+
+        let id = sqlx::query(
+            "INSERT INTO import_batches(name, origin, started_at, finished_at) VALUES(?, ?, ?, ?)",
+        )
+        .bind(value.name.as_deref())
+        .bind(Self::import_batch_origin_str(value.origin))
+        .bind(started_at)
+        .bind(value.finished_at)
+        .execute(&self.write_pool)
+        .await?
+        .last_insert_rowid();
+
+        value.id = id as u64;
+
+        Ok(())
+    }
+
+    async fn update(&self, value: &ImportBatch) -> Result<(), DataSourceError> {
+        sqlx::query("UPDATE import_batches SET name = ? WHERE import_batch_id = ?")
+            .bind(value.name.as_deref())
+            .bind(value.id as i64)
+            .execute(&self.write_pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: u64) -> Result<Option<ImportBatch>, DataSourceError> {
+        let mut rows = sqlx::query("SELECT * FROM import_batches WHERE import_batch_id = ?")
+            .bind(id as i64)
+            .map(|r| Self::map_import_batch(&r))
+            .fetch(&self.read_pool);
+
+        if let Some(row) = rows.try_next().await? {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_page(
+        &self,
+        page: &PageParams,
+    ) -> Result<Page<SearchImportBatch>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
+
+        let total_row_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM import_batches")
+            .fetch_one(conn.deref_mut())
+            .await?;
+
+        let rows = sqlx::query(
+            "SELECT b.*, (SELECT COUNT(*) FROM posts p WHERE p.import_batch_id = b.import_batch_id AND p.deleted_at IS NULL) as 'post_count'
+            FROM import_batches b
+            ORDER BY b.import_batch_id DESC
+            LIMIT ? OFFSET ?",
+        )
+        .bind(page.page_size() as i64)
+        .bind(page.offset() as i64)
+        .map(|r| Self::map_search_import_batch(&r))
+        .fetch_all(conn.deref_mut())
+        .await?;
+
+        Ok(Page {
+            page_size: page.page_size(),
+            page_number: page.offset(),
+            total_row_count: total_row_count.0 as usize,
+            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl TagDataSource for SqliteIndex {
+    async fn add(&self, value: &mut Tag) -> Result<(), DataSourceError> {
+        let id = sqlx::query("INSERT INTO tags(name, group_id, created_at) VALUES(?, ?, ?)")
+            .bind(value.name.as_str())
+            .bind(value.group.as_ref().map(|g| g.id() as i64))
+            .bind(value.created_at)
+            .execute(&self.write_pool)
+            .await?
+            .last_insert_rowid();
+
+        value.id = id as u64;
+
+        Ok(())
+    }
+
+    async fn update(&self, value: &Tag, origin: &ChangeOrigin) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let previous = Self::tag_snapshot(tx.deref_mut(), value.id).await?;
+
+        sqlx::query("UPDATE tags SET name = ?, group_id = ? WHERE tag_id = ?")
+            .bind(value.name.as_str())
+            .bind(value.group.as_ref().map(|g| g.id() as i64))
+            .bind(value.id as i64)
+            .execute(tx.deref_mut())
+            .await?;
+
+        if let Some(previous) = previous {
+            let current = Self::tag_snapshot(tx.deref_mut(), value.id).await?;
+
+            Self::log_change(
+                tx.deref_mut(),
+                ChangeEntity::Tag,
+                value.id,
+                ChangeAction::Update,
+                Some(&ChangeSnapshot::Tag(previous)),
+                current.map(ChangeSnapshot::Tag).as_ref(),
+                origin,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, tag_id: u64, origin: &ChangeOrigin) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let previous = Self::tag_snapshot(tx.deref_mut(), tag_id).await?;
+
+        sqlx::query("DELETE FROM tags_posts WHERE tag_id = ?")
+            .bind(tag_id as i64)
+            .execute(tx.deref_mut())
+            .await?;
+
+        sqlx::query("DELETE FROM tags WHERE tag_id = ?")
+            .bind(tag_id as i64)
+            .execute(tx.deref_mut())
+            .await?;
+
+        if let Some(previous) = previous {
+            Self::log_change(
+                tx.deref_mut(),
+                ChangeEntity::Tag,
+                tag_id,
+                ChangeAction::Delete,
+                Some(&ChangeSnapshot::Tag(previous)),
+                None,
+                origin,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: u64) -> Result<Option<Tag>, DataSourceError> {
+        let mut rows = sqlx::query("SELECT * FROM tags WHERE tag_id = ?")
+            .bind(id as i64)
+            .map(|r| Self::map_tag(&r))
+            .fetch(&self.read_pool);
+
+        if let Some(row) = rows.try_next().await? {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn add_tag_to_post(&self, tag_id: u64, post_id: u64) -> Result<(), DataSourceError> {
+        sqlx::query("INSERT INTO tags_posts(tag_id, post_id) VALUES(?, ?)")
+            .bind(tag_id as i64)
+            .bind(post_id as i64)
+            .execute(&self.write_pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn remove_tag_to_post(&self, tag_id: u64, post_id: u64) -> Result<(), DataSourceError> {
+        sqlx::query("DELETE FROM tags_posts WHERE tag_id = ? AND post_id = ?")
+            .bind(tag_id as i64)
+            .bind(post_id as i64)
+            .execute(&self.write_pool)
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TagGroupDataSource for SqliteIndex {
+    async fn add(&self, value: &mut TagGroup) -> Result<(), DataSourceError> {
+        let id = sqlx::query("INSERT INTO tag_group(name, color, created_at) VALUES(?, ?, ?)")
+            .bind(value.name.as_str())
+            .bind(value.hex_color.as_str())
+            .bind(value.created_at)
+            .execute(&self.write_pool)
+            .await?
+            .last_insert_rowid();
+
+        value.id = id as u64;
+
+        Ok(())
+    }
+
+    async fn get_by_id(&self, id: u64) -> Result<Option<TagGroup>, DataSourceError> {
+        let mut rows = sqlx::query("SELECT * FROM tag_group WHERE group_id = ?")
+            .bind(id as i64)
+            .map(|r| Self::map_tag_group(&r))
+            .fetch(&self.read_pool);
+
+        if let Some(row) = rows.try_next().await? {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn search(
+        &self,
+        page: &PageParams,
+        query: &str,
+        exact: bool,
+    ) -> Result<Page<TagGroup>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
+
+        let total_row_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tag_group")
+            .fetch_one(conn.deref_mut())
+            .await?;
+
+        let query_is_empty = query.len() < 3;
+
+        let where_clause = if exact {
+            " WHERE name = ?"
+        } else {
+            if query_is_empty {
+                " WHERE name LIKE ?"
+            } else {
+                "WHERE tag_groups_vtab MATCH (?)"
+            }
+        };
+
+        let query_str =
+            format!("SELECT * FROM tag_groups_vtab {where_clause} ORDER BY rank LIMIT ? OFFSET ?");
+
+        let mut sql_query = sqlx::query(query_str.as_str());
+
+        if exact {
+            sql_query = sql_query.bind(query);
+        } else {
+            if !query_is_empty {
+                let value = query
+                    .trim()
+                    .split(' ')
+                    .map(|word| format!("{{name}}: \"{word}\""))
+                    .collect::<Vec<String>>()
+                    .join(" OR ");
+
+                sql_query = sql_query.bind(value);
+            } else {
+                sql_query = sql_query.bind(format!("%{query}%"))
+            }
+        }
+
+        let rows = sql_query
+            .bind(page.page_size() as i64)
+            .bind(page.offset() as i64)
+            .map(|r| Self::map_tag_group(&r))
+            .fetch_all(conn.deref_mut())
+            .await?;
+
+        Ok(Page {
+            page_size: page.page_size(),
+            page_number: page.offset(),
+            total_row_count: total_row_count.0 as usize,
+            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+        })
+    }
+}
+
+#[async_trait]
+impl CustomFieldDataSource for SqliteIndex {
+    async fn add(&self, value: &mut CustomField) -> Result<(), DataSourceError> {
+        if !CustomField::is_valid_name(&value.name) {
+            return Err(DataSourceError::InvalidFieldName);
+        }
+
+        let id =
+            sqlx::query("INSERT INTO custom_fields(name, field_type, created_at) VALUES(?, ?, ?)")
+                .bind(value.name.as_str())
+                .bind(Self::custom_field_type_str(value.field_type))
+                .bind(value.created_at)
+                .execute(&self.write_pool)
+                .await?
+                .last_insert_rowid();
+
+        value.id = id as u64;
+
+        Ok(())
+    }
+
+    async fn delete(&self, field_id: u64) -> Result<(), DataSourceError> {
+        let result = sqlx::query("DELETE FROM custom_fields WHERE field_id = ?")
+            .bind(field_id as i64)
+            .execute(&self.write_pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(DataSourceError::NotFound);
+        }
+
+        Ok(())
+    }
+
+    async fn get_by_id(&self, field_id: u64) -> Result<Option<CustomField>, DataSourceError> {
+        sqlx::query("SELECT * FROM custom_fields WHERE field_id = ?")
+            .bind(field_id as i64)
+            .fetch_optional(&self.read_pool)
+            .await?
+            .map(|r| Self::map_custom_field(&r))
+            .transpose()
+    }
+
+    async fn get_by_name(&self, name: &str) -> Result<Option<CustomField>, DataSourceError> {
+        sqlx::query("SELECT * FROM custom_fields WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.read_pool)
+            .await?
+            .map(|r| Self::map_custom_field(&r))
+            .transpose()
+    }
+
+    async fn get_all(&self) -> Result<Vec<CustomField>, DataSourceError> {
+        sqlx::query("SELECT * FROM custom_fields ORDER BY name ASC")
+            .map(|r| Self::map_custom_field(&r))
+            .fetch_all(&self.read_pool)
+            .await?
+            .into_iter()
+            .collect()
+    }
+
+    async fn get_from_post(&self, post_id: u64) -> Result<Vec<PostCustomField>, DataSourceError> {
+        sqlx::query("SELECT f.*, pf.value FROM posts_custom_fields pf JOIN custom_fields f ON f.field_id = pf.field_id WHERE pf.post_id = ? ORDER BY f.name ASC")
+            .bind(post_id as i64)
+            .map(|r| Self::map_post_custom_field(&r))
+            .fetch_all(&self.read_pool)
+            .await?
+            .into_iter()
+            .collect()
+    }
+
+    async fn set_value(
+        &self,
+        post_id: u64,
+        field_id: u64,
+        value: Option<&CustomFieldValue>,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let field = sqlx::query("SELECT * FROM custom_fields WHERE field_id = ?")
+            .bind(field_id as i64)
+            .fetch_optional(tx.deref_mut())
+            .await?
+            .map(|r| Self::map_custom_field(&r))
+            .transpose()?
+            .ok_or(DataSourceError::NotFound)?;
+
+        let post_exists: Option<(i64,)> =
+            sqlx::query_as("SELECT post_id FROM posts WHERE post_id = ?")
+                .bind(post_id as i64)
+                .fetch_optional(tx.deref_mut())
+                .await?;
+
+        if post_exists.is_none() {
+            return Err(DataSourceError::NotFound);
+        }
+
+        let previous = Self::post_snapshot(tx.deref_mut(), post_id).await?;
+
+        let Some(value) = value else {
+            sqlx::query("DELETE FROM posts_custom_fields WHERE post_id = ? AND field_id = ?")
+                .bind(post_id as i64)
+                .bind(field_id as i64)
+                .execute(tx.deref_mut())
+                .await?;
+
+            Self::log_post_change(
+                tx.deref_mut(),
+                post_id,
+                ChangeAction::Update,
+                previous,
+                origin,
+            )
+            .await?;
+
+            tx.commit().await?;
+
+            return Ok(());
+        };
+
+        if value.field_type() != field.field_type {
+            return Err(DataSourceError::FieldTypeMismatch);
+        }
+
+        let query = sqlx::query("INSERT INTO posts_custom_fields(post_id, field_id, value) VALUES(?, ?, ?) ON CONFLICT(post_id, field_id) DO UPDATE SET value = excluded.value")
+            .bind(post_id as i64)
+            .bind(field_id as i64);
+
+        let query = match value {
+            CustomFieldValue::Number(number) => query.bind(*number),
+            CustomFieldValue::String(string) => query.bind(string.as_str()),
+            CustomFieldValue::Date(date) => query.bind(Self::custom_date_str(date)),
+            CustomFieldValue::Url(url) => query.bind(url.as_str()),
+        };
+
+        query.execute(tx.deref_mut()).await?;
+
+        Self::log_post_change(
+            tx.deref_mut(),
+            post_id,
+            ChangeAction::Update,
+            previous,
+            origin,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CrossDataSource for SqliteIndex {
+    async fn get_post_detail(&self, post_id: u64) -> Result<Option<PostDetail>, DataSourceError> {
+        let mut get_post_query = sqlx::query(" SELECT *, (SELECT COUNT(*) FROM post_items pi WHERE pi.post_id = p.post_id) as 'item_count' FROM posts p WHERE p.post_id = ?")
+            .bind(post_id as i64)
+            .fetch(&self.read_pool);
+
+        if let Some(row) = get_post_query.try_next().await? {
+            let post = Self::map_post(&row)?;
+
+            let item_count: i64 = row.try_get("item_count")?;
+
+            drop(row);
+            drop(get_post_query);
+
+            let tags = CrossDataSource::get_tags_from_post(self, post_id).await?;
+            let custom_fields = CustomFieldDataSource::get_from_post(self, post_id).await?;
+
+            Ok(Some(PostDetail {
+                post,
+                tags,
+                item_count: item_count as usize,
+                custom_fields,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn search_posts(
+        &self,
+        query: &PostSearchQuery,
+        page: &PageParams,
+    ) -> Result<Page<SearchPost>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
+
+        let order = match (query.order, query.text.is_some()) {
+            (Some(PostSearchQueryOrder::Newest), _)
+            | (None, _)
+            | (Some(PostSearchQueryOrder::Relevant), false) => "p.created_at DESC",
+            (Some(PostSearchQueryOrder::Oldest), _) => "p.created_at ASC",
+            (Some(PostSearchQueryOrder::Relevant), true) => "rank ASC, p.created_at DESC",
+            (Some(PostSearchQueryOrder::Random(_)), _) => {
+                "substr(p.post_id * ?, length(p.post_id) + 2)"
+            }
+            (Some(PostSearchQueryOrder::Rating), false) => {
+                "p.rating DESC NULLS LAST, p.created_at DESC"
+            }
+            (Some(PostSearchQueryOrder::Rating), true) => {
+                "pp.rating DESC NULLS LAST, p.created_at DESC"
+            }
+            (Some(PostSearchQueryOrder::RecentlyWatched), _) => {
+                "(SELECT MAX(watch_updated_at) FROM post_items WHERE post_id = p.post_id) DESC NULLS LAST, p.created_at DESC"
+            }
+        };
+
+        let table = if query.text.is_none() {
+            "posts"
+        } else {
+            "posts_vtab"
+        };
+
+        // the virtual table only mirrors the searchable columns of posts
+        let (post_columns, post_join) = if query.text.is_none() {
+            ("p.*", "")
+        } else {
+            (
+                "p.*, pp.deleted_at, pp.rating, pp.favourite",
+                "JOIN posts pp ON pp.post_id = p.post_id",
+            )
+        };
+
+        // snippets and highlights are only available when the virtual table is matched
+        let is_match = matches!(query.text.as_deref(), Some(text) if text.len() >= 3);
+        let highlight = is_match && query.highlight;
+
+        let snippet = if is_match { SEARCH_SNIPPET } else { "NULL" };
+        let highlights = if highlight {
+            format!(", {}", Self::search_highlight_columns())
+        } else {
+            String::new()
+        };
+
+        let after_where = format!("ORDER BY {order} LIMIT ? OFFSET ?");
+
+        let search_query_str = SqliteIndex::create_search_query_str(
+            query,
+            &format!(
+                "SELECT {post_columns}, {SEARCH_POST_COLUMNS}, {snippet} as 'snippet'{highlights}
+        FROM {table} p
+        {post_join}
+        {SEARCH_POST_JOINS}"
+            ),
+            &after_where,
+        );
+
+        let mut search_query =
+            SqliteIndex::add_search_query_values(query, search_query_str.as_str());
+
+        if let Some(PostSearchQueryOrder::Random(seed)) = query.order {
+            search_query = search_query.bind(seed);
+        }
+
+        let rows = search_query
+            .bind(page.page_size() as i64)
+            .bind(page.offset() as i64)
+            .map(|r| {
+                let mut post = Self::map_search_post(&r)?;
+
+                if highlight {
+                    post.highlights = Self::map_search_highlights(&r)?;
+                }
+
+                Ok::<_, DataSourceError>(post)
+            })
+            .fetch_all(conn.deref_mut())
+            .await?;
+
+        let query_row_count_str = SqliteIndex::create_search_query_str(
+            query,
+            &format!("SELECT COUNT(*) FROM {table} p"),
+            "",
+        );
+        let query_row_count = SqliteIndex::add_search_query_values(query, &query_row_count_str);
+
+        let total_row_count: i64 = query_row_count
+            .map(|r| r.get(0))
+            .fetch_one(conn.deref_mut())
+            .await?;
+
+        Ok(Page {
+            page_size: page.page_size(),
+            page_number: page.offset(),
+            total_row_count: total_row_count as usize,
+            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+        })
+    }
+
+    async fn get_geo_clusters(
+        &self,
+        query: &PostSearchQuery,
+        zoom: u8,
+    ) -> Result<Vec<GeoCluster>, DataSourceError> {
+        let table = if query.text.is_none() {
+            "posts"
+        } else {
+            "posts_vtab"
+        };
+
+        // the coordinates are shifted to be positive, so casting them rounds down to a cell
+        let query_str = SqliteIndex::create_search_query_str(
+            query,
+            &format!(
+                "SELECT AVG(gm.latitude) as 'latitude', AVG(gm.longitude) as 'longitude', COUNT(DISTINCT p.post_id) as 'post_count', MAX(p.post_id) as 'post_id'
+        FROM {table} p
+        JOIN post_items gpi ON gpi.post_id = p.post_id
+        JOIN media gm ON gm.media_id = gpi.content_id AND gm.latitude IS NOT NULL AND gm.longitude IS NOT NULL"
+            ),
+            "GROUP BY CAST((gm.latitude + 90) / ? AS INTEGER), CAST((gm.longitude + 180) / ? AS INTEGER)",
+        );
+
+        let cell_size = GeoCluster::cell_size(zoom);
+
+        let clusters = SqliteIndex::add_search_query_values(query, &query_str)
+            .bind(cell_size)
+            .bind(cell_size)
+            .map(|r| {
+                Ok::<_, DataSourceError>(GeoCluster {
+                    latitude: r.try_get("latitude")?,
+                    longitude: r.try_get("longitude")?,
+                    post_count: r.try_get::<'_, i64, _>("post_count")? as u64,
+                    post_id: r.try_get::<'_, i64, _>("post_id")? as u64,
+                })
+            })
+            .fetch_all(&self.read_pool)
+            .await?;
+
+        clusters.into_iter().collect()
+    }
+
+    async fn search_items(
+        &self,
+        post_id: u64,
+        query: &PostItemSearchQuery,
+        page: PageParams,
+    ) -> Result<Page<SearchPostItem>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
+
+        let total_row_count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM post_items pi JOIN posts p ON pi.post_id = p.post_id WHERE pi.post_id = ? AND p.deleted_at IS NULL")
+                .bind(post_id as i64)
+                .fetch_one(conn.deref_mut())
+                .await?;
+
+        let rows = sqlx::query(
+            "SELECT pi.*, m.*, cm.mime_type || '/' || cm.mime_sub_type as content_mime_type, cm.duration as 'content_duration', cm.frame_count as 'content_frame_count' FROM post_items pi
+        JOIN posts p ON pi.post_id = p.post_id
+        LEFT JOIN content c ON pi.content_id = c.content_id
+        LEFT JOIN media cm ON pi.content_id = cm.media_id
+        LEFT JOIN media m ON c.thumbnail_id = m.media_id
+        WHERE pi.post_id = ? AND p.deleted_at IS NULL AND (? != 0 OR content_duration > 0)
+        ORDER BY pi.item_order ASC
+        LIMIT ? OFFSET ?",
+        )
+            .bind(post_id as i64)
+            .bind(!query.require_playable)
+            .bind(page.page_size() as i64)
+            .bind(page.offset() as i64)
+            .map(|r| Self::map_search_post_item(&r))
+            .fetch_all(conn.deref_mut())
+            .await?;
+
+        Ok(Page {
+            page_size: page.page_size(),
+            page_number: page.offset(),
+            total_row_count: total_row_count.0 as usize,
+            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+        })
+    }
+
+    async fn get_full_post_item(
+        &self,
+        post_id: u64,
+        position: i32,
+    ) -> Result<Option<PostItem>, DataSourceError> {
+        let mut rows = sqlx::query(
+            "SELECT * FROM post_items pi
+            LEFT JOIN posts p ON p.post_id = pi.post_id
+            LEFT JOIN content c ON c.content_id = pi.content_id
+            LEFT JOIN media m ON m.media_id = pi.content_id
+            WHERE pi.post_id = ? AND pi.item_order = ?",
+        )
+        .bind(post_id as i64)
+        .bind(position as i64)
+        .map(|r| Self::map_full_post_item(&r))
+        .fetch(&self.read_pool);
+
+        if let Some(row) = rows.try_next().await? {
+            Ok(Some(row?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn add_full_post(
+        &self,
+        data: CreateFullPost,
+    ) -> Result<(ImportBatch, Vec<Post>), DataSourceError> {
+        if data.rating.is_some_and(|rating| rating > Post::MAX_RATING) {
+            return Err(DataSourceError::InvalidRating);
+        }
+
+        let now = Utc::now();
+
+        let mut tx = self.write_pool.begin().await?;
+
+        let (use_taken_at,): (bool,) = sqlx::query_as("SELECT use_taken_at FROM bucket_settings")
+            .fetch_one(tx.deref_mut())
+            .await?;
+
+        // when each item was taken, only looked up if it can become the creation date
+        let mut taken_at = Vec::with_capacity(data.items.len());
+
+        if data.created_at.is_none() && use_taken_at {
+            for item in data.items.iter() {
+                let (item_taken_at,): (Option<DateTime<Utc>>,) =
+                    sqlx::query_as("SELECT taken_at FROM media WHERE media_id = ?")
+                        .bind(item.content_id as i64)
+                        .fetch_optional(tx.deref_mut())
+                        .await?
+                        .unwrap_or((None,));
+
+                taken_at.push(item_taken_at);
+            }
+        }
+
+        let batch_id = match data.batch_id {
+            None => {
+                sqlx::query("INSERT INTO import_batches(name, origin, started_at) VALUES(?, ?, ?)")
+                    .bind(data.batch_name.as_deref())
+                    .bind(Self::import_batch_origin_str(data.batch_origin))
+                    .bind(Utc::now())
+                    .execute(tx.deref_mut())
+                    .await?
+                    .last_insert_rowid() as u64
+            }
+            Some(v) => v,
+        };
+
+        let batch = sqlx::query(
+            "UPDATE import_batches SET finished_at = ? WHERE import_batch_id = ? RETURNING *",
+        )
+        .bind(Utc::now())
+        .bind(batch_id as i64)
+        .map(|r| Self::map_import_batch(&r))
+        .fetch_optional(tx.deref_mut())
+        .await?
+        .transpose()?
+        .ok_or(DataSourceError::NotFound)?;
+
+        let amount_of_posts_to_create = data.items.len().max(1);
+        let mut posts = Vec::with_capacity(amount_of_posts_to_create);
+
+        for i in 0..amount_of_posts_to_create {
+            // a flattened post only contains the i-th item, otherwise the earliest photo counts
+            let post_taken_at = if data.flatten {
+                taken_at.get(i).copied().flatten()
+            } else {
+                taken_at.iter().flatten().min().copied()
+            };
+
+            let created_at = data.created_at.or(post_taken_at).unwrap_or(now);
+
+            let id = sqlx::query("INSERT INTO posts(source, title, description, import_batch_id, created_at, rating, favourite) VALUES(?,?,?,?,?,?,?)")
+                .bind(data.source.as_ref().map(|url| url.as_str()))
+                .bind(data.title.as_deref())
+                .bind(data.description.as_deref())
+                .bind(batch.id as i64)
+                .bind(created_at)
+                .bind(data.rating)
+                .bind(data.favourite)
+                .execute(tx.deref_mut())
+                .await?
+                .last_insert_rowid();
+
+            let post = Post {
+                id: id as u64,
+                source: data.source.clone(),
+                title: data.title.clone(),
+                description: data.description.clone(),
+                import_batch: ManyToOne::Obj(batch.clone()),
+                created_at,
+                deleted_at: None,
+                rating: data.rating,
+                favourite: data.favourite,
+            };
+
+            posts.push(post);
+
+            if !data.flatten {
+                break;
+            }
+        }
+
+        let mut items = Vec::with_capacity(data.items.len());
+
+        if !data.flatten {
+            for post in posts.iter() {
+                for (position, item) in data.items.iter().enumerate() {
+                    items.push((post.id, item, position));
+                }
+            }
+        } else {
+            for (i, post) in posts.iter().enumerate() {
+                let item = &data.items[i];
+                items.push((post.id, item, 0));
+            }
+        }
+
+        if !data.items.is_empty() {
+            let insert_str = "INSERT INTO post_items(post_id, item_order, content_id, original_name, original_accessed, original_modified, original_directory, uploaded_at) VALUES \n";
+            let row_value_str = "(?, ?, ?, ?,?,?,?,?),\n";
+
+            let mut insert_items_query =
+                String::with_capacity(insert_str.len() + row_value_str.len() * items.len());
+
+            insert_items_query.push_str(insert_str);
+
+            for _ in items.iter() {
+                insert_items_query.push_str(row_value_str);
+            }
+
+            // Remove the last newline and comma
+            insert_items_query.pop();
+            insert_items_query.pop();
+
+            let mut query = sqlx::query(insert_items_query.as_str());
+
+            for (post_id, item, position) in items {
+                query = query.bind(post_id as i64);
+                query = query.bind(position as i64);
+                query = query.bind(item.content_id as i64);
+
+                query = query.bind(item.metadata.original_filename.as_deref());
+                query = query.bind(item.metadata.original_accessed_at);
+                query = query.bind(item.metadata.original_modified_at);
+                query = query.bind(item.metadata.original_directory.as_deref());
+                query = query.bind(Utc::now());
+            }
+
+            query.execute(tx.deref_mut()).await?;
+        }
+
+        if !data.tag_ids.is_empty() {
+            let insert_str = "INSERT INTO tags_posts(tag_id, post_id) VALUES \n";
+            let row_value_str = "(?, ?),\n";
+
+            let mut insert_items_query = String::with_capacity(
+                insert_str.len() + row_value_str.len() * (data.tag_ids.len() * posts.len()),
+            );
+
+            insert_items_query.push_str(insert_str);
+
+            for _ in 0..posts.len() * data.tag_ids.len() {
+                insert_items_query.push_str(row_value_str);
+            }
+
+            // Remove the last newline and comma
+            insert_items_query.pop();
+            insert_items_query.pop();
+
+            let mut query = sqlx::query(insert_items_query.as_str());
+
+            for post in posts.iter() {
+                for tag_id in data.tag_ids.iter() {
+                    query = query.bind(*tag_id as i64);
+                    query = query.bind(post.id as i64);
+                }
+            }
+
+            query.execute(tx.deref_mut()).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok((batch, posts))
+    }
+
+    async fn search_tags(
+        &self,
+        page: &PageParams,
+        query: &str,
+        exact: bool,
+    ) -> Result<Page<SearchTag>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
+
+        let total_row_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tags")
+            .fetch_one(conn.deref_mut())
+            .await?;
+
+        let query_is_empty = query.len() < 3;
+
+        let where_clause = if query.is_empty() {
+            ""
+        } else {
+            if exact {
+                " WHERE t.name = ?"
+            } else {
+                if query_is_empty {
+                    " WHERE t.name LIKE ?"
+                } else {
+                    "WHERE tags_vtab MATCH (?)"
+                }
+            }
+        };
+
+        let query_str =
+            format!("SELECT t.*, g.name as g_name, g.color as color, g.created_at as g_created_at, (SELECT COUNT(*) FROM tags_posts tpc WHERE tpc.tag_id = t.tag_id) as 'linked_posts' FROM tags_vtab t LEFT JOIN tag_group g ON t.group_id = g.group_id {where_clause} ORDER BY rank, t.created_at DESC LIMIT ? OFFSET ?");
+
+        let mut sql_query = sqlx::query(query_str.as_str());
+
+        if !query.is_empty() {
+            if exact {
+                sql_query = sql_query.bind(query);
+            } else {
+                if !query_is_empty {
+                    let value = query
+                        .trim()
+                        .split(' ')
+                        .map(|word| format!("{{name}}: \"{word}\""))
+                        .collect::<Vec<String>>()
+                        .join(" OR ");
+
+                    sql_query = sql_query.bind(value);
+                } else {
+                    sql_query = sql_query.bind(format!("%{query}%"))
+                }
+            }
+        }
+
+        let rows = sql_query
+            .bind(page.page_size() as i64)
+            .bind(page.offset() as i64)
+            .map(|r| Self::map_search_tag(&r))
+            .fetch_all(conn.deref_mut())
+            .await?;
+
+        Ok(Page {
+            page_size: page.page_size(),
+            page_number: page.offset(),
+            total_row_count: total_row_count.0 as usize,
+            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+        })
+    }
+
+    async fn update_full_post(
+        &self,
+        value: &Post,
+        tags: &[u64],
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        self.write_full_post(tx.deref_mut(), value, tags, ChangeAction::Update, origin)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn cascade_delete_post(
+        &self,
+        id: u64,
+        origin: &ChangeOrigin,
+    ) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        Self::delete_post(tx.deref_mut(), id, origin).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn trash_post(&self, id: u64, origin: &ChangeOrigin) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        Self::set_post_deleted_at(
+            tx.deref_mut(),
+            id,
+            Some(Utc::now()),
+            ChangeAction::Trash,
+            origin,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn restore_post(&self, id: u64, origin: &ChangeOrigin) -> Result<(), DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        Self::set_post_deleted_at(tx.deref_mut(), id, None, ChangeAction::Restore, origin).await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn undo_import_batch(
+        &self,
+        batch_id: u64,
+        origin: &ChangeOrigin,
+    ) -> Result<u64, DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let batch_count: (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM import_batches WHERE import_batch_id = ?")
+                .bind(batch_id as i64)
+                .fetch_one(tx.deref_mut())
+                .await?;
+
+        if batch_count.0 == 0 {
+            return Err(DataSourceError::NotFound);
+        }
+
+        let post_ids: Vec<(i64,)> = sqlx::query_as(
+            "SELECT post_id FROM posts WHERE import_batch_id = ? AND deleted_at IS NULL",
+        )
+        .bind(batch_id as i64)
+        .fetch_all(tx.deref_mut())
+        .await?;
+
+        let now = Utc::now();
+
+        for (post_id,) in post_ids.iter() {
+            Self::set_post_deleted_at(
+                tx.deref_mut(),
+                *post_id as u64,
+                Some(now),
+                ChangeAction::Trash,
+                origin,
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(post_ids.len() as u64)
+    }
+
+    async fn purge_post(
+        &self,
+        id: u64,
+        origin: &ChangeOrigin,
+    ) -> Result<Vec<Media>, DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let trashed: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM posts WHERE post_id = ? AND deleted_at IS NOT NULL",
+        )
+        .bind(id as i64)
+        .fetch_one(tx.deref_mut())
+        .await?;
+
+        if trashed.0 == 0 {
+            return Err(DataSourceError::NotFound);
+        }
+
+        let removed = Self::purge_posts(tx.deref_mut(), &[id], origin).await?;
+
+        tx.commit().await?;
+
+        Ok(removed)
+    }
+
+    async fn purge_trash(
+        &self,
+        deleted_before: DateTime<Utc>,
+    ) -> Result<Vec<Media>, DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let ids: Vec<(i64,)> = sqlx::query_as(
+            "SELECT post_id FROM posts WHERE deleted_at IS NOT NULL AND deleted_at < ?",
+        )
+        .bind(deleted_before)
+        .fetch_all(tx.deref_mut())
+        .await?;
+
+        let ids: Vec<u64> = ids.into_iter().map(|(id,)| id as u64).collect();
+
+        let removed = Self::purge_posts(tx.deref_mut(), &ids, &ChangeOrigin::default()).await?;
+
+        tx.commit().await?;
+
+        Ok(removed)
+    }
+
+    async fn revert_post(
+        &self,
+        post_id: u64,
+        change_id: u64,
+        fields: &[PostField],
+        origin: &ChangeOrigin,
+    ) -> Result<Post, DataSourceError> {
+        let mut tx = self.write_pool.begin().await?;
+
+        let change = sqlx::query(
+            "SELECT * FROM change_log WHERE change_id = ? AND entity_type = 'post' AND entity_id = ?",
+        )
+        .bind(change_id as i64)
+        .bind(post_id as i64)
+        .map(|r| Self::map_change(&r))
+        .fetch_optional(tx.deref_mut())
+        .await?
+        .transpose()?
+        .ok_or(DataSourceError::NotFound)?;
+
+        let Some(ChangeSnapshot::Post(target)) = change.previous else {
+            return Err(DataSourceError::NotFound);
+        };
+
+        let mut post = sqlx::query("SELECT * FROM posts WHERE post_id = ?")
+            .bind(post_id as i64)
+            .map(|r| Self::map_post(&r))
+            .fetch_optional(tx.deref_mut())
+            .await?
+            .transpose()?
+            .ok_or(DataSourceError::NotFound)?;
+
+        let current_tags: Vec<(i64,)> =
+            sqlx::query_as("SELECT tag_id FROM tags_posts WHERE post_id = ?")
+                .bind(post_id as i64)
+                .fetch_all(tx.deref_mut())
+                .await?;
+        let mut tags: Vec<u64> = current_tags.into_iter().map(|(id,)| id as u64).collect();
+
+        for field in fields {
+            match field {
+                PostField::Source => post.source = target.source.clone(),
+                PostField::Title => post.title = target.title.clone(),
+                PostField::Description => post.description = target.description.clone(),
+                PostField::Tags => {
+                    let mut existing = Vec::with_capacity(target.tag_ids.len());
+
+                    for tag_id in target.tag_ids.iter() {
+                        if Self::tag_snapshot(tx.deref_mut(), *tag_id).await?.is_some() {
+                            existing.push(*tag_id);
+                        }
+                    }
+
+                    tags = existing;
+                }
+            }
+        }
+
+        self.write_full_post(tx.deref_mut(), &post, &tags, ChangeAction::Revert, origin)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(post)
+    }
+
+    async fn merge_posts(
+        &self,
+        post_ids: &[u64],
+        origin: &ChangeOrigin,
+    ) -> Result<Post, DataSourceError> {
+        let mut unique_ids = Vec::with_capacity(post_ids.len());
+
+        for id in post_ids {
+            if !unique_ids.contains(id) {
+                unique_ids.push(*id);
+            }
+        }
+
+        let Some((&target_id, merged_ids)) = unique_ids.split_first() else {
+            return Err(DataSourceError::NotFound);
+        };
+
+        let mut tx = self.write_pool.begin().await?;
+
+        let params = unique_ids
+            .iter()
+            .map(|_| "?")
+            .collect::<Vec<_>>()
+            .join(", ");
+        let count_query = format!("SELECT COUNT(*) FROM posts WHERE post_id IN ({params})");
+        let mut query = sqlx::query_as::<_, (i64,)>(&count_query);
+
+        for id in unique_ids.iter() {
+            query = query.bind(*id as i64);
+        }
+
+        if query.fetch_one(tx.deref_mut()).await?.0 as usize != unique_ids.len() {
+            return Err(DataSourceError::NotFound);
+        }
+
+        let previous = Self::post_snapshot(tx.deref_mut(), target_id).await?;
+
+        for merged_id in merged_ids {
+            let merged_previous = Self::post_snapshot(tx.deref_mut(), *merged_id).await?;
+            let offset = Self::count_post_items(tx.deref_mut(), target_id).await?;
+
+            sqlx::query(
+                "UPDATE post_items SET post_id = ?, item_order = item_order + ? WHERE post_id = ?",
+            )
+            .bind(target_id as i64)
+            .bind(offset)
+            .bind(*merged_id as i64)
+            .execute(tx.deref_mut())
+            .await?;
+
+            sqlx::query("INSERT OR IGNORE INTO tags_posts(tag_id, post_id) SELECT tag_id, ? FROM tags_posts WHERE post_id = ?")
+                .bind(target_id as i64)
+                .bind(*merged_id as i64)
+                .execute(tx.deref_mut())
+                .await?;
+
+            sqlx::query("DELETE FROM tags_posts WHERE post_id = ?")
+                .bind(*merged_id as i64)
+                .execute(tx.deref_mut())
+                .await?;
+
+            Self::copy_custom_fields(tx.deref_mut(), *merged_id, target_id).await?;
+
+            sqlx::query("DELETE FROM posts WHERE post_id = ?")
+                .bind(*merged_id as i64)
+                .execute(tx.deref_mut())
+                .await?;
+
+            Self::log_post_change(
+                tx.deref_mut(),
+                *merged_id,
+                ChangeAction::Delete,
+                merged_previous,
+                origin,
+            )
+            .await?;
+        }
+
+        Self::log_post_change(
+            tx.deref_mut(),
+            target_id,
+            ChangeAction::Update,
+            previous,
+            origin,
+        )
+        .await?;
+
+        let post = sqlx::query("SELECT * FROM posts WHERE post_id = ?")
+            .bind(target_id as i64)
+            .map(|r| Self::map_post(&r))
+            .fetch_one(tx.deref_mut())
+            .await??;
+
+        tx.commit().await?;
+
+        Ok(post)
+    }
+
+    async fn split_post(
+        &self,
+        post_id: u64,
+        positions: &[i32],
+        origin: &ChangeOrigin,
+    ) -> Result<Post, DataSourceError> {
+        let mut positions = positions.to_vec();
+        positions.sort_unstable();
+        positions.dedup();
+
+        let mut tx = self.write_pool.begin().await?;
+
+        let item_count = Self::count_post_items(tx.deref_mut(), post_id).await?;
+
+        if positions.iter().any(|p| !(0..item_count).contains(p)) {
+            return Err(DataSourceError::NotFound);
+        }
+
+        if positions.is_empty() || positions.len() as i32 == item_count {
+            return Err(DataSourceError::EmptyPost);
+        }
+
+        let previous = Self::post_snapshot(tx.deref_mut(), post_id).await?;
+
+        let new_id = sqlx::query("INSERT INTO posts(source, title, import_batch_id, created_at, rating, favourite) SELECT source, title, import_batch_id, created_at, rating, favourite FROM posts WHERE post_id = ?")
+            .bind(post_id as i64)
+            .execute(tx.deref_mut())
+            .await?
+            .last_insert_rowid();
+
+        for (new_position, old_position) in positions.iter().enumerate() {
+            sqlx::query("UPDATE post_items SET post_id = ?, item_order = ? WHERE post_id = ? AND item_order = ?")
+                .bind(new_id)
+                .bind(new_position as i32)
+                .bind(post_id as i64)
+                .bind(*old_position)
+                .execute(tx.deref_mut())
+                .await?;
+        }
+
+        Self::compact_post_items(tx.deref_mut(), post_id).await?;
+
+        sqlx::query(
+            "INSERT INTO tags_posts(tag_id, post_id) SELECT tag_id, ? FROM tags_posts WHERE post_id = ?",
+        )
+        .bind(new_id)
+        .bind(post_id as i64)
+        .execute(tx.deref_mut())
+        .await?;
+
+        Self::copy_custom_fields(tx.deref_mut(), post_id, new_id as u64).await?;
+
+        Self::log_post_change(
+            tx.deref_mut(),
+            post_id,
+            ChangeAction::Update,
+            previous,
+            origin,
+        )
+        .await?;
+
+        Self::log_post_change(
+            tx.deref_mut(),
+            new_id as u64,
+            ChangeAction::Create,
+            None,
+            origin,
+        )
+        .await?;
+
+        let post = sqlx::query("SELECT * FROM posts WHERE post_id = ?")
+            .bind(new_id)
+            .map(|r| Self::map_post(&r))
+            .fetch_one(tx.deref_mut())
+            .await??;
+
+        tx.commit().await?;
+
+        Ok(post)
+    }
+
+    async fn graph_post(&self, query: &PostGraphQuery) -> Result<Graph, DataSourceError> {
+        if query.select.is_empty() {
+            return Ok(Graph { series: vec![] });
+        }
+
+        let mut conn = self.read_pool.acquire().await?;
+
+        let (x_axis, x_axis_name, x_axis_kind, group_join) = match query.discriminator {
+            GraphDiscriminator::Duration(_) => {
+                ("MIN(p.created_at)".to_string(), "null", "date", "")
+            }
+            GraphDiscriminator::Tag => (
+                "t.tag_id".to_string(),
+                "t.name",
+                "tag",
+                "JOIN tags_posts tp ON tp.post_id = p.post_id
+                JOIN tags t ON t.tag_id = tp.tag_id",
+            ),
+            // a post with several tags of the same group is only counted once for that group
+            GraphDiscriminator::TagGroup => (
+                "g.group_id".to_string(),
+                "g.name",
+                "tag_group",
+                "JOIN (SELECT DISTINCT tp.post_id, t.group_id FROM tags_posts tp JOIN tags t ON t.tag_id = tp.tag_id) tg ON tg.post_id = p.post_id
+                JOIN tag_group g ON g.group_id = tg.group_id",
+            ),
+            GraphDiscriminator::MimeType => (
+                "m.mime_type || '/' || m.mime_sub_type".to_string(),
+                "null",
+                "mime_type",
+                "",
+            ),
+            GraphDiscriminator::ImportBatch => (
+                "b.import_batch_id".to_string(),
+                "b.name",
+                "import_batch",
+                "JOIN import_batches b ON b.import_batch_id = p.import_batch_id",
+            ),
+            GraphDiscriminator::Dimension(size) => (
+                format!("(MAX(m.width, m.height) / {0}) * {0}", size.max(1)),
+                "null",
+                "dimension",
+                "",
+            ),
+            GraphDiscriminator::None => ("null".to_string(), "null", "none", ""),
+        };
+
+        let is_cumulative = matches!(query.discriminator, GraphDiscriminator::Duration(_));
+
+        let selects = query
+            .select
+            .iter()
+            .enumerate()
+            .map(|(i, select)| {
+                let value = match select {
+                    GraphSelect::Count => "COUNT(DISTINCT p.post_id)",
+                    GraphSelect::ItemCount => "COUNT(pi.content_id)",
+                    GraphSelect::FileSize => "COALESCE(SUM(m.file_size), 0)",
+                    GraphSelect::Duration => "COALESCE(SUM(m.duration), 0)",
+                };
+
+                if is_cumulative {
+                    format!("SUM({value} * 1.0) OVER (ORDER BY MIN(p.created_at) ROWS UNBOUNDED PRECEDING) AS y{i}")
+                } else {
+                    format!("{value} * 1.0 AS y{i}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let before_where = format!(
+            "SELECT '{x_axis_kind}' AS kind, {x_axis} AS x, {x_axis_name} AS x_name, {selects} FROM posts_vtab p
+            {group_join}
+            LEFT JOIN post_items pi ON pi.post_id = p.post_id
+            LEFT JOIN media m ON m.media_id = pi.content_id"
+        );
+
+        let after_where = match query.discriminator {
+            GraphDiscriminator::Duration(_) => {
+                "GROUP BY strftime('%s', p.created_at) / ? ORDER BY MIN(p.created_at) ASC"
+            }
+            GraphDiscriminator::None => "",
+            GraphDiscriminator::Dimension(_) => "GROUP BY x ORDER BY x ASC",
+            _ => "GROUP BY x ORDER BY y0 DESC",
+        };
+
+        let query_str =
+            SqliteIndex::create_search_query_str(&query.filter, &before_where, after_where);
+        let mut graph_query = SqliteIndex::add_search_query_values(&query.filter, &query_str);
+
+        if let GraphDiscriminator::Duration(duration) = query.discriminator {
+            graph_query = graph_query.bind(duration.as_secs() as i64);
+        }
+
+        let rows = graph_query
+            .map(|r| Self::map_graph_row(&r, query.select.len()))
+            .fetch_all(conn.deref_mut())
+            .await?
+            .into_iter()
+            .collect::<Result<Vec<_>, DataSourceError>>()?;
+
+        let mut series: Vec<GraphSeries> = query
+            .select
+            .iter()
+            .map(|select| GraphSeries {
+                select: *select,
+                points: Vec::with_capacity(rows.len()),
+            })
+            .collect();
+
+        for (x, ys) in rows {
+            for (series, y) in series.iter_mut().zip(ys) {
+                series.points.push(GraphPoint { x: x.clone(), y });
+            }
+        }
+
+        Ok(Graph { series })
+    }
+
+    async fn get_tags_from_post(&self, post_id: u64) -> Result<Vec<SearchTag>, DataSourceError> {
+        let rows = sqlx::query(
+            "SELECT t.*, g.name as g_name, g.color as color, g.created_at as g_created_at, (SELECT COUNT(*) FROM tags_posts tpc WHERE tpc.tag_id = t.tag_id) as 'linked_posts' FROM tags_posts tp JOIN tags t ON t.tag_id = tp.tag_id LEFT JOIN tag_group g ON t.group_id = g.group_id WHERE post_id = ?",
+        )
+            .bind(post_id as i64)
+            .map(|r| Self::map_search_tag(&r))
+            .fetch_all(&self.read_pool)
+            .await?;
+
+        Ok(rows.into_iter().filter_map(|x| Some(x.unwrap())).collect())
+    }
+
+    async fn get_tag_detail(&self, tag_id: u64) -> Result<Option<TagDetail>, DataSourceError> {
+        let mut get_tag_query = sqlx::query(
+            "SELECT t.*, g.name as g_name, g.color as color, g.created_at as g_created_at FROM tags tp JOIN tags t ON t.tag_id = tp.tag_id LEFT JOIN tag_group g ON t.group_id = g.group_id WHERE t.tag_id = ?",
+        )
+            .bind(tag_id as i64)
+            .fetch(&self.read_pool);
+
+        if let Some(row) = get_tag_query.try_next().await? {
+            let tag = Self::map_full_tag(&row)?;
+
+            drop(row);
+            drop(get_tag_query);
+
+            Ok(Some(TagDetail { tag }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn get_related_tags(
+        &self,
+        tag_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<RelatedTag>, DataSourceError> {
+        let co_occurrences = "SELECT tp2.tag_id, COUNT(*) AS co_count, COUNT(*) * 1.0 / (SELECT COUNT(*) FROM tags_posts WHERE tag_id = ?) AS confidence FROM tags_posts tp1
+            JOIN tags_posts tp2 ON tp2.post_id = tp1.post_id AND tp2.tag_id != tp1.tag_id
+            WHERE tp1.tag_id = ?
+            GROUP BY tp2.tag_id";
+
+        self.get_co_occurring_tags(co_occurrences, tag_id, 2, 1, page)
+            .await
+    }
+
+    async fn get_suggested_tags(
+        &self,
+        post_id: u64,
+        page: &PageParams,
+    ) -> Result<Page<RelatedTag>, DataSourceError> {
+        let (own_tag_count,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM tags_posts WHERE post_id = ?")
+                .bind(post_id as i64)
+                .fetch_one(&self.read_pool)
+                .await?;
+
+        // first count the co-occurrences per tag of the post, so they can be weighed by how
+        // often that tag is used
+        let co_occurrences = "SELECT b AS tag_id, SUM(n) AS co_count, SUM(n * 1.0 / (SELECT COUNT(*) FROM tags_posts WHERE tag_id = a)) AS confidence FROM (
+                SELECT own.tag_id AS a, tp2.tag_id AS b, COUNT(*) AS n FROM tags_posts own
+                JOIN tags_posts tp1 ON tp1.tag_id = own.tag_id AND tp1.post_id != own.post_id
+                JOIN tags_posts tp2 ON tp2.post_id = tp1.post_id
+                WHERE own.post_id = ? AND tp2.tag_id NOT IN (SELECT tag_id FROM tags_posts WHERE post_id = ?)
+                GROUP BY own.tag_id, tp2.tag_id
+            )
+            GROUP BY b";
+
+        self.get_co_occurring_tags(
+            co_occurrences,
+            post_id,
+            2,
+            own_tag_count.max(1) as u64,
+            page,
+        )
+        .await
+    }
+
+    async fn get_similar_posts(
+        &self,
+        post_id: u64,
+        require_playable: bool,
+        page: &PageParams,
+    ) -> Result<Page<SearchPost>, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
+
+        let post = sqlx::query("SELECT * FROM posts WHERE post_id = ?")
+            .bind(post_id as i64)
+            .map(|r| Self::map_post(&r))
+            .fetch_optional(conn.deref_mut())
+            .await?
+            .transpose()?
+            .ok_or(DataSourceError::NotFound)?;
+
+        let (post_count,): (i64,) =
+            sqlx::query_as("SELECT COUNT(*) FROM posts WHERE deleted_at IS NULL")
+                .fetch_one(conn.deref_mut())
+                .await?;
+
+        let tag_usage: Vec<(i64, i64)> = sqlx::query_as(
+            "SELECT tag_id, COUNT(*) FROM tags_posts WHERE tag_id IN (SELECT tag_id FROM tags_posts WHERE post_id = ?) GROUP BY tag_id",
+        )
+        .bind(post_id as i64)
+        .fetch_all(conn.deref_mut())
+        .await?;
+
+        // rarer tags say more about a post, weigh them by their inverse document frequency
+        let tag_weights: Vec<(i64, f64)> = tag_usage
+            .into_iter()
+            .map(|(tag_id, usage)| (tag_id, (post_count.max(usage) as f64 / usage as f64).ln()))
+            .collect();
+
+        let domain_patterns = post
+            .source
+            .as_ref()
+            .and_then(|url| url.host_str())
+            .map(|host| {
+                let host = host
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_");
+                (format!("%://{host}/%"), format!("%://{host}"))
+            });
+
+        let mut scores = vec!["SELECT post_id, ? AS score FROM posts WHERE import_batch_id = ?"];
+
+        if !tag_weights.is_empty() {
+            scores.push("SELECT tp.post_id, w.weight AS score FROM tags_posts tp JOIN weights w ON w.tag_id = tp.tag_id");
+        }
+
+        if domain_patterns.is_some() {
+            scores.push("SELECT post_id, ? AS score FROM posts WHERE source LIKE ? ESCAPE '\\' OR source LIKE ? ESCAPE '\\'");
+        }
+
+        // a values list cannot be empty, so use a row that matches no tag
+        let weights = if tag_weights.is_empty() {
+            String::from("(NULL, 0)")
+        } else {
+            vec!["(?, ?)"; tag_weights.len()].join(", ")
+        };
+
+        let similarity = format!(
+            "WITH weights(tag_id, weight) AS (VALUES {weights})
+            SELECT post_id, SUM(score) AS score FROM ({}) GROUP BY post_id",
+            scores.join(" UNION ALL ")
+        );
+
+        let filter = "WHERE p.post_id != ? AND p.deleted_at IS NULL
+            AND (? OR EXISTS(SELECT pi.post_id FROM post_items pi JOIN media c ON pi.content_id = c.media_id WHERE pi.post_id = p.post_id AND c.duration > 0))";
+
+        let count_query_str = format!(
+            "SELECT COUNT(*) FROM ({similarity}) s JOIN posts p ON p.post_id = s.post_id {filter}"
+        );
+        let query_str = format!(
+            "SELECT p.*, {SEARCH_POST_COLUMNS}, NULL as 'snippet' FROM ({similarity}) s
+            JOIN posts p ON p.post_id = s.post_id
+            {SEARCH_POST_JOINS}
+            {filter}
+            ORDER BY s.score DESC, p.created_at DESC
+            LIMIT ? OFFSET ?"
+        );
+
+        let total_row_count: i64 = Self::add_similarity_values(
+            &count_query_str,
+            &tag_weights,
+            post.import_batch.id(),
+            domain_patterns.as_ref(),
+            post_id,
+            require_playable,
+        )
+        .map(|r| r.get(0))
+        .fetch_one(conn.deref_mut())
+        .await?;
+
+        let rows = Self::add_similarity_values(
+            &query_str,
+            &tag_weights,
+            post.import_batch.id(),
+            domain_patterns.as_ref(),
+            post_id,
+            require_playable,
+        )
+        .bind(page.page_size() as i64)
+        .bind(page.offset() as i64)
+        .map(|r| Self::map_search_post(&r))
+        .fetch_all(conn.deref_mut())
+        .await?;
+
+        Ok(Page {
+            page_size: page.page_size(),
+            page_number: page.offset(),
+            total_row_count: total_row_count as usize,
+            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
+        })
+    }
+
+    async fn get_bucket_stats(&self) -> Result<BucketStats, DataSourceError> {
+        let mut conn = self.read_pool.acquire().await?;
+
+        let storage = sqlx::query(
+            "SELECT mime_type, mime_sub_type, COUNT(*) AS file_count, SUM(file_size) AS total_file_size FROM media
+            GROUP BY mime_type, mime_sub_type
+            ORDER BY total_file_size DESC",
+        )
+        .map(|r| Self::map_mime_type_stats(&r))
+        .fetch_all(conn.deref_mut())
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>, DataSourceError>>()?;
+
+        let (thumbnail_count, thumbnail_size): (i64, i64) = sqlx::query_as(
+            "SELECT COUNT(*), COALESCE(SUM(file_size), 0) FROM media
+            WHERE media_id IN (SELECT thumbnail_id FROM content)
+            AND media_id NOT IN (SELECT content_id FROM content)",
+        )
+        .fetch_one(conn.deref_mut())
+        .await?;
+
+        // every use of a media after the first one would have been a copy without deduplication
+        let (deduplication_savings,): (i64,) = sqlx::query_as(
+            "SELECT COALESCE(SUM(m.file_size * (u.uses - 1)), 0) FROM media m
+            JOIN (
+                SELECT media_id, COUNT(*) AS uses FROM (
+                    SELECT content_id AS media_id FROM post_items
+                    UNION ALL
+                    SELECT thumbnail_id AS media_id FROM content WHERE thumbnail_id != content_id
+                ) GROUP BY media_id
+            ) u ON u.media_id = m.media_id",
+        )
+        .fetch_one(conn.deref_mut())
+        .await?;
+
+        let largest_posts = sqlx::query(
+            "SELECT p.*, COUNT(*) AS item_count, SUM(m.file_size) AS total_file_size FROM posts p
+            JOIN post_items pi ON pi.post_id = p.post_id
+            JOIN media m ON m.media_id = pi.content_id
+            WHERE p.deleted_at IS NULL
+            GROUP BY p.post_id
+            ORDER BY total_file_size DESC
+            LIMIT 10",
+        )
+        .map(|r| Self::map_post_stats(&r))
+        .fetch_all(conn.deref_mut())
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>, DataSourceError>>()?;
+
+        let (post_count, trashed_post_count, untagged_post_count, posts_without_source): (
+            i64,
+            i64,
+            i64,
+            i64,
+        ) = sqlx::query_as(
+            "SELECT
+                COUNT(*) FILTER (WHERE deleted_at IS NULL),
+                COUNT(*) FILTER (WHERE deleted_at IS NOT NULL),
+                COUNT(*) FILTER (WHERE deleted_at IS NULL AND post_id NOT IN (SELECT post_id FROM tags_posts)),
+                COUNT(*) FILTER (WHERE deleted_at IS NULL AND source IS NULL)
+            FROM posts",
+        )
+        .fetch_one(conn.deref_mut())
+        .await?;
+
+        let (tag_count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tags")
+            .fetch_one(conn.deref_mut())
+            .await?;
+
+        let tag_groups = sqlx::query(
+            "SELECT g.*, COUNT(DISTINCT t.tag_id) AS tag_count, COUNT(DISTINCT tp.post_id) AS post_count FROM tags t
+            LEFT JOIN tag_group g ON g.group_id = t.group_id
+            LEFT JOIN tags_posts tp ON tp.tag_id = t.tag_id
+            GROUP BY t.group_id
+            ORDER BY tag_count DESC",
+        )
+        .map(|r| Self::map_tag_group_stats(&r))
+        .fetch_all(conn.deref_mut())
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>, DataSourceError>>()?;
+
+        let growth = sqlx::query(
+            "SELECT strftime('%Y-%m', p.created_at) AS month, COUNT(DISTINCT p.post_id) AS post_count, COUNT(pi.content_id) AS item_count, COALESCE(SUM(m.file_size), 0) AS total_file_size FROM posts p
+            LEFT JOIN post_items pi ON pi.post_id = p.post_id
+            LEFT JOIN media m ON m.media_id = pi.content_id
+            WHERE p.deleted_at IS NULL
+            GROUP BY month
+            ORDER BY month ASC",
+        )
+        .map(|r| Self::map_monthly_growth(&r))
+        .fetch_all(conn.deref_mut())
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>, DataSourceError>>()?;
+
+        Ok(BucketStats {
+            storage,
+            thumbnail_size: thumbnail_size as u64,
+            thumbnail_count: thumbnail_count as u64,
+            deduplication_savings: deduplication_savings as u64,
+            largest_posts,
+            post_count: post_count as u64,
+            trashed_post_count: trashed_post_count as u64,
+            untagged_post_count: untagged_post_count as u64,
+            posts_without_source: posts_without_source as u64,
+            tag_count: tag_count as u64,
+            tag_groups,
+            growth,
+        })
+    }
+
+    async fn get_settings(&self) -> Result<BucketSettings, DataSourceError> {
+        let (ocr, use_taken_at, strip_gps, renditions, hls_ladder, resize_cache_size): (
+            bool,
+            bool,
+            bool,
+            bool,
+            bool,
+            i64,
+        ) = sqlx::query_as(
+            "SELECT ocr, use_taken_at, strip_gps, renditions, hls_ladder, resize_cache_size FROM bucket_settings",
+        )
+        .fetch_one(&self.read_pool)
+        .await?;
+
+        Ok(BucketSettings {
+            ocr,
+            use_taken_at,
+            strip_gps,
+            renditions,
+            hls_ladder,
+            resize_cache_size: resize_cache_size as u64,
+        })
+    }
+
+    async fn update_settings(&self, settings: &BucketSettings) -> Result<(), DataSourceError> {
+        sqlx::query(
+            "UPDATE bucket_settings SET ocr = ?, use_taken_at = ?, strip_gps = ?, renditions = ?, hls_ladder = ?, resize_cache_size = ?",
+        )
+        .bind(settings.ocr)
+        .bind(settings.use_taken_at)
+        .bind(settings.strip_gps)
+        .bind(settings.renditions)
+        .bind(settings.hls_ladder)
+        .bind(settings.resize_cache_size as i64)
+        .execute(&self.write_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn gc(&self) -> Result<u64, DataSourceError> {
+        let mut conn = self.write_pool.acquire().await?;
+
+        let mut rows_affected = 0;
+
+        rows_affected += sqlx::query("pragma wal_checkpoint(truncate)")
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+
+        rows_affected += sqlx::query("pragma vacuum")
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+
+        rows_affected += sqlx::query("pragma optimize")
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mark_matches() {
+        assert_eq!(
+            "a <mark>cat</mark> and a <mark>dog</mark>",
+            SqliteIndex::mark_matches("a \u{2}cat\u{3} and a \u{2}dog\u{3}")
+        );
+    }
+
+    #[test]
+    fn mark_matches_escapes_html() {
+        assert_eq!(
+            "&lt;script&gt;<mark>x</mark>&amp;&quot;&#39;&lt;/script&gt;",
+            SqliteIndex::mark_matches("<script>\u{2}x\u{3}&\"'</script>")
+        );
+    }
+
+    /// An empty index in a temporary directory that is removed when it is dropped.
+    #[cfg(feature = "encryption")]
+    async fn test_index() -> (tempfile::TempDir, SqliteIndex) {
+        let dir = tempfile::tempdir().unwrap();
+        let index = SqliteIndex::create_encrypted(
+            &dir.path().join("index.db"),
+            crate::local::secret::Secret::random(),
+        )
+        .await
+        .unwrap();
+
+        (dir, index)
+    }
+
+    #[cfg(feature = "encryption")]
+    async fn add_media(index: &SqliteIndex, exif: ExifMetadata) -> Media {
+        let hash = Uuid::new_v4().to_string();
+        let mut media = Media {
+            id: 0,
+            file_id: Uuid::new_v4(),
+            file_size: 3,
+            sha1: hash.clone(),
+            sha256: hash,
+            md5: String::from("md5"),
+            metadata: MediaMetadata::Image {
+                dims: Dimensions {
+                    width: 10,
+                    height: 10,
+                },
+                exif,
+                animation: None,
+            },
+            mime: "image/png".parse().unwrap(),
+        };
+        MediaDataSource::add(index, &mut media).await.unwrap();
 
-            query.execute(tx.deref_mut()).await?;
-        }
+        media
+    }
 
-        if !data.tag_ids.is_empty() {
-            let insert_str = "INSERT INTO tags_posts(tag_id, post_id) VALUES \n";
-            let row_value_str = "(?, ?),\n";
+    /// Add an image and return the id of its content.
+    #[cfg(feature = "encryption")]
+    async fn add_content(index: &SqliteIndex) -> u64 {
+        add_content_with_exif(index, Default::default()).await
+    }
 
-            let mut insert_items_query = String::with_capacity(
-                insert_str.len() + row_value_str.len() * (data.tag_ids.len() * posts.len()),
-            );
+    #[cfg(feature = "encryption")]
+    async fn add_content_with_exif(index: &SqliteIndex, exif: ExifMetadata) -> u64 {
+        let media = add_media(index, exif).await;
 
-            insert_items_query.push_str(insert_str);
+        let mut content = Content {
+            content: ManyToOne::Id(media.id),
+            thumbnail: ManyToOne::Id(media.id),
+            rendition: None,
+        };
+        ContentDataSource::add(index, &mut content).await.unwrap();
 
-            for _ in 0..posts.len() * data.tag_ids.len() {
-                insert_items_query.push_str(row_value_str);
-            }
+        media.id
+    }
 
-            // Remove the last newline and comma
-            insert_items_query.pop();
-            insert_items_query.pop();
+    #[cfg(feature = "encryption")]
+    async fn add_post(index: &SqliteIndex, content_ids: &[u64]) -> u64 {
+        let (_, posts) = index
+            .add_full_post(CreateFullPost {
+                title: Some(String::from("post")),
+                description: None,
+                source: None,
+                created_at: None,
+                items: content_ids
+                    .iter()
+                    .map(|content_id| CreateFullPostItem {
+                        content_id: *content_id,
+                        metadata: Default::default(),
+                    })
+                    .collect(),
+                tag_ids: vec![],
+                flatten: false,
+                batch_id: None,
+                batch_name: None,
+                batch_origin: ImportBatchOrigin::Web,
+                rating: None,
+                favourite: false,
+            })
+            .await
+            .unwrap();
 
-            let mut query = sqlx::query(insert_items_query.as_str());
+        posts[0].id
+    }
 
-            for post in posts.iter() {
-                for tag_id in data.tag_ids.iter() {
-                    query = query.bind(*tag_id as i64);
-                    query = query.bind(post.id as i64);
-                }
-            }
+    /// The content ids of the items of a post in order.
+    #[cfg(feature = "encryption")]
+    async fn item_ids(index: &SqliteIndex, post_id: u64) -> Vec<u64> {
+        let mut conn = index.read_pool.acquire().await.unwrap();
+
+        SqliteIndex::post_snapshot(&mut conn, post_id)
+            .await
+            .unwrap()
+            .map(|snapshot| snapshot.item_ids)
+            .unwrap_or_default()
+    }
 
-            query.execute(tx.deref_mut()).await?;
-        }
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn insert_post_item_shifts_following_items() {
+        let (_dir, index) = test_index().await;
+        let (a, b, c) = (
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+        );
+        let post_id = add_post(&index, &[a, b]).await;
 
-        tx.commit().await?;
+        let mut item = PostItem {
+            post: ManyToOne::Id(post_id),
+            position: 1,
+            content: ManyToOne::Id(c),
+            upload: Default::default(),
+        };
+        PostItemDataSource::insert(&index, &mut item, &ChangeOrigin::default())
+            .await
+            .unwrap();
 
-        Ok((batch, posts))
+        assert_eq!(vec![a, c, b], item_ids(&index, post_id).await);
+
+        // a position past the end appends the item
+        item.position = 10;
+        PostItemDataSource::insert(&index, &mut item, &ChangeOrigin::default())
+            .await
+            .unwrap();
+
+        assert_eq!(3, item.position);
+        assert_eq!(vec![a, c, b, c], item_ids(&index, post_id).await);
     }
 
-    async fn search_tags(
-        &self,
-        page: &PageParams,
-        query: &str,
-        exact: bool,
-    ) -> Result<Page<SearchTag>, DataSourceError> {
-        let mut conn = self.read_pool.acquire().await?;
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn remove_post_item_shifts_following_items() {
+        let (_dir, index) = test_index().await;
+        let (a, b, c) = (
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+        );
+        let post_id = add_post(&index, &[a, b, c]).await;
 
-        let total_row_count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM tags")
-            .fetch_one(conn.deref_mut())
-            .await?;
+        PostItemDataSource::remove(&index, post_id, 0, &ChangeOrigin::default())
+            .await
+            .unwrap();
+        assert_eq!(vec![b, c], item_ids(&index, post_id).await);
 
-        let query_is_empty = query.len() < 3;
+        PostItemDataSource::remove(&index, post_id, 1, &ChangeOrigin::default())
+            .await
+            .unwrap();
+        assert_eq!(vec![b], item_ids(&index, post_id).await);
+
+        assert!(matches!(
+            PostItemDataSource::remove(&index, post_id, 0, &ChangeOrigin::default()).await,
+            Err(DataSourceError::EmptyPost)
+        ));
+        assert!(matches!(
+            PostItemDataSource::remove(&index, post_id, 5, &ChangeOrigin::default()).await,
+            Err(DataSourceError::NotFound)
+        ));
+    }
 
-        let where_clause = if query.is_empty() {
-            ""
-        } else {
-            if exact {
-                " WHERE t.name = ?"
-            } else {
-                if query_is_empty {
-                    " WHERE t.name LIKE ?"
-                } else {
-                    "WHERE tags_vtab MATCH (?)"
-                }
-            }
-        };
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_reorder_post_items() {
+        let (_dir, index) = test_index().await;
+        let ids = [
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+        ];
+        let post_id = add_post(&index, &ids).await;
+        let [a, b, c, d] = ids;
+
+        PostItemDataSource::reorder(&index, post_id, 0, 2, &ChangeOrigin::default())
+            .await
+            .unwrap();
+        assert_eq!(vec![b, c, a, d], item_ids(&index, post_id).await);
 
-        let query_str =
-            format!("SELECT t.*, g.name as g_name, g.color as color, g.created_at as g_created_at, (SELECT COUNT(*) FROM tags_posts tpc WHERE tpc.tag_id = t.tag_id) as 'linked_posts' FROM tags_vtab t LEFT JOIN tag_group g ON t.group_id = g.group_id {where_clause} ORDER BY rank, t.created_at DESC LIMIT ? OFFSET ?");
+        PostItemDataSource::reorder(&index, post_id, 3, 0, &ChangeOrigin::default())
+            .await
+            .unwrap();
+        assert_eq!(vec![d, b, c, a], item_ids(&index, post_id).await);
 
-        let mut sql_query = sqlx::query(query_str.as_str());
+        assert!(matches!(
+            PostItemDataSource::reorder(&index, post_id, 0, 4, &ChangeOrigin::default()).await,
+            Err(DataSourceError::NotFound)
+        ));
+    }
 
-        if !query.is_empty() {
-            if exact {
-                sql_query = sql_query.bind(query);
-            } else {
-                if !query_is_empty {
-                    let value = query
-                        .trim()
-                        .split(' ')
-                        .map(|word| format!("{{name}}: \"{word}\""))
-                        .collect::<Vec<String>>()
-                        .join(" OR ");
+    #[cfg(feature = "encryption")]
+    async fn add_tag(index: &SqliteIndex, name: &str, post_ids: &[u64]) -> u64 {
+        let mut tag = Tag {
+            id: 0,
+            name: String::from(name),
+            group: None,
+            created_at: Utc::now(),
+        };
+        TagDataSource::add(index, &mut tag).await.unwrap();
 
-                    sql_query = sql_query.bind(value);
-                } else {
-                    sql_query = sql_query.bind(format!("%{query}%"))
-                }
-            }
+        for post_id in post_ids {
+            index.add_tag_to_post(tag.id, *post_id).await.unwrap();
         }
 
-        let rows = sql_query
-            .bind(page.page_size() as i64)
-            .bind(page.offset() as i64)
-            .map(|r| Self::map_search_tag(&r))
-            .fetch_all(conn.deref_mut())
-            .await?;
+        tag.id
+    }
 
-        Ok(Page {
-            page_size: page.page_size(),
-            page_number: page.offset(),
-            total_row_count: total_row_count.0 as usize,
-            data: rows.into_iter().filter_map(|x| x.ok()).collect(),
-        })
+    #[cfg(feature = "encryption")]
+    async fn tag_ids(index: &SqliteIndex, post_id: u64) -> Vec<u64> {
+        let mut conn = index.read_pool.acquire().await.unwrap();
+
+        SqliteIndex::post_snapshot(&mut conn, post_id)
+            .await
+            .unwrap()
+            .map(|snapshot| snapshot.tag_ids)
+            .unwrap_or_default()
     }
 
-    async fn update_full_post(&self, value: &Post, tags: &[u64]) -> Result<(), DataSourceError> {
-        let mut tx = self.write_pool.begin().await?;
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_merge_posts() {
+        let (_dir, index) = test_index().await;
+        let (a, b, c, d) = (
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+        );
+        let first = add_post(&index, &[a, b]).await;
+        let second = add_post(&index, &[c]).await;
+        let third = add_post(&index, &[d]).await;
+        let shared = add_tag(&index, "shared", &[first, second]).await;
+        let other = add_tag(&index, "other", &[third]).await;
+
+        let post = index
+            .merge_posts(&[first, third, second, third], &ChangeOrigin::default())
+            .await
+            .unwrap();
 
-        self.post_update(value, tx.deref_mut()).await?;
+        assert_eq!(first, post.id);
+        assert_eq!(vec![a, b, d, c], item_ids(&index, first).await);
+        assert_eq!(vec![shared, other], tag_ids(&index, first).await);
+        assert!(PostDataSource::get_by_id(&index, second)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(PostDataSource::get_by_id(&index, third)
+            .await
+            .unwrap()
+            .is_none());
+    }
 
-        sqlx::query("DELETE FROM tags_posts WHERE post_id = ?")
-            .bind(value.id as i64)
-            .execute(tx.deref_mut())
-            .await?;
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn merge_posts_missing_post() {
+        let (_dir, index) = test_index().await;
+        let a = add_content(&index).await;
+        let post_id = add_post(&index, &[a]).await;
+
+        assert!(matches!(
+            index
+                .merge_posts(&[post_id, post_id + 1], &ChangeOrigin::default())
+                .await,
+            Err(DataSourceError::NotFound)
+        ));
+        assert!(matches!(
+            index.merge_posts(&[], &ChangeOrigin::default()).await,
+            Err(DataSourceError::NotFound)
+        ));
+        assert_eq!(vec![a], item_ids(&index, post_id).await);
+    }
 
-        if !tags.is_empty() {
-            let value_binds = tags.iter().map(|_| "(?, ?)").collect::<Vec<_>>().join(", ");
-            let insert_query =
-                format!("INSERT INTO tags_posts(post_id, tag_id) VALUES {value_binds}");
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn merge_posts_into_missing_target() {
+        let (_dir, index) = test_index().await;
+        let a = add_content(&index).await;
+        let post_id = add_post(&index, &[a]).await;
+
+        assert!(matches!(
+            index
+                .merge_posts(&[post_id + 1, post_id], &ChangeOrigin::default())
+                .await,
+            Err(DataSourceError::NotFound)
+        ));
+        assert!(PostDataSource::get_by_id(&index, post_id)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(vec![a], item_ids(&index, post_id).await);
+    }
 
-            let mut query = sqlx::query(&insert_query);
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn merge_post_into_itself() {
+        let (_dir, index) = test_index().await;
+        let (a, b) = (add_content(&index).await, add_content(&index).await);
+        let post_id = add_post(&index, &[a, b]).await;
+
+        let post = index
+            .merge_posts(&[post_id, post_id], &ChangeOrigin::default())
+            .await
+            .unwrap();
 
-            for tag in tags {
-                query = query.bind(value.id as i64).bind(*tag as i64);
-            }
+        // the items are neither duplicated nor deleted with the post
+        assert_eq!(post_id, post.id);
+        assert_eq!(vec![a, b], item_ids(&index, post_id).await);
+    }
 
-            query.execute(tx.deref_mut()).await?;
-        }
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_split_post() {
+        let (_dir, index) = test_index().await;
+        let ids = [
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+        ];
+        let post_id = add_post(&index, &ids).await;
+        let tag_id = add_tag(&index, "tag", &[post_id]).await;
+        let [a, b, c, d] = ids;
+
+        let post = index
+            .split_post(post_id, &[3, 1, 3], &ChangeOrigin::default())
+            .await
+            .unwrap();
 
-        tx.commit().await?;
-        Ok(())
+        assert_eq!(vec![a, c], item_ids(&index, post_id).await);
+        assert_eq!(vec![b, d], item_ids(&index, post.id).await);
+        assert_eq!(Some(String::from("post")), post.title);
+        assert_eq!(vec![tag_id], tag_ids(&index, post.id).await);
     }
 
-    async fn cascade_delete_post(&self, id: u64) -> Result<(), DataSourceError> {
-        let mut tx = self.write_pool.begin().await?;
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn split_post_invalid_positions() {
+        let (_dir, index) = test_index().await;
+        let (a, b) = (add_content(&index).await, add_content(&index).await);
+        let post_id = add_post(&index, &[a, b]).await;
+
+        assert!(matches!(
+            index
+                .split_post(post_id, &[2], &ChangeOrigin::default())
+                .await,
+            Err(DataSourceError::NotFound)
+        ));
+        assert!(matches!(
+            index
+                .split_post(post_id, &[0, 1], &ChangeOrigin::default())
+                .await,
+            Err(DataSourceError::EmptyPost)
+        ));
+        assert!(matches!(
+            index
+                .split_post(post_id, &[], &ChangeOrigin::default())
+                .await,
+            Err(DataSourceError::EmptyPost)
+        ));
+        assert_eq!(vec![a, b], item_ids(&index, post_id).await);
+    }
 
-        sqlx::query("DELETE FROM post_items WHERE post_id = ?")
-            .bind(id as i64)
-            .execute(tx.deref_mut())
-            .await?;
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn split_out_every_item() {
+        let (_dir, index) = test_index().await;
+        let ids = [
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+        ];
+        let post_id = add_post(&index, &ids).await;
+
+        assert!(matches!(
+            index
+                .split_post(post_id, &[2, 0, 1, 0], &ChangeOrigin::default())
+                .await,
+            Err(DataSourceError::EmptyPost)
+        ));
+        assert_eq!(ids.to_vec(), item_ids(&index, post_id).await);
+        assert!(PostDataSource::get_by_id(&index, post_id + 1)
+            .await
+            .unwrap()
+            .is_none());
+    }
 
-        sqlx::query("DELETE FROM tags_posts WHERE post_id = ?")
-            .bind(id as i64)
-            .execute(tx.deref_mut())
-            .await?;
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn test_purge_trash() {
+        let (_dir, index) = test_index().await;
+        let (a, b, c) = (
+            add_content(&index).await,
+            add_content(&index).await,
+            add_content(&index).await,
+        );
+        let first = add_post(&index, &[a, b]).await;
+        let second = add_post(&index, &[b]).await;
+        let third = add_post(&index, &[c]).await;
+        let before_trash = Utc::now() - chrono::Duration::seconds(1);
+
+        // the rendition and storyboard of a purged content are removed with it
+        let rendition = add_media(&index, Default::default()).await;
+        let sprite = add_media(&index, Default::default()).await;
+        let mut content = index.get_by_content_id(c).await.unwrap().unwrap();
+        index
+            .update_rendition_id(Some(rendition.id), &mut content)
+            .await
+            .unwrap();
+        index
+            .set_storyboard(
+                c,
+                &Storyboard {
+                    sprite: ManyToOne::Id(sprite.id),
+                    interval: 10,
+                    tile: Dimensions {
+                        width: 160,
+                        height: 90,
+                    },
+                    columns: 10,
+                    frame_count: 1,
+                },
+            )
+            .await
+            .unwrap();
 
-        sqlx::query("DELETE FROM posts WHERE post_id = ?")
-            .bind(id as i64)
-            .execute(tx.deref_mut())
-            .await?;
+        index
+            .trash_post(first, &ChangeOrigin::default())
+            .await
+            .unwrap();
+        index
+            .trash_post(third, &ChangeOrigin::default())
+            .await
+            .unwrap();
 
-        tx.commit().await?;
+        assert!(index.purge_trash(before_trash).await.unwrap().is_empty());
 
-        Ok(())
+        let mut removed: Vec<u64> = index
+            .purge_trash(Utc::now() + chrono::Duration::seconds(1))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|media| media.id)
+            .collect();
+        removed.sort();
+
+        // content that is still used by another post is kept
+        assert_eq!(vec![a, c, rendition.id, sprite.id], removed);
+        assert!(index.get_by_content_id(c).await.unwrap().is_none());
+        assert!(index.get_storyboard(c).await.unwrap().is_none());
+        assert!(PostDataSource::get_by_id(&index, first)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(MediaDataSource::get_by_id(&index, b)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(vec![b], item_ids(&index, second).await);
     }
 
-    async fn graph_post(&self, query: &PostGraphQuery) -> Result<Graph, DataSourceError> {
-        let mut conn = self.read_pool.acquire().await?;
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn purge_post_outside_trash() {
+        let (_dir, index) = test_index().await;
+        let a = add_content(&index).await;
+        let post_id = add_post(&index, &[a]).await;
+
+        assert!(matches!(
+            index.purge_post(post_id, &ChangeOrigin::default()).await,
+            Err(DataSourceError::NotFound)
+        ));
+
+        index
+            .trash_post(post_id, &ChangeOrigin::default())
+            .await
+            .unwrap();
+        let removed = index
+            .purge_post(post_id, &ChangeOrigin::default())
+            .await
+            .unwrap();
 
-        let select = match query.select {
-            GraphSelect::Count => "SUM(COUNT(*) * 1.0) over (ROWS UNBOUNDED PRECEDING)",
-        };
+        assert_eq!(
+            vec![a],
+            removed
+                .into_iter()
+                .map(|media| media.id)
+                .collect::<Vec<_>>()
+        );
+    }
 
-        let y_axis = match query.discriminator {
-            GraphDiscriminator::Duration(_) => "p.created_at",
-            GraphDiscriminator::None => "null",
+    /// The ids of the posts whose date field `released` matches, newest first.
+    #[cfg(feature = "encryption")]
+    async fn search_released(
+        index: &SqliteIndex,
+        operator: CustomFieldOperator,
+        start: &str,
+        end: &str,
+    ) -> Vec<u64> {
+        let parse = |time: &str| DateTime::parse_from_rfc3339(time).unwrap().to_utc();
+        let query = PostSearchQuery {
+            fields: vec![CustomFieldPredicate {
+                name: String::from("released"),
+                operator,
+                value: Some(String::from(start)),
+                date_start: Some(parse(start)),
+                date_end: Some(parse(end)),
+            }],
+            ..Default::default()
         };
 
-        let y_axis_kind = match query.discriminator {
-            GraphDiscriminator::Duration(_) => "date",
-            GraphDiscriminator::None => "none",
-        };
+        index
+            .search_posts(&query, &PageParams::new(10, 0))
+            .await
+            .unwrap()
+            .data
+            .into_iter()
+            .map(|post| post.post.id)
+            .collect()
+    }
 
-        let before_where = format!("SELECT {select}, {y_axis}, '{y_axis_kind}' FROM posts_vtab p");
-        let after_where = match query.discriminator {
-            GraphDiscriminator::Duration(_) => "GROUP BY strftime('%s', p.created_at) / ?",
-            GraphDiscriminator::None => "",
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn search_date_field() {
+        let (_dir, index) = test_index().await;
+        let first = add_post(&index, &[add_content(&index).await]).await;
+        let second = add_post(&index, &[add_content(&index).await]).await;
+        let mut field = CustomField {
+            id: 0,
+            name: String::from("released"),
+            field_type: CustomFieldType::Date,
+            created_at: Utc::now(),
         };
+        CustomFieldDataSource::add(&index, &mut field)
+            .await
+            .unwrap();
 
-        let query_str =
-            SqliteIndex::create_search_query_str(&query.filter, &before_where, &after_where);
-        let mut graph_query = SqliteIndex::add_search_query_values(&query.filter, &query_str);
-
-        match query.discriminator {
-            GraphDiscriminator::Duration(duration) => {
-                graph_query = graph_query.bind(duration.as_secs() as i64);
-            }
-            _ => {}
+        // stored with different offsets, the first is still on the first of January in UTC
+        for (post_id, time) in [
+            (first, "2020-01-01T23:30:00+02:00"),
+            (second, "2020-01-02T01:00:00-03:00"),
+        ] {
+            let value =
+                CustomFieldValue::Date(DateTime::parse_from_rfc3339(time).unwrap().to_utc());
+            index
+                .set_value(post_id, field.id, Some(&value), &ChangeOrigin::default())
+                .await
+                .unwrap();
         }
 
-        let rows = graph_query
-            .map(|r| Self::map_graph_point(&r))
-            .fetch_all(conn.deref_mut())
-            .await?
-            .into_iter()
-            .collect::<Result<Vec<_>, DataSourceError>>()?;
-        Ok(Graph { points: rows })
-    }
+        let day = ("2020-01-01T00:00:00Z", "2020-01-01T23:59:59.999Z");
+        let search = |operator| search_released(&index, operator, day.0, day.1);
 
-    async fn get_tags_from_post(&self, post_id: u64) -> Result<Vec<SearchTag>, DataSourceError> {
-        let rows = sqlx::query(
-            "SELECT t.*, g.name as g_name, g.color as color, g.created_at as g_created_at, (SELECT COUNT(*) FROM tags_posts tpc WHERE tpc.tag_id = t.tag_id) as 'linked_posts' FROM tags_posts tp JOIN tags t ON t.tag_id = tp.tag_id LEFT JOIN tag_group g ON t.group_id = g.group_id WHERE post_id = ?",
-        )
-            .bind(post_id as i64)
-            .map(|r| Self::map_search_tag(&r))
-            .fetch_all(&self.read_pool)
-            .await?;
+        assert_eq!(vec![first], search(CustomFieldOperator::Eq).await);
+        assert_eq!(vec![second], search(CustomFieldOperator::Ne).await);
+        assert_eq!(vec![second], search(CustomFieldOperator::Gt).await);
+        assert_eq!(vec![first], search(CustomFieldOperator::Le).await);
+        assert!(search(CustomFieldOperator::Lt).await.is_empty());
+        assert_eq!(vec![second, first], search(CustomFieldOperator::Ge).await);
 
-        Ok(rows.into_iter().filter_map(|x| Some(x.unwrap())).collect())
+        let time = "2020-01-02T04:00:00Z";
+        assert_eq!(
+            vec![second],
+            search_released(&index, CustomFieldOperator::Eq, time, time).await
+        );
     }
 
-    async fn get_tag_detail(&self, tag_id: u64) -> Result<Option<TagDetail>, DataSourceError> {
-        let mut get_tag_query = sqlx::query(
-            "SELECT t.*, g.name as g_name, g.color as color, g.created_at as g_created_at FROM tags tp JOIN tags t ON t.tag_id = tp.tag_id LEFT JOIN tag_group g ON t.group_id = g.group_id WHERE t.tag_id = ?",
-        )
-            .bind(tag_id as i64)
-            .fetch(&self.read_pool);
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn set_custom_field_value_is_logged() {
+        let (_dir, index) = test_index().await;
+        let post_id = add_post(&index, &[add_content(&index).await]).await;
+        let mut field = CustomField {
+            id: 0,
+            name: String::from("episode"),
+            field_type: CustomFieldType::Number,
+            created_at: Utc::now(),
+        };
+        CustomFieldDataSource::add(&index, &mut field)
+            .await
+            .unwrap();
+        let changes = || async {
+            ChangeDataSource::get_page_from_post(&index, post_id, &PageParams::new(10, 0))
+                .await
+                .unwrap()
+                .data
+        };
+        let logged = changes().await.len();
+        let value = CustomFieldValue::Number(3.0);
+
+        // setting the same value twice only records the first change
+        for _ in 0..2 {
+            index
+                .set_value(post_id, field.id, Some(&value), &ChangeOrigin::default())
+                .await
+                .unwrap();
+        }
+        index
+            .set_value(post_id, field.id, None, &ChangeOrigin::default())
+            .await
+            .unwrap();
 
-        if let Some(row) = get_tag_query.try_next().await? {
-            let tag = Self::map_full_tag(&row)?;
+        let changes = changes().await;
+        assert_eq!(logged + 2, changes.len());
 
-            drop(row);
-            drop(get_tag_query);
+        let Some(ChangeSnapshot::Post(previous)) = &changes[0].previous else {
+            panic!("expected a post snapshot");
+        };
+        let Some(ChangeSnapshot::Post(current)) = &changes[0].current else {
+            panic!("expected a post snapshot");
+        };
 
-            Ok(Some(TagDetail { tag }))
-        } else {
-            Ok(None)
-        }
+        assert_eq!(ChangeAction::Update, changes[0].action);
+        assert_eq!(Some(&value), previous.custom_fields.get(&field.id));
+        assert!(current.custom_fields.is_empty());
     }
 
-    async fn gc(&self) -> Result<u64, DataSourceError> {
-        let mut conn = self.write_pool.acquire().await?;
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn search_snippet_only_for_text_matches() {
+        let (_dir, index) = test_index().await;
+        let first = add_post(&index, &[add_content(&index).await]).await;
+        let second = add_post(&index, &[add_content(&index).await]).await;
+        add_tag(&index, "shared", &[first, second]).await;
+
+        let query = PostSearchQuery {
+            text: Some(String::from("post")),
+            ..Default::default()
+        };
+        let found = index
+            .search_posts(&query, &PageParams::new(10, 0))
+            .await
+            .unwrap();
 
-        let mut rows_affected = 0;
+        assert_eq!(2, found.data.len());
+        assert!(found.data.iter().all(|post| post
+            .snippet
+            .as_deref()
+            .is_some_and(|snippet| snippet.contains("<mark>post</mark>"))));
 
-        rows_affected += sqlx::query("pragma wal_checkpoint(truncate)")
-            .execute(&mut *conn)
-            .await?
-            .rows_affected();
+        let similar = index
+            .get_similar_posts(first, false, &PageParams::new(10, 0))
+            .await
+            .unwrap();
 
-        rows_affected += sqlx::query("pragma vacuum")
-            .execute(&mut *conn)
-            .await?
-            .rows_affected();
+        assert_eq!(
+            vec![second],
+            similar
+                .data
+                .iter()
+                .map(|post| post.post.id)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(None, similar.data[0].snippet);
+    }
 
-        rows_affected += sqlx::query("pragma optimize")
-            .execute(&mut *conn)
-            .await?
-            .rows_affected();
+    #[cfg(feature = "encryption")]
+    #[tokio::test]
+    async fn search_near_antimeridian() {
+        let (_dir, index) = test_index().await;
+        let mut posts = Vec::new();
+
+        for longitude in [179.99, -179.99, 0.0] {
+            let exif = ExifMetadata {
+                latitude: Some(0.0),
+                longitude: Some(longitude),
+                ..Default::default()
+            };
+            let content_id = add_content_with_exif(&index, exif).await;
+            posts.push(add_post(&index, &[content_id]).await);
+        }
 
-        Ok(rows_affected)
+        for longitude in [179.999, -179.999] {
+            let query = PostSearchQuery {
+                near: Some(GeoRadius {
+                    latitude: 0.0,
+                    longitude,
+                    radius: 5000.0,
+                }),
+                ..Default::default()
+            };
+            let mut found: Vec<u64> = index
+                .search_posts(&query, &PageParams::new(10, 0))
+                .await
+                .unwrap()
+                .data
+                .into_iter()
+                .map(|post| post.post.id)
+                .collect();
+            found.sort();
+
+            assert_eq!(posts[..2], found);
+        }
     }
 }
@@ -1,15 +1,362 @@
 use std::env;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Arc;
 
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
 use mediatype::{MediaType, MediaTypeBuf};
-use tokio::fs::OpenOptions;
-use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, SeekFrom};
 use tokio::process::{Child, Command};
 use uuid::Uuid;
 
 use crate::data_source::MediaImportError;
-use crate::model::{Dimensions, Media, MediaMetadata};
+use crate::model::{
+    Animation, Dimensions, ExifMetadata, ManyToOne, Media, MediaMetadata, MediaTextKind, ResizeFit,
+    ResizeFormat, ResizeParams, Storyboard,
+};
+
+/// The maximum number of bytes of extracted text that is kept per media file.
+const MAX_EXTRACTED_TEXT_LEN: usize = 1024 * 1024;
+
+/// Reads the metadata of, and renders thumbnails for, the file types it supports.
+#[async_trait]
+pub trait MediaImporter: Send + Sync {
+    /// Whether files of this type can be imported.
+    fn supports(&self, mime: &MediaType<'_>) -> bool;
+
+    async fn metadata(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+    ) -> Result<MediaMetadata, MediaImportError>;
+
+    /// Render a jpeg thumbnail of at most 300x300 pixels to `output`.
+    /// Importers that cannot render thumbnails reject the file, which fails its import.
+    async fn thumbnail(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+        output: &Path,
+    ) -> Result<(), MediaImportError> {
+        Err(MediaImportError::UnsupportedMimeType)
+    }
+}
+
+/// The importers that are consulted when media is imported.
+///
+/// The default registry contains the built-in importers,
+/// an importer takes precedence over the importers registered before it.
+#[derive(Clone)]
+pub struct MediaImporterRegistry {
+    importers: Vec<Arc<dyn MediaImporter>>,
+}
+
+impl MediaImporterRegistry {
+    /// A registry without any importers, not even the built-in ones.
+    pub fn empty() -> Self {
+        Self {
+            importers: Vec::new(),
+        }
+    }
+
+    pub fn register(&mut self, importer: impl MediaImporter + 'static) {
+        self.importers.push(Arc::new(importer));
+    }
+
+    /// The most recently registered importer that supports this type.
+    pub fn find(&self, mime: &MediaType<'_>) -> Option<&dyn MediaImporter> {
+        self.importers
+            .iter()
+            .rev()
+            .find(|importer| importer.supports(mime))
+            .map(|importer| importer.as_ref())
+    }
+}
+
+impl Default for MediaImporterRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+
+        registry.register(ImageMagickImporter);
+        registry.register(FfmpegImporter);
+        registry.register(AudioImporter);
+        registry.register(PdfImporter);
+        registry.register(OfficeDocumentImporter);
+
+        registry
+    }
+}
+
+/// Imports images with ImageMagick, the EXIF and XMP metadata is read with exiftool if it is installed.
+pub struct ImageMagickImporter;
+
+#[async_trait]
+impl MediaImporter for ImageMagickImporter {
+    fn supports(&self, mime: &MediaType<'_>) -> bool {
+        mime.ty == "image"
+    }
+
+    async fn metadata(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+    ) -> Result<MediaMetadata, MediaImportError> {
+        Ok(MediaMetadata::Image {
+            dims: get_image_dimensions(mime, path).await?,
+            exif: read_exif(path).await?,
+            animation: get_image_animation(mime, path).await?,
+        })
+    }
+
+    async fn thumbnail(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+        output: &Path,
+    ) -> Result<(), MediaImportError> {
+        render_image_thumbnail(&format!("{}:{}[0]", mime.subty, path.display()), output).await
+    }
+}
+
+/// Imports videos with ffprobe and ffmpeg.
+pub struct FfmpegImporter;
+
+#[async_trait]
+impl MediaImporter for FfmpegImporter {
+    fn supports(&self, mime: &MediaType<'_>) -> bool {
+        mime.ty == "video"
+    }
+
+    async fn metadata(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+    ) -> Result<MediaMetadata, MediaImportError> {
+        let duration = get_video_duration(path).await?;
+
+        let dism_probe = Command::new("ffprobe")
+            .arg("-v")
+            .arg("error")
+            .arg("-f")
+            .arg(map_ffmpeg_mime(mime))
+            .arg("-select_streams")
+            .arg("v:0")
+            .arg("-show_entries")
+            .arg("stream=width,height")
+            .arg("-of")
+            .arg("csv=s=x:p=0")
+            .arg("-loglevel")
+            .arg("quiet")
+            .arg(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let dims_output = dism_probe.wait_with_output().await?;
+
+        let dims = parse_dimensions(dims_output.stdout)?;
+        Ok(MediaMetadata::Video {
+            dims,
+            duration: duration as i32,
+            video_encoding: get_video_codec(path).await?,
+        })
+    }
+
+    async fn thumbnail(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+        output: &Path,
+    ) -> Result<(), MediaImportError> {
+        let duration = get_video_duration(path).await?;
+
+        let mut ffmpeg_command = Command::new("ffmpeg")
+            .arg("-ss")
+            .arg((duration / 2.0).to_string())
+            .arg("-f")
+            .arg(map_ffmpeg_mime(mime))
+            .arg("-i")
+            .arg(path.to_str().unwrap())
+            .arg("-vframes")
+            .arg("1")
+            .arg("-c:v")
+            .arg("mjpeg")
+            .arg("-f")
+            .arg("mjpeg")
+            .arg(output)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        ffmpeg_command.wait().await?;
+
+        Ok(())
+    }
+}
+
+/// Imports audio files with ffprobe, the thumbnail is the embedded cover art
+/// or a waveform if the file has none.
+pub struct AudioImporter;
+
+#[async_trait]
+impl MediaImporter for AudioImporter {
+    fn supports(&self, mime: &MediaType<'_>) -> bool {
+        mime.ty == "audio"
+    }
+
+    async fn metadata(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+    ) -> Result<MediaMetadata, MediaImportError> {
+        // ID3 tags are stored on the format and Vorbis comments on the stream
+        let probe = Command::new("ffprobe")
+            .arg("-v")
+            .arg("error")
+            .arg("-select_streams")
+            .arg("a:0")
+            .arg("-show_entries")
+            .arg("format=duration,bit_rate:format_tags:stream=sample_rate:stream_tags")
+            .arg("-of")
+            .arg("default=nw=1")
+            .arg(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let output = probe.wait_with_output().await?;
+        let output_str =
+            String::from_utf8(output.stdout).map_err(|_| MediaImportError::UnexpectedOutput)?;
+
+        parse_audio_probe(output_str.as_str())
+    }
+
+    async fn thumbnail(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+        output: &Path,
+    ) -> Result<(), MediaImportError> {
+        let has_cover_art = !get_video_codec(path).await?.is_empty();
+
+        let mut command = Command::new("ffmpeg");
+        command.arg("-i").arg(path);
+
+        if has_cover_art {
+            command
+                .arg("-an")
+                .arg("-vf")
+                .arg("scale=300:300:force_original_aspect_ratio=decrease");
+        } else {
+            command
+                .arg("-filter_complex")
+                .arg("showwavespic=s=300x150:colors=#3f51b5");
+        }
+
+        let mut ffmpeg_command = command
+            .arg("-frames:v")
+            .arg("1")
+            .arg("-c:v")
+            .arg("mjpeg")
+            .arg("-f")
+            .arg("mjpeg")
+            .arg(output)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        ffmpeg_command.wait().await?;
+
+        Ok(())
+    }
+}
+
+/// Imports pdfs with pdfinfo and renders the first page with ImageMagick.
+pub struct PdfImporter;
+
+#[async_trait]
+impl MediaImporter for PdfImporter {
+    fn supports(&self, mime: &MediaType<'_>) -> bool {
+        mime.subty == "pdf"
+    }
+
+    async fn metadata(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+    ) -> Result<MediaMetadata, MediaImportError> {
+        let command = Command::new("pdfinfo")
+            .arg(path)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+
+        let output = command.wait_with_output().await?;
+        let output_str =
+            String::from_utf8(output.stdout).map_err(|_| MediaImportError::UnexpectedOutput)?;
+
+        parse_pdf_info(output_str.as_str())
+    }
+
+    async fn thumbnail(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+        output: &Path,
+    ) -> Result<(), MediaImportError> {
+        render_image_thumbnail(&format!("pdf:{}[0]", path.display()), output).await
+    }
+}
+
+/// Reads the metadata of office documents by converting them to a pdf with unoconv, they have no thumbnail.
+pub struct OfficeDocumentImporter;
+
+impl OfficeDocumentImporter {
+    fn convert_to_pdf(path: &Path) -> Result<Child, MediaImportError> {
+        Ok(Command::new("unoconv")
+            .arg("--stdout")
+            .arg("-f")
+            .arg("pdf")
+            .arg(path)
+            .stderr(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .spawn()?)
+    }
+}
+
+#[async_trait]
+impl MediaImporter for OfficeDocumentImporter {
+    fn supports(&self, mime: &MediaType<'_>) -> bool {
+        is_office_document(mime)
+    }
+
+    async fn metadata(
+        &self,
+        mime: &MediaType<'_>,
+        path: &Path,
+    ) -> Result<MediaMetadata, MediaImportError> {
+        let mut unoconv = Self::convert_to_pdf(path)?;
+
+        let mut pdfinfo = Command::new("pdfinfo")
+            .arg("-")
+            .stderr(Stdio::inherit())
+            .stdout(Stdio::piped())
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        pipe_commands(&mut unoconv, &mut pdfinfo).await?;
+
+        let output = pdfinfo.wait_with_output().await?;
+        unoconv.wait().await?;
+
+        let output_str =
+            String::from_utf8(output.stdout).map_err(|_| MediaImportError::UnexpectedOutput)?;
+
+        parse_pdf_info(output_str.as_str())
+    }
+}
 
 pub struct MediaImportOutput {
     pub content: Media,
@@ -17,6 +364,7 @@ pub struct MediaImportOutput {
 }
 
 pub async fn import_file_with_thumbnail<O: AsyncWrite + Unpin>(
+    importers: &MediaImporterRegistry,
     file: &Path,
     mime: MediaTypeBuf,
     content_location: Uuid,
@@ -24,53 +372,596 @@ pub async fn import_file_with_thumbnail<O: AsyncWrite + Unpin>(
     thumbnail_location: Uuid,
     thumbnail_output: O,
 ) -> Result<MediaImportOutput, MediaImportError> {
+    let importer = importers
+        .find(&mime.to_ref())
+        .ok_or(MediaImportError::UnsupportedMimeType)?;
+
+    let mut content_digest = MediaDigestable::new(mime.clone(), content_location, content_output);
+    content_digest.write_file(file).await?;
+
+    let metadata = importer.metadata(&mime.to_ref(), file).await?;
+    let content = content_digest.digest(metadata).await?;
+
+    let thumbnail = import_thumbnail(
+        importer,
+        &mime.to_ref(),
+        file,
+        thumbnail_location,
+        thumbnail_output,
+    )
+    .await?;
+
+    Ok(MediaImportOutput { content, thumbnail })
+}
+
+/// Read the metadata and render the thumbnail of an already imported media file at `file` again.
+/// The file itself is not written, so the content keeps its location and hashes.
+pub async fn reprocess_file<O: AsyncWrite + Unpin>(
+    importers: &MediaImporterRegistry,
+    media: &Media,
+    file: &Path,
+    thumbnail_location: Uuid,
+    thumbnail_output: O,
+) -> Result<MediaImportOutput, MediaImportError> {
+    let mime = media.mime.to_ref();
+    let importer = importers
+        .find(&mime)
+        .ok_or(MediaImportError::UnsupportedMimeType)?;
+
+    let content = Media {
+        metadata: importer.metadata(&mime, file).await?,
+        ..media.clone()
+    };
+
+    let thumbnail =
+        import_thumbnail(importer, &mime, file, thumbnail_location, thumbnail_output).await?;
+
+    Ok(MediaImportOutput { content, thumbnail })
+}
+
+async fn import_thumbnail<O: AsyncWrite + Unpin>(
+    importer: &dyn MediaImporter,
+    mime: &MediaType<'_>,
+    file: &Path,
+    location: Uuid,
+    output: O,
+) -> Result<Media, MediaImportError> {
     let thumbnail_mime = MediaTypeBuf::new(mediatype::names::IMAGE, mediatype::names::JPEG);
 
-    let mut digest = ThumbnailWithMediaDigestable {
-        media_digest: MediaDigestable {
-            size_digest: Default::default(),
-            sha256_digest: Default::default(),
-            sha1_digest: Default::default(),
-            md5_digest: Default::default(),
-            metadata_digest: MetadataDigest::from_file(mime.to_ref(), file)?,
-            mime: mime.clone(),
-            location: content_location,
-            output: content_output,
-        },
-        thumbnail_digest: ThumbnailDigestable {
-            method: ThumbnailMethod::from_file(mime.to_ref(), file)?,
-            media_digest: MediaDigestable {
-                size_digest: Default::default(),
-                sha256_digest: Default::default(),
-                sha1_digest: Default::default(),
-                md5_digest: Default::default(),
-                metadata_digest: MetadataDigest::new_image_stream(thumbnail_mime.to_ref())?,
-                mime: thumbnail_mime.clone(),
-                location: thumbnail_location,
-                output: thumbnail_output,
-            },
-        },
+    let thumbnail_file = TmpFile::new().await?;
+    importer
+        .thumbnail(mime, file, thumbnail_file.path())
+        .await?;
+
+    let mut thumbnail_digest = MediaDigestable::new(thumbnail_mime.clone(), location, output);
+    thumbnail_digest.write_file(thumbnail_file.path()).await?;
+
+    let metadata = MediaMetadata::Image {
+        dims: get_image_dimensions(&thumbnail_mime.to_ref(), thumbnail_file.path()).await?,
+        exif: ExifMetadata::default(),
+        animation: None,
+    };
+
+    thumbnail_digest.digest(metadata).await
+}
+
+/// How a video is converted to a rendition that browsers can play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenditionMethod {
+    /// Copy the video stream into an mp4, only the audio is converted to AAC.
+    Remux,
+
+    /// Encode the video as H.264 and the audio as AAC.
+    Transcode,
+}
+
+/// The video codecs that browsers can play.
+const BROWSER_VIDEO_CODECS: [&str; 4] = ["h264", "vp8", "vp9", "av1"];
+
+/// The video codecs that can be copied into an mp4 as is.
+const MP4_VIDEO_CODECS: [&str; 3] = ["h264", "vp9", "av1"];
+
+/// How a video has to be converted to be played in browsers, `None` if it can already be played
+/// or is not a video.
+pub fn rendition_method(media: &Media) -> Option<RenditionMethod> {
+    let codec = media.metadata.video_encoding()?;
+    let is_browser_container = matches!(media.mime.subty().as_str(), "mp4" | "webm");
+
+    if is_browser_container && BROWSER_VIDEO_CODECS.contains(&codec) {
+        None
+    } else if MP4_VIDEO_CODECS.contains(&codec) {
+        Some(RenditionMethod::Remux)
+    } else {
+        Some(RenditionMethod::Transcode)
+    }
+}
+
+/// Convert the video at `file` to an mp4 that browsers can play.
+/// Returns `None` if the video can already be played.
+pub async fn import_rendition<O: AsyncWrite + Unpin>(
+    importers: &MediaImporterRegistry,
+    media: &Media,
+    file: &Path,
+    location: Uuid,
+    output: O,
+) -> Result<Option<Media>, MediaImportError> {
+    let Some(method) = rendition_method(media) else {
+        return Ok(None);
     };
 
-    let mut file = OpenOptions::new()
-        .read(true)
-        .write(false)
-        .open(file)
+    let mime = MediaTypeBuf::new(mediatype::names::VIDEO, mediatype::names::MP4);
+    let importer = importers
+        .find(&mime.to_ref())
+        .ok_or(MediaImportError::UnsupportedMimeType)?;
+
+    let rendition_file = TmpFile::new().await?;
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-v")
+        .arg("error")
+        .arg("-i")
+        .arg(file)
+        .arg("-map")
+        .arg("0:v:0")
+        .arg("-map")
+        .arg("0:a:0?");
+
+    match method {
+        RenditionMethod::Remux => {
+            command.arg("-c:v").arg("copy");
+        }
+        RenditionMethod::Transcode => {
+            // yuv420p, which most browsers require, needs even dimensions
+            command
+                .arg("-c:v")
+                .arg("libx264")
+                .arg("-preset")
+                .arg("veryfast")
+                .arg("-crf")
+                .arg("23")
+                .arg("-pix_fmt")
+                .arg("yuv420p")
+                .arg("-vf")
+                .arg("scale=trunc(iw/2)*2:trunc(ih/2)*2");
+        }
+    }
+
+    command
+        .arg("-c:a")
+        .arg("aac")
+        .arg("-b:a")
+        .arg("160k")
+        .arg("-movflags")
+        .arg("+faststart")
+        .arg("-f")
+        .arg("mp4")
+        .arg(rendition_file.path())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit());
+
+    let status = spawn_program(&mut command, "ffmpeg")?.wait().await?;
+
+    if !status.success() {
+        return Err(MediaImportError::UnexpectedOutput);
+    }
+
+    let mut digest = MediaDigestable::new(mime.clone(), location, output);
+    digest.write_file(rendition_file.path()).await?;
+
+    let metadata = importer
+        .metadata(&mime.to_ref(), rendition_file.path())
         .await?;
 
-    let mut buffer = [0_u8; 1024];
+    Ok(Some(digest.digest(metadata).await?))
+}
 
-    loop {
-        let size = file.read(&mut buffer).await?;
+/// The number of seconds between two frames of a storyboard of a short video.
+const STORYBOARD_INTERVAL: i32 = 10;
 
-        if size == 0 {
-            break;
+/// Longer videos use a longer interval, so the sprite never has more frames than this.
+const STORYBOARD_MAX_FRAMES: i32 = 100;
+
+const STORYBOARD_COLUMNS: i32 = 10;
+
+/// The width of a single frame of a storyboard.
+const STORYBOARD_TILE_WIDTH: i32 = 160;
+
+/// How the frames of a video with this duration in seconds are laid out in its storyboard,
+/// as the interval, number of columns and number of frames.
+fn storyboard_layout(duration: i32) -> (i32, i32, i32) {
+    let duration = duration.max(1);
+    let interval =
+        STORYBOARD_INTERVAL.max((duration + STORYBOARD_MAX_FRAMES - 1) / STORYBOARD_MAX_FRAMES);
+    let frame_count = (duration + interval - 1) / interval;
+
+    (interval, STORYBOARD_COLUMNS.min(frame_count), frame_count)
+}
+
+/// Render the storyboard sprite of the video at `file` as a jpeg with ffmpeg.
+/// Only key frames are decoded, so the frames are taken from the closest key frame.
+/// The sprite of the returned storyboard has not been stored yet, `None` is returned if the media is
+/// not a video.
+pub async fn import_storyboard<O: AsyncWrite + Unpin>(
+    media: &Media,
+    file: &Path,
+    location: Uuid,
+    output: O,
+) -> Result<Option<Storyboard>, MediaImportError> {
+    let (Some(duration), Some(width), Some(height)) = (
+        media.metadata.duration(),
+        media.metadata.width(),
+        media.metadata.height(),
+    ) else {
+        return Ok(None);
+    };
+
+    if media.metadata.video_encoding().is_none() || *width <= 0 || *height <= 0 {
+        return Ok(None);
+    }
+
+    let (interval, columns, frame_count) = storyboard_layout(duration);
+    let rows = (frame_count + columns - 1) / columns;
+
+    // yuvj420p needs even dimensions
+    let tile_height = STORYBOARD_TILE_WIDTH * height / width;
+    let tile = Dimensions {
+        width: STORYBOARD_TILE_WIDTH,
+        height: tile_height + tile_height % 2,
+    };
+
+    let sprite_file = TmpFile::new().await?;
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-v")
+        .arg("error")
+        .arg("-skip_frame")
+        .arg("nokey")
+        .arg("-i")
+        .arg(file)
+        .arg("-vf")
+        .arg(format!(
+            "fps=1/{interval},scale={}:{},tile={columns}x{rows}",
+            tile.width, tile.height
+        ))
+        .arg("-frames:v")
+        .arg("1")
+        .arg("-c:v")
+        .arg("mjpeg")
+        .arg("-f")
+        .arg("mjpeg")
+        .arg(sprite_file.path())
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit());
+
+    let status = spawn_program(&mut command, "ffmpeg")?.wait().await?;
+
+    if !status.success() {
+        return Err(MediaImportError::UnexpectedOutput);
+    }
+
+    let mime = MediaTypeBuf::new(mediatype::names::IMAGE, mediatype::names::JPEG);
+    let mut digest = MediaDigestable::new(mime, location, output);
+    digest.write_file(sprite_file.path()).await?;
+
+    let metadata = MediaMetadata::Image {
+        dims: Dimensions {
+            width: tile.width * columns,
+            height: tile.height * rows,
+        },
+        exif: ExifMetadata::default(),
+        animation: None,
+    };
+
+    Ok(Some(Storyboard {
+        sprite: ManyToOne::Obj(digest.digest(metadata).await?),
+        interval,
+        tile,
+        columns,
+        frame_count,
+    }))
+}
+
+/// The length of HLS segments in seconds, the last segment may be shorter.
+const HLS_SEGMENT_SECONDS: u32 = 6;
+
+/// The lower qualities of the HLS bitrate ladder, as the height and video bitrate in kbit/s.
+const HLS_LADDER: [(i32, u32); 4] = [(1080, 5000), (720, 2800), (480, 1400), (360, 800)];
+
+/// How a variant of a video is encoded for HLS streaming.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HlsVariantSpec {
+    /// The height the video is scaled to, `None` keeps the original size.
+    pub height: Option<i32>,
+
+    /// The video bitrate in kbit/s, `None` copies H.264 video or encodes it at a constant quality.
+    pub bitrate: Option<u32>,
+}
+
+impl HlsVariantSpec {
+    /// The dimensions of this variant of a video with the `original` dimensions.
+    pub fn dims(&self, original: &Dimensions) -> Dimensions {
+        let Some(height) = self.height.filter(|_| original.height > 0) else {
+            return original.clone();
+        };
+
+        // ffmpeg keeps the aspect ratio and rounds the width to an even number
+        let width = (original.width as i64 * height as i64 / original.height as i64) as i32;
+
+        Dimensions {
+            width: width + width % 2,
+            height,
+        }
+    }
+}
+
+/// The variants a video is segmented into for HLS, the highest quality first.
+/// The original quality is always included, the lower bitrates only if `ladder` is set.
+pub fn hls_variant_specs(media: &Media, ladder: bool) -> Vec<HlsVariantSpec> {
+    if media.metadata.video_encoding().is_none() {
+        return Vec::new();
+    }
+
+    let mut specs = vec![HlsVariantSpec {
+        height: None,
+        bitrate: None,
+    }];
+
+    if ladder {
+        let original_height = media.metadata.height().copied().unwrap_or_default();
+
+        specs.extend(
+            HLS_LADDER
+                .iter()
+                .filter(|(height, _)| *height < original_height)
+                .map(|(height, bitrate)| HlsVariantSpec {
+                    height: Some(*height),
+                    bitrate: Some(*bitrate),
+                }),
+        );
+    }
+
+    specs
+}
+
+/// Cut the video at `file` into MPEG-TS segments in `dir` with ffmpeg.
+/// Returns the duration in seconds and the path of each segment in playback order.
+pub async fn segment_hls(
+    media: &Media,
+    file: &Path,
+    spec: &HlsVariantSpec,
+    dir: &Path,
+) -> Result<Vec<(f64, PathBuf)>, MediaImportError> {
+    let playlist_path = dir.join("index.m3u8");
+
+    let mut command = Command::new("ffmpeg");
+    command
+        .arg("-v")
+        .arg("error")
+        .arg("-i")
+        .arg(file)
+        .arg("-map")
+        .arg("0:v:0")
+        .arg("-map")
+        .arg("0:a:0?");
+
+    let is_h264 = media.metadata.video_encoding() == Some("h264");
+
+    if spec.height.is_none() && spec.bitrate.is_none() && is_h264 {
+        command.arg("-c:v").arg("copy");
+    } else {
+        let scale = match spec.height {
+            Some(height) => format!("scale=-2:{height}"),
+            None => "scale=trunc(iw/2)*2:trunc(ih/2)*2".to_string(),
+        };
+
+        // key frames at every segment boundary keep the segments of all variants aligned
+        command
+            .arg("-c:v")
+            .arg("libx264")
+            .arg("-preset")
+            .arg("veryfast")
+            .arg("-pix_fmt")
+            .arg("yuv420p")
+            .arg("-vf")
+            .arg(scale)
+            .arg("-force_key_frames")
+            .arg(format!("expr:gte(t,n_forced*{HLS_SEGMENT_SECONDS})"));
+
+        match spec.bitrate {
+            Some(bitrate) => {
+                command
+                    .arg("-b:v")
+                    .arg(format!("{bitrate}k"))
+                    .arg("-maxrate")
+                    .arg(format!("{}k", bitrate * 107 / 100))
+                    .arg("-bufsize")
+                    .arg(format!("{}k", bitrate * 3 / 2));
+            }
+            None => {
+                command.arg("-crf").arg("23");
+            }
+        }
+    }
+
+    command
+        .arg("-c:a")
+        .arg("aac")
+        .arg("-b:a")
+        .arg("128k")
+        .arg("-ac")
+        .arg("2")
+        .arg("-f")
+        .arg("hls")
+        .arg("-hls_time")
+        .arg(HLS_SEGMENT_SECONDS.to_string())
+        .arg("-hls_playlist_type")
+        .arg("vod")
+        .arg("-hls_segment_filename")
+        .arg(dir.join("%d.ts"))
+        .arg(&playlist_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::inherit());
+
+    let status = spawn_program(&mut command, "ffmpeg")?.wait().await?;
+
+    if !status.success() {
+        return Err(MediaImportError::UnexpectedOutput);
+    }
+
+    let playlist = tokio::fs::read_to_string(&playlist_path).await?;
+    let segments = parse_hls_playlist(&playlist)
+        .into_iter()
+        .map(|(duration, name)| (duration, dir.join(name)))
+        .collect();
+
+    Ok(segments)
+}
+
+/// Read the duration and file name of each segment from a media playlist written by ffmpeg.
+fn parse_hls_playlist(playlist: &str) -> Vec<(f64, &str)> {
+    let mut segments = Vec::new();
+    let mut duration = None;
+
+    for line in playlist.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            duration = info.split(',').next().and_then(|d| d.parse::<f64>().ok());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            if let Some(duration) = duration.take() {
+                segments.push((duration, line));
+            }
+        }
+    }
+
+    segments
+}
+
+/// Whether text of this kind can be extracted from files of this type.
+pub fn supports_text(mime: &MediaType<'_>, kind: MediaTextKind) -> bool {
+    match kind {
+        MediaTextKind::Document => mime.subty == "pdf" || is_office_document(mime),
+        MediaTextKind::Ocr => {
+            mime.subty == "pdf" || (mime.ty == "image" && mime.subty != "svg+xml")
+        }
+    }
+}
+
+fn is_office_document(mime: &MediaType<'_>) -> bool {
+    matches!(
+        (mime.ty.as_str(), mime.subty.as_str()),
+        ("document", _)
+            | (
+                "application",
+                "vnd.openxmlformats-officedocument.wordprocessingml.document"
+            )
+    )
+}
+
+/// Extract the text of a pdf or office document.
+/// Returns `None` if text cannot be extracted from files of this type.
+pub async fn extract_document_text(
+    mime: MediaType<'_>,
+    path: &Path,
+) -> Result<Option<String>, MediaImportError> {
+    let (name, mut command) = if mime.subty == "pdf" {
+        let mut command = Command::new("pdftotext");
+        command
+            .arg("-q")
+            .arg("-enc")
+            .arg("UTF-8")
+            .arg(path)
+            .arg("-");
+        ("pdftotext", command)
+    } else if is_office_document(&mime) {
+        let mut command = Command::new("unoconv");
+        command.arg("--stdout").arg("-f").arg("txt").arg(path);
+        ("unoconv", command)
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(read_program_text(&mut command, name).await?))
+}
+
+/// Recognise the text of an image or a scanned pdf with `tesseract`.
+/// Returns `None` if files of this type cannot be recognised.
+pub async fn extract_ocr_text(
+    mime: MediaType<'_>,
+    path: &Path,
+) -> Result<Option<String>, MediaImportError> {
+    if !supports_text(&mime, MediaTextKind::Ocr) {
+        return Ok(None);
+    }
+
+    let mut pages = None;
+
+    let image = if mime.subty == "pdf" {
+        // tesseract cannot read pdfs, so the pages are rendered to a multi page tiff first
+        let tiff = pages.insert(TmpFile::new().await?);
+
+        let mut convert = Command::new("convert");
+        convert
+            .arg("-density")
+            .arg("300")
+            .arg(format!("pdf:{}", path.display()))
+            .arg("-depth")
+            .arg("8")
+            .arg(format!("tiff:{}", tiff.path().display()))
+            .stderr(Stdio::inherit());
+
+        let status = spawn_program(&mut convert, "convert")?.wait().await?;
+
+        if !status.success() {
+            return Err(MediaImportError::UnexpectedOutput);
         }
 
-        digest.write(&buffer[0..size]).await?;
+        tiff.path()
+    } else {
+        path
+    };
+
+    let mut command = Command::new("tesseract");
+    command.arg(image).arg("stdout");
+
+    Ok(Some(read_program_text(&mut command, "tesseract").await?))
+}
+
+/// Spawn a command, reporting `MissingProgram` if it is not installed.
+fn spawn_program(command: &mut Command, name: &'static str) -> Result<Child, MediaImportError> {
+    command.spawn().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => MediaImportError::MissingProgram { name },
+        _ => e.into(),
+    })
+}
+
+/// Run a command and read its output as text, truncated to `MAX_EXTRACTED_TEXT_LEN`.
+/// A failing command is reported as `UnexpectedOutput` instead of storing its partial output.
+async fn read_program_text(
+    command: &mut Command,
+    name: &'static str,
+) -> Result<String, MediaImportError> {
+    command.stdout(Stdio::piped()).stderr(Stdio::inherit());
+
+    let output = spawn_program(command, name)?.wait_with_output().await?;
+
+    if !output.status.success() {
+        return Err(MediaImportError::UnexpectedOutput);
+    }
+
+    let mut text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    if text.len() > MAX_EXTRACTED_TEXT_LEN {
+        let mut end = MAX_EXTRACTED_TEXT_LEN;
+
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        text.truncate(end);
     }
 
-    digest.digest().await
+    Ok(text)
 }
 
 async fn get_video_codec(path: &Path) -> Result<String, MediaImportError> {
@@ -102,32 +993,6 @@ async fn get_video_codec(path: &Path) -> Result<String, MediaImportError> {
     Ok(output)
 }
 
-struct ThumbnailWithMediaDigestable<'a, O> {
-    media_digest: MediaDigestable<'a, O>,
-    thumbnail_digest: ThumbnailDigestable<'a, O>,
-}
-
-impl<'a, O> ThumbnailWithMediaDigestable<'a, O>
-where
-    O: AsyncWrite + Unpin,
-{
-    async fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
-        self.media_digest.write(data).await?;
-        Ok(())
-    }
-
-    async fn digest(self) -> Result<MediaImportOutput, MediaImportError> {
-        Ok(MediaImportOutput {
-            content: self.media_digest.digest().await?,
-            thumbnail: self.thumbnail_digest.digest().await?,
-        })
-    }
-}
-
-enum CompatMethod<'a> {
-    Unoconv { path: &'a Path },
-}
-
 pub struct TmpFile {
     path: PathBuf,
 }
@@ -153,21 +1018,29 @@ impl Drop for TmpFile {
     }
 }
 
-enum ThumbnailMethod<'a> {
-    ImageMagick { path: &'a Path, mime: MediaType<'a> },
-    Ffmpeg { path: &'a Path, mime: MediaType<'a> },
+/// A temporary directory that is removed together with its files when dropped.
+pub struct TmpDir {
+    path: PathBuf,
 }
 
-impl<'a> ThumbnailMethod<'a> {
-    fn from_file(
-        mime: MediaType<'a>,
-        path: &'a Path,
-    ) -> Result<ThumbnailMethod<'a>, MediaImportError> {
-        match (mime.ty.as_str(), mime.subty.as_str()) {
-            ("image", _) | ("application", "pdf") => Ok(Self::ImageMagick { path, mime }),
-            ("video", _) => Ok(Self::Ffmpeg { path, mime }),
-            _ => Err(MediaImportError::UnsupportedMimeType),
-        }
+impl TmpDir {
+    pub async fn new() -> Result<Self, MediaImportError> {
+        let uuid = Uuid::new_v4();
+        let path = env::temp_dir().join(format!("mb-{uuid}"));
+
+        tokio::fs::create_dir(&path).await?;
+
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
     }
 }
 
@@ -179,68 +1052,371 @@ fn map_ffmpeg_mime<'a>(mime: &'a MediaType<'_>) -> &'a str {
     }
 }
 
-struct ThumbnailDigestable<'a, O> {
-    method: ThumbnailMethod<'a>,
-    media_digest: MediaDigestable<'a, O>,
+fn thumbnail_command(input: &str, output: &Path) -> Command {
+    let mut command = Command::new("convert");
+    // rotate by the EXIF orientation before it is stripped
+    command
+        .arg(input)
+        .arg("-auto-orient")
+        .arg("-strip")
+        .arg("-quality")
+        .arg("50")
+        .arg("-resize")
+        .arg("300x300")
+        .arg("-background")
+        .arg("white")
+        .arg("-alpha")
+        .arg("remove")
+        .arg("-alpha")
+        .arg("off")
+        .arg(format!("jpg:{}", output.display()))
+        .stderr(Stdio::inherit());
+    command
+}
+
+/// Render a thumbnail of an ImageMagick input like `png:path[0]`.
+async fn render_image_thumbnail(input: &str, output: &Path) -> Result<(), MediaImportError> {
+    thumbnail_command(input, output).spawn()?.wait().await?;
+
+    Ok(())
+}
+
+/// Whether images of this type can be resized with ImageMagick.
+pub fn supports_resizing(mime: &MediaType<'_>) -> bool {
+    mime.ty == "image"
+}
+
+/// The ImageMagick geometry that resizes an image to `params`, `None` if it keeps its size.
+fn resize_geometry(params: &ResizeParams) -> Option<String> {
+    let size = match (params.width, params.height) {
+        (0, 0) => return None,
+        (width, 0) => format!("{width}"),
+        (0, height) => format!("x{height}"),
+        (width, height) => format!("{width}x{height}"),
+    };
+
+    let constrained = params.width != 0 && params.height != 0;
+
+    Some(match params.fit {
+        ResizeFit::Cover if constrained => format!("{size}^"),
+        ResizeFit::Fill if constrained => format!("{size}!"),
+        // never enlarge an image that already fits
+        _ => format!("{size}>"),
+    })
+}
+
+/// Render the first frame of an image at `path` resized to `params` into `output`.
+pub async fn render_resized_image(
+    mime: &MediaType<'_>,
+    path: &Path,
+    params: &ResizeParams,
+    output: &Path,
+) -> Result<(), MediaImportError> {
+    let mut command = Command::new("convert");
+    command
+        .arg(format!("{}:{}[0]", mime.subty, path.display()))
+        .arg("-auto-orient");
+
+    if let Some(geometry) = resize_geometry(params) {
+        command.arg("-resize").arg(geometry);
+    }
+
+    if params.fit == ResizeFit::Cover && params.width != 0 && params.height != 0 {
+        command
+            .arg("-gravity")
+            .arg("center")
+            .arg("-extent")
+            .arg(format!("{}x{}", params.width, params.height));
+    }
+
+    if params.format == ResizeFormat::Jpeg {
+        command
+            .arg("-background")
+            .arg("white")
+            .arg("-alpha")
+            .arg("remove")
+            .arg("-alpha")
+            .arg("off");
+    }
+
+    command
+        .arg("-strip")
+        .arg("-quality")
+        .arg("80")
+        .arg(format!("{}:{}", params.format.as_str(), output.display()))
+        .stderr(Stdio::inherit());
+
+    let status = spawn_program(&mut command, "convert")?.wait().await?;
+
+    if !status.success() {
+        return Err(MediaImportError::UnexpectedOutput);
+    }
+
+    Ok(())
+}
+
+/// The tags that are read by `read_exif`, in the order `parse_exif` expects them.
+/// The same tags in XMP are used if a file has no EXIF metadata.
+const EXIF_TAGS: [&str; 13] = [
+    "-Make",
+    "-Model",
+    "-LensModel",
+    "-ExposureTime",
+    "-FNumber",
+    "-ISO",
+    "-FocalLength",
+    "-Orientation",
+    "-DateTimeOriginal",
+    "-CreateDate",
+    "-OffsetTimeOriginal",
+    "-GPSLatitude",
+    "-GPSLongitude",
+];
+
+/// Read the EXIF and XMP metadata of a photo, nothing is read if exiftool is not installed.
+async fn read_exif(path: &Path) -> Result<ExifMetadata, MediaImportError> {
+    let mut command = Command::new("exiftool");
+    command
+        .arg("-T")
+        .arg("-n")
+        .arg("-fast")
+        .args(EXIF_TAGS)
+        .arg(path)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+
+    let output = match spawn_program(&mut command, "exiftool") {
+        Ok(child) => child.wait_with_output().await?,
+        Err(MediaImportError::MissingProgram { .. }) => return Ok(ExifMetadata::default()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(parse_exif(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the tab separated output of `exiftool -T -n` for `EXIF_TAGS`, missing tags are a `-`.
+/// The coordinates are signed, south and west are negative.
+fn parse_exif(output: &str) -> ExifMetadata {
+    let values: Vec<Option<&str>> = output
+        .trim_end_matches(['\r', '\n'])
+        .split('\t')
+        .map(|value| Some(value.trim()).filter(|value| !value.is_empty() && *value != "-"))
+        .collect();
+
+    let value = |i: usize| values.get(i).copied().flatten();
+    let text = |i: usize| value(i).map(str::to_string);
+    let number = |i: usize| value(i).and_then(|value| value.parse::<f64>().ok());
+
+    let offset = value(10);
+    let taken_at = value(8)
+        .and_then(|date| parse_exif_date(date, offset))
+        .or_else(|| value(9).and_then(|date| parse_exif_date(date, offset)));
+
+    ExifMetadata {
+        camera_make: text(0),
+        camera_model: text(1),
+        lens: text(2),
+        exposure_time: number(3),
+        f_number: number(4),
+        iso: number(5).map(|iso| iso as i32),
+        focal_length: number(6),
+        orientation: number(7).map(|orientation| orientation as i32),
+        taken_at,
+        latitude: number(11).filter(|latitude| latitude.abs() <= 90.0),
+        longitude: number(12).filter(|longitude| longitude.abs() <= 180.0),
+    }
+}
+
+/// Parses an EXIF date like `2023:05:01 12:30:00`, optionally followed by fractional seconds
+/// and a time zone as XMP dates are. The time is assumed to be UTC without any time zone.
+fn parse_exif_date(date: &str, offset: Option<&str>) -> Option<DateTime<Utc>> {
+    let local = NaiveDateTime::parse_from_str(date.get(..19)?, "%Y:%m:%d %H:%M:%S").ok()?;
+
+    let zone = date[19..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    let zone = Some(zone).filter(|zone| !zone.is_empty()).or(offset);
+
+    let offset = match zone {
+        None | Some("Z") => FixedOffset::east_opt(0)?,
+        Some(zone) => zone.parse().ok()?,
+    };
+
+    Some(
+        local
+            .and_local_timezone(offset)
+            .single()?
+            .with_timezone(&Utc),
+    )
+}
+
+/// Whether exiftool can remove the location from files of this type.
+pub fn supports_gps_stripping(mime: &MediaType<'_>) -> bool {
+    matches!(
+        (mime.ty.as_str(), mime.subty.as_str()),
+        ("image", "jpeg" | "png" | "tiff" | "webp" | "heic" | "heif")
+            | ("video", "mp4" | "quicktime")
+    )
+}
+
+/// Copy a photo or video to `output` without any GPS tags, in EXIF, XMP or QuickTime metadata.
+pub async fn strip_gps(path: &Path, output: &Path) -> Result<(), MediaImportError> {
+    let mut command = Command::new("exiftool");
+    command
+        .arg("-q")
+        .arg("-gps*=")
+        .arg("-o")
+        .arg(output)
+        .arg(path)
+        .stderr(Stdio::inherit());
+
+    let status = spawn_program(&mut command, "exiftool")?.wait().await?;
+
+    if !status.success() {
+        return Err(MediaImportError::UnexpectedOutput);
+    }
+
+    Ok(())
+}
+
+/// Count the frames of an animated gif, webp or png, `None` if the image has a single frame.
+async fn get_image_animation(
+    mime: &MediaType<'_>,
+    path: &Path,
+) -> Result<Option<Animation>, MediaImportError> {
+    let coder = match mime.subty.as_str() {
+        coder @ ("gif" | "webp" | "apng") => coder,
+        // the png coder only reads the first frame of an animated png
+        "png" if is_apng(path).await? => "apng",
+        _ => return Ok(None),
+    };
+
+    let command = Command::new("identify")
+        .arg("-format")
+        .arg("%T\n")
+        .arg(format!("{coder}:{}", path.display()))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+
+    let output = command.wait_with_output().await?;
+    let output_str =
+        String::from_utf8(output.stdout).map_err(|_| MediaImportError::UnexpectedOutput)?;
+
+    parse_frame_delays(output_str.as_str())
+}
+
+/// Parses the delay of each frame in centiseconds, one per line.
+/// Like browsers do, frames with a delay of at most one centisecond are shown for ten.
+fn parse_frame_delays(output: &str) -> Result<Option<Animation>, MediaImportError> {
+    let delays = output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| MediaImportError::UnexpectedOutput)?;
+
+    if delays.len() <= 1 {
+        return Ok(None);
+    }
+
+    Ok(Some(Animation {
+        frame_count: delays.len() as i32,
+        loop_duration_ms: delays
+            .iter()
+            .map(|delay| if *delay <= 1 { 100 } else { delay * 10 })
+            .sum(),
+    }))
+}
+
+/// Whether a png has an animation control chunk, which comes before the image data.
+async fn is_apng(path: &Path) -> Result<bool, MediaImportError> {
+    let mut file = File::open(path).await?;
+
+    // a file that ends before an animation control chunk is not animated, however short it is
+    let mut signature = [0_u8; 8];
+
+    if !read_unless_eof(&mut file, &mut signature).await? {
+        return Ok(false);
+    }
+
+    loop {
+        let mut chunk_header = [0_u8; 8];
+
+        if !read_unless_eof(&mut file, &mut chunk_header).await? {
+            return Ok(false);
+        }
+
+        let (length, chunk_type) = chunk_header.split_at(4);
+
+        match chunk_type {
+            b"acTL" => return Ok(true),
+            b"IDAT" => return Ok(false),
+            _ => {
+                let length = u32::from_be_bytes(length.try_into().unwrap());
+                // skip the data and the crc
+                file.seek(SeekFrom::Current(length as i64 + 4)).await?;
+            }
+        }
+    }
+}
+
+/// Fill `buf` from the file, returns false if the file ends first.
+async fn read_unless_eof(file: &mut File, buf: &mut [u8]) -> Result<bool, std::io::Error> {
+    match file.read_exact(buf).await {
+        Ok(_) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+async fn get_image_dimensions(
+    mime: &MediaType<'_>,
+    path: &Path,
+) -> Result<Dimensions, MediaImportError> {
+    let command = Command::new("identify")
+        .arg("-ping")
+        .arg("-format")
+        .arg("%wx%h")
+        .arg(format!("{}:{}[0]", mime.subty, path.display()))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+
+    let output = command.wait_with_output().await?;
+
+    parse_dimensions(output.stdout)
+}
+
+struct MediaDigestable<O> {
+    size_digest: SizeDigestable,
+    sha256_digest: Sha256Digestable,
+    sha1_digest: Sha1Digestable,
+    md5_digest: MD5Digestable,
+    mime: MediaTypeBuf,
+    location: Uuid,
+    output: O,
 }
 
-impl<'a, O> ThumbnailDigestable<'a, O>
+impl<O> MediaDigestable<O>
 where
     O: AsyncWrite + Unpin,
 {
-    async fn digest(mut self) -> Result<Media, MediaImportError> {
-        let output_file = TmpFile::new().await?;
-
-        match self.method {
-            ThumbnailMethod::ImageMagick { path, mime } => {
-                let mut command = Command::new("convert")
-                    .arg(format!("{}:{}[0]", mime.subty, path.display()))
-                    .arg("-strip")
-                    .arg("-quality")
-                    .arg("50")
-                    .arg("-resize")
-                    .arg("300x300")
-                    .arg("-background")
-                    .arg("white")
-                    .arg("-alpha")
-                    .arg("remove")
-                    .arg("-alpha")
-                    .arg("off")
-                    .arg(format!("jpg:{}", output_file.path.display()))
-                    .stderr(Stdio::inherit())
-                    .spawn()?;
-
-                command.wait().await?;
-            }
-            ThumbnailMethod::Ffmpeg { path, mime } => {
-                let duration = get_video_duration(path).await?;
-
-                let mut ffmpeg_command = Command::new("ffmpeg")
-                    .arg("-ss")
-                    .arg((duration / 2.0).to_string())
-                    .arg("-f")
-                    .arg(map_ffmpeg_mime(&mime))
-                    .arg("-i")
-                    .arg(path.to_str().unwrap())
-                    .arg("-vframes")
-                    .arg("1")
-                    .arg("-c:v")
-                    .arg("mjpeg")
-                    .arg("-f")
-                    .arg("mjpeg")
-                    .arg(output_file.path.as_path())
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::inherit())
-                    .spawn()?;
-
-                ffmpeg_command.wait().await?;
-            }
+    fn new(mime: MediaTypeBuf, location: Uuid, output: O) -> Self {
+        Self {
+            size_digest: Default::default(),
+            sha256_digest: Default::default(),
+            sha1_digest: Default::default(),
+            md5_digest: Default::default(),
+            mime,
+            location,
+            output,
         }
+    }
 
+    async fn write_file(&mut self, path: &Path) -> std::io::Result<()> {
         let mut file = OpenOptions::new()
             .read(true)
             .write(false)
-            .open(&output_file.path)
+            .open(path)
             .await?;
 
         let mut buffer = [0_u8; 1024];
@@ -252,46 +1428,29 @@ where
                 break;
             }
 
-            self.media_digest.write(&buffer[0..size]).await?;
+            self.write(&buffer[0..size]).await?;
         }
 
-        self.media_digest.digest().await
+        Ok(())
     }
-}
-
-struct MediaDigestable<'a, O> {
-    size_digest: SizeDigestable,
-    sha256_digest: Sha256Digestable,
-    sha1_digest: Sha1Digestable,
-    md5_digest: MD5Digestable,
-    metadata_digest: MetadataDigest<'a>,
-    mime: MediaTypeBuf,
-    location: Uuid,
-    output: O,
-}
 
-impl<'a, O> MediaDigestable<'a, O>
-where
-    O: AsyncWrite + Unpin,
-{
     async fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
         self.size_digest.write(data);
         self.sha256_digest.write(data);
         self.sha1_digest.write(data);
         self.md5_digest.write(data);
 
-        self.metadata_digest.write(data).await?;
         self.output.write_all(data).await?;
 
         Ok(())
     }
 
-    async fn digest(mut self) -> Result<Media, MediaImportError> {
+    async fn digest(mut self, metadata: MediaMetadata) -> Result<Media, MediaImportError> {
         self.output.flush().await?;
 
         Ok(Media {
             id: 0,
-            metadata: self.metadata_digest.digest().await?,
+            metadata,
             file_id: self.location,
             file_size: self.size_digest.digest(),
             sha1: self.sha1_digest.digest(),
@@ -328,228 +1487,126 @@ async fn pipe_commands(source: &mut Child, target: &mut Child) -> Result<(), Med
     Ok(())
 }
 
-enum MetadataDigest<'a> {
-    ImageFile { path: &'a Path, mime: MediaType<'a> },
-    PdfFile { path: &'a Path, mime: MediaType<'a> },
-    MiscDoc { path: &'a Path, mime: MediaType<'a> },
-    ImageStream { process: Child },
-    VideoFile { path: &'a Path, mime: MediaType<'a> },
-}
-
-impl<'a> MetadataDigest<'a> {
-    fn from_file(
-        mime: MediaType<'a>,
-        path: &'a Path,
-    ) -> Result<MetadataDigest<'a>, MediaImportError> {
-        if mime.subty == "pdf" {
-            return Ok(Self::PdfFile { path, mime });
-        }
+fn parse_dimensions(output: Vec<u8>) -> Result<Dimensions, MediaImportError> {
+    let output_str = String::from_utf8(output).map_err(|_| MediaImportError::UnexpectedOutput)?;
 
-        match (mime.ty.as_str(), mime.subty.as_str()) {
-            ("image", _) => Ok(Self::ImageFile { path, mime }),
-            ("video", _) => Ok(Self::VideoFile { path, mime }),
-            ("document", _)
-            | ("application", "vnd.openxmlformats-officedocument.wordprocessingml.document") => {
-                Ok(Self::MiscDoc { path, mime })
-            }
-            _ => Err(MediaImportError::UnsupportedMimeType),
-        }
-    }
+    let split: Vec<&str> = output_str.split('x').collect();
 
-    fn new_image_stream(mime: MediaType<'_>) -> Result<MetadataDigest<'a>, MediaImportError> {
-        Ok(Self::ImageStream {
-            process: Command::new("identify")
-                .arg("-ping")
-                .arg("-format")
-                .arg("%wx%h")
-                .arg(format!("{}:-[0]", mime.subty))
-                .stdin(Stdio::piped())
-                .stdout(Stdio::piped())
-                .stderr(Stdio::inherit())
-                .spawn()?,
-        })
-    }
-
-    async fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
-        if let Self::ImageStream { process } = self {
-            if let Some(stdin) = process.stdin.as_mut() {
-                stdin.write_all(data).await?;
-            }
-        }
-
-        Ok(())
+    if split.len() != 2 {
+        return Err(MediaImportError::UnexpectedOutput);
     }
 
-    fn parse_output(output: Vec<u8>) -> Result<Dimensions, MediaImportError> {
-        let output_str =
-            String::from_utf8(output).map_err(|_| MediaImportError::UnexpectedOutput)?;
-
-        let split: Vec<&str> = output_str.split('x').collect();
+    Ok(Dimensions {
+        width: split[0]
+            .trim()
+            .parse()
+            .map_err(|_| MediaImportError::UnexpectedOutput)?,
+        height: split[1]
+            .trim()
+            .parse()
+            .map_err(|_| MediaImportError::UnexpectedOutput)?,
+    })
+}
 
-        if split.len() != 2 {
-            return Err(MediaImportError::UnexpectedOutput);
+/// Parses the `key=value` lines of ffprobe, tags are prefixed with `TAG:` and their case differs per format.
+fn parse_audio_probe(output_str: &str) -> Result<MediaMetadata, MediaImportError> {
+    let mut duration = None;
+    let mut bitrate = None;
+    let mut sample_rate = None;
+    let mut artist = None;
+    let mut album = None;
+    let mut title = None;
+    let mut track = None;
+
+    for line in output_str.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        let key = key.to_lowercase();
+        let value = value.trim();
+
+        if value.is_empty() || value == "N/A" {
+            continue;
         }
 
-        Ok(Dimensions {
-            width: split[0]
-                .trim()
-                .parse()
-                .map_err(|_| MediaImportError::UnexpectedOutput)?,
-            height: split[1]
-                .trim()
-                .parse()
-                .map_err(|_| MediaImportError::UnexpectedOutput)?,
-        })
-    }
-
-    async fn digest(self) -> Result<MediaMetadata, MediaImportError> {
-        match self {
-            MetadataDigest::ImageFile { mime, path } => {
-                let command = Command::new("identify")
-                    .arg("-ping")
-                    .arg("-format")
-                    .arg("%wx%h")
-                    .arg(format!("{}:{}[0]", mime.subty, path.display()))
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::inherit())
-                    .spawn()?;
-
-                let output = command.wait_with_output().await?;
-
-                let dims = Self::parse_output(output.stdout)?;
-                Ok(MediaMetadata::Image { dims })
-            }
-            MetadataDigest::ImageStream { mut process } => {
-                if let Some(mut stdout) = process.stdin.take() {
-                    stdout.flush().await?;
-                }
-
-                let output = process.wait_with_output().await?;
-
-                let dims = Self::parse_output(output.stdout)?;
-                Ok(MediaMetadata::Image { dims })
-            }
-            MetadataDigest::VideoFile { mime, path } => {
-                let duration = get_video_duration(path).await?;
-
-                let dism_probe = Command::new("ffprobe")
-                    .arg("-v")
-                    .arg("error")
-                    .arg("-f")
-                    .arg(map_ffmpeg_mime(&mime))
-                    .arg("-select_streams")
-                    .arg("v:0")
-                    .arg("-show_entries")
-                    .arg("stream=width,height")
-                    .arg("-of")
-                    .arg("csv=s=x:p=0")
-                    .arg("-loglevel")
-                    .arg("quiet")
-                    .arg(path)
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::inherit())
-                    .spawn()?;
-
-                let dims_output = dism_probe.wait_with_output().await?;
-
-                let dims = Self::parse_output(dims_output.stdout)?;
-                Ok(MediaMetadata::Video {
-                    dims,
-                    duration: duration as i32,
-                    video_encoding: get_video_codec(path).await?,
-                })
-            }
-            MetadataDigest::PdfFile { mime, path } => {
-                let command = Command::new("pdfinfo")
-                    .arg(path)
-                    .stdout(Stdio::piped())
-                    .stderr(Stdio::inherit())
-                    .spawn()?;
-
-                let output = command.wait_with_output().await?;
-                let output_str = String::from_utf8(output.stdout)
-                    .map_err(|_| MediaImportError::UnexpectedOutput)?;
-
-                Self::parse_pdf_info(output_str.as_str())
-            }
-            MetadataDigest::MiscDoc { mime, path } => {
-                let mut unoconv = Command::new("unoconv")
-                    .arg("--stdout")
-                    .arg("-f")
-                    .arg("pdf")
-                    .arg(path)
-                    .stderr(Stdio::inherit())
-                    .stdout(Stdio::piped())
-                    .spawn()?;
-
-                let mut pdfinfo = Command::new("pdfinfo")
-                    .arg("-")
-                    .stderr(Stdio::inherit())
-                    .stdout(Stdio::piped())
-                    .stdin(Stdio::piped())
-                    .spawn()?;
-
-                pipe_commands(&mut unoconv, &mut pdfinfo).await?;
-
-                let output = pdfinfo.wait_with_output().await?;
-                unoconv.wait().await?;
-
-                let output_str = String::from_utf8(output.stdout)
-                    .map_err(|_| MediaImportError::UnexpectedOutput)?;
-
-                Self::parse_pdf_info(output_str.as_str())
+        let tag = || Some(String::from(value));
+
+        match key.as_str() {
+            "duration" => duration = value.parse::<f32>().ok(),
+            "bit_rate" => bitrate = value.parse::<i32>().ok(),
+            "sample_rate" => sample_rate = value.parse::<i32>().ok(),
+            "tag:artist" => artist = artist.or_else(tag),
+            "tag:album" => album = album.or_else(tag),
+            "tag:title" => title = title.or_else(tag),
+            // the track can be written as `3/12`
+            "tag:track" | "tag:tracknumber" => {
+                track = track.or_else(|| value.split('/').next()?.trim().parse::<i32>().ok())
             }
+            _ => {}
         }
     }
 
-    fn parse_pdf_info(output_str: &str) -> Result<MediaMetadata, MediaImportError> {
-        let mut pages = None;
-        let mut author = None;
-        let mut title = None;
-        let mut page_size = None;
-
-        for line in output_str.lines() {
-            if let Some((key, value)) = line.split_once(':') {
-                let key = key.trim();
-                let value = value.trim();
-
-                match key {
-                    "Title" => title = Some(String::from(value)),
-                    "Author" => author = Some(String::from(value)),
-                    "Pages" => pages = value.parse::<i32>().ok(),
-                    "Page size" => {
-                        if let Some((mut w, mut h)) = value.split_once('x') {
-                            w = w.trim();
+    let duration = duration.ok_or(MediaImportError::UnexpectedOutput)?;
+
+    Ok(MediaMetadata::Audio {
+        duration: duration as i32,
+        bitrate,
+        sample_rate,
+        artist,
+        album,
+        title,
+        track,
+    })
+}
+
+fn parse_pdf_info(output_str: &str) -> Result<MediaMetadata, MediaImportError> {
+    let mut pages = None;
+    let mut author = None;
+    let mut title = None;
+    let mut page_size = None;
+
+    for line in output_str.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            let key = key.trim();
+            let value = value.trim();
+
+            match key {
+                "Title" => title = Some(String::from(value)),
+                "Author" => author = Some(String::from(value)),
+                "Pages" => pages = value.parse::<i32>().ok(),
+                "Page size" => {
+                    if let Some((mut w, mut h)) = value.split_once('x') {
+                        w = w.trim();
+                        h = h.trim();
+
+                        if let Some(mut h) = h.split(' ').next() {
                             h = h.trim();
 
-                            if let Some(mut h) = h.split(' ').next() {
-                                h = h.trim();
-
-                                if let (Some(w), Some(h)) =
-                                    (w.parse::<f32>().ok(), h.parse::<f32>().ok())
-                                {
-                                    page_size = Some(Dimensions {
-                                        width: w as i32,
-                                        height: h as i32,
-                                    })
-                                }
+                            if let (Some(w), Some(h)) =
+                                (w.parse::<f32>().ok(), h.parse::<f32>().ok())
+                            {
+                                page_size = Some(Dimensions {
+                                    width: w as i32,
+                                    height: h as i32,
+                                })
                             }
                         }
                     }
-                    _ => {}
                 }
+                _ => {}
             }
         }
+    }
 
-        match (pages, page_size) {
-            (Some(pages), Some(page_size)) => Ok(MediaMetadata::Document {
-                author,
-                pages,
-                title,
-                page_size,
-            }),
-            _ => Err(MediaImportError::UnexpectedOutput),
-        }
+    match (pages, page_size) {
+        (Some(pages), Some(page_size)) => Ok(MediaMetadata::Document {
+            author,
+            pages,
+            title,
+            page_size,
+        }),
+        _ => Err(MediaImportError::UnexpectedOutput),
     }
 }
 
@@ -649,3 +1706,229 @@ async fn get_video_duration(path: &Path) -> Result<f32, MediaImportError> {
 
     Ok(duration)
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_exif() {
+        let exif = parse_exif(
+            "Canon\tCanon EOS 5D\tEF50mm f/1.8\t0.004\t1.8\t400\t50\t6\t2023:05:01 12:30:00\t-\t+02:00\t-\t-\n",
+        );
+
+        assert_eq!(Some("Canon".to_string()), exif.camera_make);
+        assert_eq!(Some("Canon EOS 5D".to_string()), exif.camera_model);
+        assert_eq!(Some("EF50mm f/1.8".to_string()), exif.lens);
+        assert_eq!(Some(0.004), exif.exposure_time);
+        assert_eq!(Some(1.8), exif.f_number);
+        assert_eq!(Some(400), exif.iso);
+        assert_eq!(Some(50.0), exif.focal_length);
+        assert_eq!(Some(6), exif.orientation);
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2023, 5, 1, 10, 30, 0).unwrap()),
+            exif.taken_at
+        );
+    }
+
+    #[test]
+    fn parse_exif_without_tags() {
+        let exif = parse_exif("-\t-\t-\t-\t-\t-\t-\t-\t-\t-\t-\t-\t-\n");
+
+        assert_eq!(None, exif.camera_make);
+        assert_eq!(None, exif.iso);
+        assert_eq!(None, exif.taken_at);
+
+        let exif = parse_exif("");
+
+        assert_eq!(None, exif.camera_make);
+        assert_eq!(None, exif.orientation);
+    }
+
+    #[test]
+    fn parse_exif_falls_back_to_create_date() {
+        let exif = parse_exif("-\t-\t-\t-\t-\t-\t-\t-\t-\t2021:01:02 03:04:05\t-\t-\t-");
+
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2021, 1, 2, 3, 4, 5).unwrap()),
+            exif.taken_at
+        );
+    }
+
+    #[test]
+    fn test_parse_exif_date() {
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2023, 5, 1, 12, 30, 0).unwrap()),
+            parse_exif_date("2023:05:01 12:30:00", None)
+        );
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2023, 5, 1, 10, 30, 0).unwrap()),
+            parse_exif_date("2023:05:01 12:30:00", Some("+02:00"))
+        );
+
+        // the zone of an XMP date takes precedence over the offset tag
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2023, 5, 1, 17, 30, 0).unwrap()),
+            parse_exif_date("2023:05:01 12:30:00.25-05:00", Some("+02:00"))
+        );
+        assert_eq!(
+            Some(Utc.with_ymd_and_hms(2023, 5, 1, 12, 30, 0).unwrap()),
+            parse_exif_date("2023:05:01 12:30:00Z", None)
+        );
+    }
+
+    #[test]
+    fn test_parse_exif_date_invalid() {
+        assert_eq!(None, parse_exif_date("0000:00:00 00:00:00", None));
+        assert_eq!(None, parse_exif_date("2023:05:01", None));
+        assert_eq!(None, parse_exif_date("2023:05:01 12:30:00", Some("local")));
+    }
+
+    #[test]
+    fn parse_exif_location() {
+        let exif = parse_exif("-\t-\t-\t-\t-\t-\t-\t-\t-\t-\t-\t-33.8568\t151.2153\n");
+
+        assert_eq!(Some(-33.8568), exif.latitude);
+        assert_eq!(Some(151.2153), exif.longitude);
+    }
+
+    #[test]
+    fn parse_exif_ignores_invalid_location() {
+        let exif = parse_exif("-\t-\t-\t-\t-\t-\t-\t-\t-\t-\t-\t91\t181");
+
+        assert_eq!(None, exif.latitude);
+        assert_eq!(None, exif.longitude);
+    }
+
+    #[test]
+    fn test_parse_audio_probe() {
+        let output = "duration=215.483000\nbit_rate=320000\nsample_rate=44100\nTAG:ARTIST=Artist\nTAG:artist=Other\nTAG:Album=Album\nTAG:title=Title\nTAG:track=3/12\n";
+
+        let MediaMetadata::Audio {
+            duration,
+            bitrate,
+            sample_rate,
+            artist,
+            album,
+            title,
+            track,
+        } = parse_audio_probe(output).unwrap()
+        else {
+            panic!("expected audio metadata");
+        };
+
+        assert_eq!(215, duration);
+        assert_eq!(Some(320000), bitrate);
+        assert_eq!(Some(44100), sample_rate);
+        assert_eq!(Some(String::from("Artist")), artist);
+        assert_eq!(Some(String::from("Album")), album);
+        assert_eq!(Some(String::from("Title")), title);
+        assert_eq!(Some(3), track);
+    }
+
+    #[test]
+    fn parse_audio_probe_missing_values() {
+        let MediaMetadata::Audio {
+            bitrate,
+            artist,
+            track,
+            ..
+        } = parse_audio_probe("duration=12.5\nbit_rate=N/A\nTAG:artist=\nTAG:tracknumber=x")
+            .unwrap()
+        else {
+            panic!("expected audio metadata");
+        };
+
+        assert_eq!(None, bitrate);
+        assert_eq!(None, artist);
+        assert_eq!(None, track);
+        assert!(parse_audio_probe("bit_rate=320000").is_err());
+    }
+
+    #[test]
+    fn test_parse_frame_delays() {
+        let animation = parse_frame_delays("4\n0\n1\n\n25\n").unwrap().unwrap();
+
+        assert_eq!(4, animation.frame_count);
+        assert_eq!(40 + 100 + 100 + 250, animation.loop_duration_ms);
+    }
+
+    #[test]
+    fn parse_frame_delays_single_frame() {
+        assert!(parse_frame_delays("").unwrap().is_none());
+        assert!(parse_frame_delays("10\n").unwrap().is_none());
+        assert!(parse_frame_delays("10\nten\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_hls_playlist() {
+        let playlist = "#EXTM3U\n#EXT-X-VERSION:3\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:0\n#EXTINF:6.006000,\nsegment0.ts\n#EXTINF:3.5,\n  segment1.ts  \n#EXT-X-ENDLIST\n";
+
+        assert_eq!(
+            vec![(6.006, "segment0.ts"), (3.5, "segment1.ts")],
+            parse_hls_playlist(playlist)
+        );
+    }
+
+    #[test]
+    fn parse_hls_playlist_skips_segments_without_duration() {
+        let playlist =
+            "#EXTM3U\nsegment0.ts\n#EXTINF:invalid,\nsegment1.ts\n#EXTINF:2,\nsegment2.ts";
+
+        assert_eq!(vec![(2.0, "segment2.ts")], parse_hls_playlist(playlist));
+    }
+
+    #[test]
+    fn test_storyboard_layout() {
+        assert_eq!((10, 1, 1), storyboard_layout(0));
+        assert_eq!((10, 1, 1), storyboard_layout(10));
+        assert_eq!((10, 5, 5), storyboard_layout(45));
+        assert_eq!((10, 10, 10), storyboard_layout(95));
+        assert_eq!((10, 10, 100), storyboard_layout(1000));
+    }
+
+    #[test]
+    fn storyboard_layout_long_video() {
+        // long videos take frames further apart to stay within the frame limit
+        assert_eq!((20, 10, 100), storyboard_layout(2000));
+        assert_eq!((21, 10, 96), storyboard_layout(2001));
+        assert_eq!((36, 10, 100), storyboard_layout(3600));
+    }
+
+    async fn is_apng_file(bytes: &[u8]) -> bool {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), bytes).unwrap();
+
+        is_apng(file.path()).await.unwrap()
+    }
+
+    /// A png chunk of `length` zero bytes with its crc.
+    fn png_chunk(length: u32, chunk_type: &[u8; 4]) -> Vec<u8> {
+        let mut chunk = length.to_be_bytes().to_vec();
+        chunk.extend_from_slice(chunk_type);
+        chunk.extend(vec![0; length as usize + 4]);
+
+        chunk
+    }
+
+    #[tokio::test]
+    async fn test_is_apng() {
+        let signature = b"\x89PNG\r\n\x1a\n".to_vec();
+        let header = png_chunk(13, b"IHDR");
+
+        let animated = [signature.clone(), header.clone(), png_chunk(8, b"acTL")].concat();
+        let still = [signature, header, png_chunk(4, b"IDAT")].concat();
+
+        assert!(is_apng_file(&animated).await);
+        assert!(!is_apng_file(&still).await);
+    }
+
+    #[tokio::test]
+    async fn is_apng_short_file() {
+        assert!(!is_apng_file(b"").await);
+        assert!(!is_apng_file(b"\x89PNG").await);
+        assert!(!is_apng_file(b"\x89PNG\r\n\x1a\n\0\0\0\x0dIH").await);
+    }
+}
@@ -5,6 +5,7 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::time::Duration;
 use url::Url;
 use uuid::Uuid;
@@ -78,10 +79,36 @@ pub struct Page<T> {
     pub data: Vec<T>,
 }
 
-#[derive(Debug, Serialize, Clone, Deserialize)]
+/// Where the posts of an import batch came from.
+#[derive(Default, Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub enum ImportBatchOrigin {
+    Web,
+    Sync,
+    #[default]
+    Unknown,
+}
+
+#[derive(Debug, Default, Serialize, Clone, Deserialize)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
 pub struct ImportBatch {
     pub id: u64,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub origin: ImportBatchOrigin,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the last post was added to the batch.
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct SearchImportBatch {
+    pub batch: ImportBatch,
+    pub post_count: u64,
 }
 
 impl ManyToOne<u64, ImportBatch> {
@@ -100,11 +127,57 @@ pub struct Dimensions {
     pub height: i32,
 }
 
+/// The EXIF and XMP metadata of a photo, each value is `None` if the camera did not record it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct ExifMetadata {
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens: Option<String>,
+    /// The exposure time in seconds.
+    pub exposure_time: Option<f64>,
+    pub f_number: Option<f64>,
+    pub iso: Option<i32>,
+    /// The focal length in millimeters.
+    pub focal_length: Option<f64>,
+    /// The EXIF orientation, 1 is upright and 2 to 8 are mirrored and/or rotated.
+    pub orientation: Option<i32>,
+    /// When the photo was taken.
+    pub taken_at: Option<DateTime<Utc>>,
+    /// Where the photo was taken, in degrees north.
+    #[serde(default)]
+    pub latitude: Option<f64>,
+    /// Where the photo was taken, in degrees east.
+    #[serde(default)]
+    pub longitude: Option<f64>,
+}
+
+/// The frames of an animated GIF, WebP or PNG.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct Animation {
+    pub frame_count: i32,
+    /// How long it takes to play all frames once.
+    pub loop_duration_ms: i32,
+}
+
+impl Animation {
+    /// The loop duration in whole seconds, rounded up like the duration of a video.
+    pub fn duration(&self) -> i32 {
+        (self.loop_duration_ms + 999) / 1000
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
 pub enum MediaMetadata {
     Image {
         dims: Dimensions,
+        #[serde(default)]
+        exif: ExifMetadata,
+        /// `None` if the image is not animated.
+        #[serde(default)]
+        animation: Option<Animation>,
     },
     Video {
         dims: Dimensions,
@@ -117,6 +190,16 @@ pub enum MediaMetadata {
         author: Option<String>,
         page_size: Dimensions,
     },
+    Audio {
+        duration: i32,
+        /// The bitrate in bits per second.
+        bitrate: Option<i32>,
+        sample_rate: Option<i32>,
+        artist: Option<String>,
+        album: Option<String>,
+        title: Option<String>,
+        track: Option<i32>,
+    },
     Unknown,
 }
 
@@ -130,21 +213,49 @@ impl MediaMetadata {
 
     pub fn width(&self) -> Option<&i32> {
         match self {
-            MediaMetadata::Image { dims } | MediaMetadata::Video { dims, .. } => Some(&dims.width),
+            MediaMetadata::Image { dims, .. } | MediaMetadata::Video { dims, .. } => {
+                Some(&dims.width)
+            }
             _ => None,
         }
     }
 
     pub fn height(&self) -> Option<&i32> {
         match self {
-            MediaMetadata::Image { dims } | MediaMetadata::Video { dims, .. } => Some(&dims.height),
+            MediaMetadata::Image { dims, .. } | MediaMetadata::Video { dims, .. } => {
+                Some(&dims.height)
+            }
+            _ => None,
+        }
+    }
+
+    /// The duration in seconds, animated images are played like videos.
+    pub fn duration(&self) -> Option<i32> {
+        match self {
+            MediaMetadata::Video { duration, .. } | MediaMetadata::Audio { duration, .. } => {
+                Some(*duration)
+            }
+            MediaMetadata::Image {
+                animation: Some(animation),
+                ..
+            } => Some(animation.duration()),
+            _ => None,
+        }
+    }
+
+    pub fn animation(&self) -> Option<&Animation> {
+        match self {
+            MediaMetadata::Image { animation, .. } => animation.as_ref(),
             _ => None,
         }
     }
 
-    pub fn duration(&self) -> Option<&i32> {
+    /// The number of frames of an image, 1 if it is not animated.
+    pub fn frame_count(&self) -> Option<i32> {
         match self {
-            MediaMetadata::Video { duration, .. } => Some(duration),
+            MediaMetadata::Image { animation, .. } => {
+                Some(animation.as_ref().map_or(1, |a| a.frame_count))
+            }
             _ => None,
         }
     }
@@ -176,6 +287,56 @@ impl MediaMetadata {
             _ => None,
         }
     }
+
+    pub fn bitrate(&self) -> Option<&i32> {
+        match self {
+            MediaMetadata::Audio { bitrate, .. } => bitrate.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn sample_rate(&self) -> Option<&i32> {
+        match self {
+            MediaMetadata::Audio { sample_rate, .. } => sample_rate.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn artist(&self) -> Option<&str> {
+        match self {
+            MediaMetadata::Audio { artist, .. } => artist.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn album(&self) -> Option<&str> {
+        match self {
+            MediaMetadata::Audio { album, .. } => album.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The title of an audio file, the title of a document is returned by `title`.
+    pub fn audio_title(&self) -> Option<&str> {
+        match self {
+            MediaMetadata::Audio { title, .. } => title.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn track(&self) -> Option<&i32> {
+        match self {
+            MediaMetadata::Audio { track, .. } => track.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn exif(&self) -> Option<&ExifMetadata> {
+        match self {
+            MediaMetadata::Image { exif, .. } => Some(exif),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -192,11 +353,147 @@ pub struct Media {
     pub mime: ::mediatype::MediaTypeBuf,
 }
 
+/// A sprite sheet of frames taken from a video at a fixed interval,
+/// shown as previews while scrubbing through it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Storyboard {
+    pub sprite: ManyToOne<u64, Media>,
+    /// The number of seconds between two frames.
+    pub interval: i32,
+    /// The size of a single frame in the sprite.
+    pub tile: Dimensions,
+    /// The number of frames in a row of the sprite.
+    pub columns: i32,
+    pub frame_count: i32,
+}
+
+impl Storyboard {
+    /// The position of a frame in the sprite.
+    pub fn frame_offset(&self, frame: i32) -> (i32, i32) {
+        (
+            frame % self.columns * self.tile.width,
+            frame / self.columns * self.tile.height,
+        )
+    }
+}
+
+/// How an image is fit into the requested width and height when it is resized.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub enum ResizeFit {
+    /// Scale the image to fit inside, keeping its aspect ratio.
+    #[default]
+    Contain,
+
+    /// Scale the image to cover the whole size, keeping its aspect ratio and cropping the rest.
+    Cover,
+
+    /// Stretch the image to exactly the size.
+    Fill,
+}
+
+impl ResizeFit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResizeFit::Contain => "contain",
+            ResizeFit::Cover => "cover",
+            ResizeFit::Fill => "fill",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub enum ResizeFormat {
+    #[default]
+    Jpeg,
+    Webp,
+}
+
+impl ResizeFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResizeFormat::Jpeg => "jpeg",
+            ResizeFormat::Webp => "webp",
+        }
+    }
+
+    pub fn mime(&self) -> ::mediatype::MediaTypeBuf {
+        let subty = match self {
+            ResizeFormat::Jpeg => ::mediatype::names::JPEG,
+            ResizeFormat::Webp => ::mediatype::names::WEBP,
+        };
+
+        ::mediatype::MediaTypeBuf::new(::mediatype::names::IMAGE, subty)
+    }
+}
+
+/// The size and format an image is resized to, a width or height of 0 is not constrained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeParams {
+    pub width: u32,
+    pub height: u32,
+    pub fit: ResizeFit,
+    pub format: ResizeFormat,
+}
+
+impl ResizeParams {
+    /// The largest width or height an image can be resized to.
+    pub const MAX_DIMENSION: u32 = 4096;
+}
+
+/// An image that has been resized on demand, cached as a blob.
+#[derive(Debug, Clone)]
+pub struct ResizedMedia {
+    pub media_id: u64,
+    pub params: ResizeParams,
+    pub file_id: Uuid,
+    pub file_size: usize,
+}
+
+/// A quality of a video that has been cut into segments for HLS streaming.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HlsVariant {
+    pub media_id: u64,
+    /// The position in the master playlist, variant 0 has the highest quality.
+    pub variant: i32,
+    pub dims: Dimensions,
+    /// The peak bitrate of a segment in bits per second.
+    pub bandwidth: u64,
+    /// The average bitrate of all segments in bits per second.
+    pub average_bandwidth: u64,
+}
+
+/// A segment of a [`HlsVariant`], stored as a blob.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HlsSegment {
+    pub position: i32,
+    /// The duration in seconds.
+    pub duration: f64,
+    pub file_id: Uuid,
+    pub file_size: usize,
+}
+
+/// Where the searchable text of a media file was extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaTextKind {
+    /// The text layer of a pdf or office document.
+    Document,
+
+    /// Text recognised in an image or a scanned pdf.
+    Ocr,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
 pub struct Content {
     pub content: ManyToOne<u64, Media>,
     pub thumbnail: ManyToOne<u64, Media>,
+    /// A copy of a video that browsers can play, if the original cannot be played.
+    #[serde(default)]
+    pub rendition: Option<ManyToOne<u64, Media>>,
 }
 
 impl ManyToOne<u64, Content> {
@@ -226,6 +523,18 @@ pub struct Post {
     pub description: Option<String>,
     pub import_batch: ManyToOne<u64, ImportBatch>,
     pub created_at: DateTime<Utc>,
+    /// When the post was moved to the trash, `None` if it is not in the trash.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// A rating from 0 to `Post::MAX_RATING`, `None` if the post is not rated.
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub favourite: bool,
+}
+
+impl Post {
+    pub const MAX_RATING: u8 = 5;
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -271,6 +580,18 @@ pub struct SearchTag {
     pub linked_posts: u64,
 }
 
+/// A tag that is often used together with one or more other tags.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct RelatedTag {
+    pub tag: SearchTag,
+    /// The number of posts that have this tag together with one of the other tags.
+    pub count: u64,
+    /// How many times more often the tags occur together than if they were unrelated,
+    /// averaged over the other tags.
+    pub lift: f32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
 pub struct TagDetail {
@@ -279,12 +600,73 @@ pub struct TagDetail {
 
 // Joins
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CustomFieldType {
+    String,
+    Number,
+    Date,
+    Url,
+}
+
+/// A user defined field that posts of the bucket can have a value for.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct CustomField {
+    pub id: u64,
+    pub name: String,
+    pub field_type: CustomFieldType,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CustomField {
+    /// Names may only contain letters, digits, `_` and `-` so they can be used in search queries.
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum CustomFieldValue {
+    String(String),
+    Number(f64),
+    Date(DateTime<Utc>),
+    Url(Url),
+}
+
+impl CustomFieldValue {
+    pub fn field_type(&self) -> CustomFieldType {
+        match self {
+            CustomFieldValue::String(_) => CustomFieldType::String,
+            CustomFieldValue::Number(_) => CustomFieldType::Number,
+            CustomFieldValue::Date(_) => CustomFieldType::Date,
+            CustomFieldValue::Url(_) => CustomFieldType::Url,
+        }
+    }
+}
+
+/// The value of a custom field on a post.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct PostCustomField {
+    pub field: CustomField,
+    pub value: CustomFieldValue,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
 pub struct PostDetail {
     pub post: Post,
     pub tags: Vec<SearchTag>,
     pub item_count: usize,
+    #[serde(default)]
+    pub custom_fields: Vec<PostCustomField>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -295,10 +677,47 @@ pub struct SearchPost {
     pub contains_video: bool,
     pub contains_moving_image: bool,
     pub contains_document: bool,
+    #[serde(default)]
+    pub contains_audio: bool,
     pub item_count: usize,
     pub duration: Option<i32>,
     pub thumbnail: Option<Media>,
     pub file_name: Option<String>,
+    /// A HTML-escaped fragment of the matched text with the matches wrapped in `<mark>`, only set for text searches.
+    #[serde(default)]
+    pub snippet: Option<String>,
+    /// The columns that matched a text search, only set if highlights were requested.
+    #[serde(default)]
+    pub highlights: Vec<SearchHighlight>,
+}
+
+/// A column of a post that is searched by text searches.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum PostSearchColumn {
+    Source,
+    Title,
+    Description,
+    Tags,
+    OriginalName,
+    OriginalDirectory,
+    DocumentTitle,
+    DocumentAuthor,
+    /// The extracted document and ocr text of the items.
+    ContentText,
+    AudioArtist,
+    AudioAlbum,
+    AudioTitle,
+}
+
+/// A column that matched a text search with the matches marked.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct SearchHighlight {
+    pub column: PostSearchColumn,
+    /// The whole column, or a fragment of it for long texts, HTML-escaped with the matches wrapped in `<mark>`.
+    pub fragment: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -309,8 +728,21 @@ pub struct SearchPostItem {
     pub contains_video: bool,
     pub contains_moving_image: bool,
     pub contains_document: bool,
+    #[serde(default)]
+    pub contains_audio: bool,
     pub duration: Option<i32>,
     pub thumbnail: Option<Media>,
+    #[serde(default)]
+    pub progress: Option<WatchProgress>,
+}
+
+/// Where playback of a post item was left off.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct WatchProgress {
+    /// The playback position in seconds.
+    pub position: f64,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -331,6 +763,16 @@ pub struct CreateFullPost {
     pub tag_ids: Vec<u64>,
     pub flatten: bool,
     pub batch_id: Option<u64>,
+    /// The name of the new batch if `batch_id` is `None`.
+    #[serde(default)]
+    pub batch_name: Option<String>,
+    /// The origin of the new batch if `batch_id` is `None`.
+    #[serde(default)]
+    pub batch_origin: ImportBatchOrigin,
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub favourite: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
@@ -340,6 +782,10 @@ pub enum PostSearchQueryOrder {
     Oldest,
     Relevant,
     Random(f32),
+    /// Highest rated first, unrated posts last.
+    Rating,
+    /// Most recently watched first, unwatched posts last.
+    RecentlyWatched,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -350,11 +796,169 @@ pub struct PostSearchQuery {
     pub source: Option<String>,
     pub order: Option<PostSearchQueryOrder>,
     pub require_playable: bool,
+    /// Search the trash instead of the posts that are not deleted.
+    #[serde(default)]
+    pub trashed: bool,
+    #[serde(default)]
+    pub batch_id: Option<u64>,
+    /// Only include posts rated at least this high.
+    #[serde(default)]
+    pub min_rating: Option<u8>,
+    /// Only include posts rated at most this high.
+    #[serde(default)]
+    pub max_rating: Option<u8>,
+    #[serde(default)]
+    pub favourite: Option<bool>,
+    /// Only include posts with an item that was started but not watched until the end.
+    #[serde(default)]
+    pub in_progress: bool,
+    /// Return which columns matched the text of the search.
+    #[serde(default)]
+    pub highlight: bool,
+    /// Only include posts whose custom fields match all of these predicates.
+    #[serde(default)]
+    pub fields: Vec<CustomFieldPredicate>,
+    /// Only include posts with a photo whose camera make or model contains this text.
+    #[serde(default)]
+    pub camera: Option<String>,
+    /// Only include posts with a photo whose lens contains this text.
+    #[serde(default)]
+    pub lens: Option<String>,
+    #[serde(default)]
+    pub min_iso: Option<i32>,
+    #[serde(default)]
+    pub max_iso: Option<i32>,
+    /// Only include posts with a photo taken at or after this time.
+    #[serde(default)]
+    pub taken_after: Option<DateTime<Utc>>,
+    /// Only include posts with a photo taken at or before this time.
+    #[serde(default)]
+    pub taken_before: Option<DateTime<Utc>>,
+    /// Only include posts with a photo taken within this area.
+    #[serde(default)]
+    pub bounds: Option<GeoBounds>,
+    /// Only include posts with a photo taken within a distance of this point.
+    #[serde(default)]
+    pub near: Option<GeoRadius>,
+}
+
+/// An area on the map in degrees, `west` is greater than `east` if it crosses the antimeridian.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct GeoBounds {
+    pub south: f64,
+    pub west: f64,
+    pub north: f64,
+    pub east: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct GeoRadius {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// The distance from the point in meters.
+    pub radius: f64,
+}
+
+/// Posts that were taken close to each other, to show them as one point on a map.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct GeoCluster {
+    /// The average location of the photos in the cluster.
+    pub latitude: f64,
+    pub longitude: f64,
+    pub post_count: u64,
+    /// One of the posts in the cluster, e.g. to show its thumbnail.
+    pub post_id: u64,
+}
+
+impl GeoCluster {
+    /// The highest zoom level that posts are clustered for.
+    pub const MAX_ZOOM: u8 = 24;
+
+    /// The size in degrees of the cells that posts are clustered into at a map zoom level,
+    /// which is a quarter of a map tile, the whole world is a single tile at level 0.
+    pub fn cell_size(zoom: u8) -> f64 {
+        360.0 / 2_f64.powi(zoom.min(Self::MAX_ZOOM) as i32) / 4.0
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CustomFieldOperator {
+    /// The post has any value for the field.
+    Exists,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CustomFieldOperator {
+    /// The operators in the order they have to be matched when parsing, `>=` before `>`.
+    pub const ALL: [CustomFieldOperator; 6] = [
+        CustomFieldOperator::Ne,
+        CustomFieldOperator::Le,
+        CustomFieldOperator::Ge,
+        CustomFieldOperator::Eq,
+        CustomFieldOperator::Lt,
+        CustomFieldOperator::Gt,
+    ];
+
+    /// The comparison operator as it is written in search queries and SQL.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            CustomFieldOperator::Exists => "",
+            CustomFieldOperator::Eq => "=",
+            CustomFieldOperator::Ne => "!=",
+            CustomFieldOperator::Lt => "<",
+            CustomFieldOperator::Le => "<=",
+            CustomFieldOperator::Gt => ">",
+            CustomFieldOperator::Ge => ">=",
+        }
+    }
+}
+
+/// A condition on the value of a custom field, e.g. `episode >= 3`.
+/// The value is compared as a number for number fields, as a time for date fields and as text otherwise.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct CustomFieldPredicate {
+    pub name: String,
+    pub operator: CustomFieldOperator,
+    pub value: Option<String>,
+    /// The first instant of the value if it is a date or time, date fields never match if it is not.
+    #[serde(default)]
+    pub date_start: Option<DateTime<Utc>>,
+    /// The last instant of the value, a date is equal to every time of its day.
+    #[serde(default)]
+    pub date_end: Option<DateTime<Utc>>,
 }
 
 impl PostSearchQuery {
     pub fn has_criteria(&self) -> bool {
-        self.tags.is_some() || self.text.is_some() || self.source.is_some()
+        self.tags.is_some()
+            || self.text.is_some()
+            || self.source.is_some()
+            || self.require_playable
+            || self.batch_id.is_some()
+            || self.min_rating.is_some()
+            || self.max_rating.is_some()
+            || self.favourite.is_some()
+            || self.in_progress
+            || !self.fields.is_empty()
+            || self.camera.is_some()
+            || self.lens.is_some()
+            || self.min_iso.is_some()
+            || self.max_iso.is_some()
+            || self.taken_after.is_some()
+            || self.taken_before.is_some()
+            || self.bounds.is_some()
+            || self.near.is_some()
     }
 }
 
@@ -364,10 +968,25 @@ pub struct PostItemSearchQuery {
     pub require_playable: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
 pub enum GraphValue {
     Date(DateTime<Utc>),
+    Tag {
+        id: u64,
+        name: String,
+    },
+    TagGroup {
+        id: u64,
+        name: String,
+    },
+    MimeType(String),
+    ImportBatch {
+        id: u64,
+        name: Option<String>,
+    },
+    /// The lower bound of a dimension bucket in pixels.
+    Dimension(u32),
     None,
 }
 
@@ -380,21 +999,46 @@ pub struct GraphPoint {
 
 #[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
-pub struct Graph {
+pub struct GraphSeries {
+    pub select: GraphSelect,
     pub points: Vec<GraphPoint>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct Graph {
+    /// One series per selection of the query, in the same order.
+    pub series: Vec<GraphSeries>,
+}
+
+/// How posts are grouped into the points of a graph.
+///
+/// Groupings over time are cumulative, all other groupings are totals per group.
+/// A post is counted once in every group it belongs to.
 #[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
 pub enum GraphDiscriminator {
     Duration(Duration),
+    Tag,
+    TagGroup,
+    MimeType,
+    ImportBatch,
+    /// Groups by the largest side of the content in buckets of the given size in pixels.
+    Dimension(u32),
     None,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
 pub enum GraphSelect {
+    /// The number of posts.
     Count,
+    /// The number of post items.
+    ItemCount,
+    /// The total size of the content in bytes.
+    FileSize,
+    /// The total duration of the content in seconds.
+    Duration,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -402,7 +1046,81 @@ pub enum GraphSelect {
 pub struct PostGraphQuery {
     pub filter: PostSearchQuery,
     pub discriminator: GraphDiscriminator,
-    pub select: GraphSelect,
+    pub select: Vec<GraphSelect>,
+}
+
+/// Selects content by id and mime type, nothing set selects all content.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct ContentQuery {
+    /// Only select these content ids.
+    #[serde(default)]
+    pub content_ids: Vec<u64>,
+
+    /// Only select content of this type, either a full mime type like `image/png` or only `image`.
+    pub mime: Option<String>,
+
+    /// Only select content with a greater id, content is always selected in the order of its id.
+    #[serde(default)]
+    pub after: u64,
+}
+
+/// How far reprocessing the selected content has come.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct ReprocessProgress {
+    /// The number of selected content when the reprocess started.
+    pub total: u64,
+    pub processed: u64,
+    pub failed: u64,
+
+    /// The id of the last processed content, passed as `after` to resume an interrupted reprocess.
+    pub last_content_id: u64,
+    pub finished: bool,
+}
+
+/// Options that are stored with the bucket itself.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct BucketSettings {
+    /// Recognise the text of imported images and scanned pdfs with `tesseract`.
+    pub ocr: bool,
+    /// Use the time the earliest photo was taken as the creation date of posts imported without one.
+    #[serde(default)]
+    pub use_taken_at: bool,
+    /// Remove the GPS location from photos and videos before they are stored,
+    /// so their location is not kept in the bucket at all.
+    #[serde(default)]
+    pub strip_gps: bool,
+    /// Convert uploaded videos that browsers cannot play to mp4 in the background.
+    #[serde(default)]
+    pub renditions: bool,
+    /// Also segment videos at lower bitrates for HLS streaming, not only at the original quality.
+    #[serde(default)]
+    pub hls_ladder: bool,
+    /// The maximum number of bytes of images resized on demand,
+    /// gc removes the least recently used ones above it.
+    #[serde(default = "BucketSettings::default_resize_cache_size")]
+    pub resize_cache_size: u64,
+}
+
+impl BucketSettings {
+    fn default_resize_cache_size() -> u64 {
+        512 * 1024 * 1024
+    }
+}
+
+impl Default for BucketSettings {
+    fn default() -> Self {
+        Self {
+            ocr: false,
+            use_taken_at: false,
+            strip_gps: false,
+            renditions: false,
+            hls_ladder: false,
+            resize_cache_size: Self::default_resize_cache_size(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -412,3 +1130,142 @@ pub struct BucketDetails {
     pub total_file_size: u64,
     pub file_count: u64,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct BucketStats {
+    /// The stored media per mime type, including thumbnails.
+    pub storage: Vec<MimeTypeStats>,
+    /// The size of the media that is only used as a thumbnail.
+    pub thumbnail_size: u64,
+    pub thumbnail_count: u64,
+    /// The size that would have been stored if media used by multiple post items
+    /// or content was stored once per use.
+    pub deduplication_savings: u64,
+    pub largest_posts: Vec<PostStats>,
+    /// The amount of posts, not including the trash.
+    pub post_count: u64,
+    pub trashed_post_count: u64,
+    pub untagged_post_count: u64,
+    pub posts_without_source: u64,
+    pub tag_count: u64,
+    pub tag_groups: Vec<TagGroupStats>,
+    pub growth: Vec<MonthlyGrowth>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct MimeTypeStats {
+    pub mime_type: String,
+    pub mime_sub_type: String,
+    pub file_count: u64,
+    pub total_file_size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct PostStats {
+    pub post: Post,
+    pub item_count: u64,
+    pub total_file_size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct TagGroupStats {
+    /// `None` for the tags without a group.
+    pub group: Option<TagGroup>,
+    pub tag_count: u64,
+    pub post_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct MonthlyGrowth {
+    /// The month formatted as `YYYY-MM`.
+    pub month: String,
+    pub post_count: u64,
+    pub item_count: u64,
+    pub total_file_size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub enum ChangeEntity {
+    Post,
+    Tag,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub enum ChangeAction {
+    Create,
+    Update,
+    Delete,
+    Revert,
+    Trash,
+    Restore,
+}
+
+/// The fields of a post that are tracked by the change log.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct PostSnapshot {
+    pub source: Option<Url>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tag_ids: Vec<u64>,
+    /// The content ids of the items in order, missing from entries recorded before items were tracked.
+    #[serde(default)]
+    pub item_ids: Vec<u64>,
+    /// The custom field values by field id, missing from entries recorded before they were tracked.
+    #[serde(default)]
+    pub custom_fields: BTreeMap<u64, CustomFieldValue>,
+}
+
+/// The fields of a tag that are tracked by the change log.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct TagSnapshot {
+    pub name: String,
+    pub group_id: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub enum ChangeSnapshot {
+    Post(PostSnapshot),
+    Tag(TagSnapshot),
+}
+
+/// Who made a change. Both fields are empty for changes made outside of an HTTP session (e.g. `mb sync`).
+#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct ChangeOrigin {
+    pub ip: Option<String>,
+    /// A hash identifying the session, never the session token itself.
+    pub session: Option<String>,
+}
+
+/// An entry of the append-only change log.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub struct Change {
+    pub id: u64,
+    pub entity: ChangeEntity,
+    pub entity_id: u64,
+    pub action: ChangeAction,
+    pub previous: Option<ChangeSnapshot>,
+    pub current: Option<ChangeSnapshot>,
+    pub origin: ChangeOrigin,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "http-server-spec", derive(utoipa::ToSchema))]
+pub enum PostField {
+    Source,
+    Title,
+    Description,
+    Tags,
+}
@@ -334,14 +334,14 @@ export class ApiService {
     }
 
     return this.authenticatedPost(auth, `/posts/graph`, {
-      select,
+      select: [select],
       discriminator,
       filter: {
         tags,
         text
       }
     }).pipe(
-      map(json => new ChartSeries(query.name, json.points.map((x: any) => this.mapChartPoint(x))))
+      map(json => new ChartSeries(query.name, json.series[0].points.map((x: any) => this.mapChartPoint(x))))
     )
   }
 